@@ -7,11 +7,15 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also records which build this is for `pico_bites::firmware`.
 
 use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::SystemTime;
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -28,4 +32,101 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+
+    write_slot(out);
+    write_firmware_info(out);
+}
+
+/// Writes `slot.x`, which `memory.x` includes to link the image for slot A
+/// or B of `pico_bites::ota`.
+fn write_slot(out: &std::path::Path) {
+    println!("cargo:rerun-if-env-changed=PICO_BITES_SLOT");
+    let slot = match env::var("PICO_BITES_SLOT").as_deref() {
+        Ok("a" | "A") | Err(_) => "A",
+        Ok("b" | "B") => "B",
+        Ok(other) => panic!("PICO_BITES_SLOT must be `a` or `b`, not `{other}`"),
+    };
+
+    let mut file = File::create(out.join("slot.x")).unwrap();
+    writeln!(file, "REGION_ALIAS(\"BOOT2\", BOOT2_{slot});").unwrap();
+    writeln!(file, "REGION_ALIAS(\"FLASH\", FLASH_{slot});").unwrap();
+}
+
+/// Writes the constants `src/firmware.rs` includes.
+fn write_firmware_info(out: &std::path::Path) {
+    let git_commit = git(&["rev-parse", "--short=12", "HEAD"]);
+    // Only the tracked files count, build output lying around is fine
+    let git_dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .map(|status| !status.is_empty())
+        .unwrap_or(false);
+    let profile = env::var("PROFILE").unwrap();
+    let board = if env::var_os("CARGO_FEATURE_RP_PICO").is_some() {
+        "rp-pico"
+    } else if env::var_os("CARGO_FEATURE_WAVESHARE_RP2040_ZERO").is_some() {
+        "waveshare-rp2040-zero"
+    } else {
+        "none"
+    };
+    let build_date = build_date();
+
+    let mut file = File::create(out.join("firmware_info.rs")).unwrap();
+    writeln!(
+        file,
+        "pub(crate) const GIT_COMMIT: &str = {:?};",
+        git_commit.as_deref().unwrap_or("unknown")
+    )
+    .unwrap();
+    writeln!(file, "pub(crate) const GIT_DIRTY: bool = {git_dirty};").unwrap();
+    writeln!(file, "pub(crate) const PROFILE: &str = {profile:?};").unwrap();
+    writeln!(file, "pub(crate) const BOARD: &str = {board:?};").unwrap();
+    writeln!(file, "pub(crate) const BUILD_DATE: &str = {build_date:?};").unwrap();
+
+    // A commit or a checkout moves `HEAD` or the branch it points to,
+    // staging and editing the sources changes the dirty flag.
+    for path in [".git/HEAD", ".git/index", "src", "examples", "Cargo.toml"] {
+        println!("cargo:rerun-if-changed={path}");
+    }
+    if let Some(head) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        println!("cargo:rerun-if-changed=.git/{head}");
+    }
+}
+
+/// `YYYY-MM-DD` in UTC, `SOURCE_DATE_EPOCH` overrides the clock for
+/// reproducible builds.
+fn build_date() -> String {
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let secs = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse().unwrap(),
+        Err(_) => SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+
+    // Days to the civil date, http://howardhinnant.github.io/date_algorithms.html
+    let z = secs / 86400 + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Runs git in the crate root, `None` outside a checkout or without git.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(env::var_os("CARGO_MANIFEST_DIR").unwrap())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
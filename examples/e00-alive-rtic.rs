@@ -1,10 +1,13 @@
 #![no_main]
 #![no_std]
 
-use defmt_rtt as _;
 use panic_halt as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true)]
+pico_bites::binary_info! {
+    description: "Shows off the minimal RTIC structure",
+}
+
+#[rtic::app(device = rp2040_hal::pac, peripherals = true)]
 mod app {
     use defmt as log;
     use rp2040_hal as hal;
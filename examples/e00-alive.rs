@@ -4,15 +4,19 @@
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
 use panic_halt as _;
 
 use defmt as log;
+use pico_bites::firmware;
 use rp2040_hal as hal;
-use rp_pico as bsp;
 
-#[bsp::entry]
+pico_bites::binary_info! {
+    description: "Shows off the minimal required structure",
+}
+
+#[hal::entry]
 fn main() -> ! {
+    log::info!("Firmware {}", log::Display2Format(&firmware::FIRMWARE_INFO));
     log::info!(
         "Board {}, git revision {:x}, ROM verion {:x}",
         hal::rom_data::copyright_string(),
@@ -1,25 +1,39 @@
 //! Blinks the LED on a Pico board
 //!
 //! This will blink an LED attached to GP25, which is the pin the Pico uses for the on-board LED.
-//! The implementation uses software tasks in RTIC and the busy wait rather than the hardware timer interrupt.
+//! The implementation uses a software task in RTIC scheduled by the monotonic running on the hardware timer.
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
 use panic_probe as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true, dispatchers = [SW0_IRQ])]
+pico_bites::binary_info! {
+    description: "Blinks the LED from an RTIC task",
+    pins: [
+        25 => "LED",
+    ],
+}
+
+#[rtic::app(device = rp2040_hal::pac, peripherals = true, dispatchers = [SW0_IRQ])]
 mod app {
     use defmt as log;
 
-    use rp2040_hal as hal;
-    use rp_pico as bsp;
+    use pico_bites::board;
 
+    use board::hal;
     use embedded_hal::digital::StatefulOutputPin;
     use hal::gpio::FunctionSio;
     use hal::gpio::PullDown;
     use hal::gpio::SioOutput;
-    use hal::Clock;
+    use hal::timer::monotonic::Monotonic;
+    use hal::timer::Alarm0;
+
+    use fugit::MicrosDurationU64;
+
+    const BLINK_PERIOD: MicrosDurationU64 = MicrosDurationU64::millis(500);
+
+    #[monotonic(binds = TIMER_IRQ_0, default = true)]
+    type Mono = Monotonic<Alarm0>;
 
     #[shared]
     struct Shared {}
@@ -27,7 +41,6 @@ mod app {
     #[local]
     struct Local {
         led_pin: hal::gpio::Pin<hal::gpio::bank0::Gpio25, FunctionSio<SioOutput>, PullDown>,
-        delay: cortex_m::delay::Delay,
         led_on: bool,
     }
 
@@ -35,63 +48,38 @@ mod app {
     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         log::info!("RTIC app init");
 
-        let mut pac = cx.device;
-        let sio = bsp::hal::sio::Sio::new(pac.SIO);
-
-        let mut watchdog = bsp::hal::watchdog::Watchdog::new(pac.WATCHDOG);
-        let clocks = bsp::hal::clocks::init_clocks_and_plls(
-            bsp::XOSC_CRYSTAL_FREQ,
-            pac.XOSC,
-            pac.CLOCKS,
-            pac.PLL_SYS,
-            pac.PLL_USB,
-            &mut pac.RESETS,
-            &mut watchdog,
-        )
-        .ok()
-        .unwrap();
-
-        let pins = bsp::Pins::new(
-            pac.IO_BANK0,
-            pac.PADS_BANK0,
-            sio.gpio_bank0,
-            &mut pac.RESETS,
-        );
+        let board::Board {
+            pins, mut timer, ..
+        } = board::Board::new(cx.device, cx.core);
+        let alarm = timer.alarm_0().unwrap();
+
         let led_pin = pins
             .led
             .into_push_pull_output_in_state(hal::gpio::PinState::Low);
 
-        let core = cx.core;
-        let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
-
         blink::spawn().unwrap();
 
         (
             Shared {},
             Local {
                 led_pin,
-                delay,
                 led_on: false,
             },
-            init::Monotonics(),
+            init::Monotonics(Monotonic::new(timer, alarm)),
         )
     }
 
     #[idle]
     fn idle(_: idle::Context) -> ! {
+        log::info!("RTIC idle");
         loop {
-            log::info!("RTIC idle");
-            cortex_m::asm::wfe();
+            cortex_m::asm::wfi();
         }
     }
 
-    #[task(local = [led_pin, delay, led_on])]
+    #[task(local = [led_pin, led_on])]
     fn blink(cx: blink::Context) {
-        let blink::LocalResources {
-            delay,
-            led_pin,
-            led_on,
-        } = cx.local;
+        let blink::LocalResources { led_pin, led_on } = cx.local;
 
         led_pin.toggle().ok();
         *led_on = !*led_on;
@@ -102,7 +90,6 @@ mod app {
             log::info!("tock!");
         }
 
-        delay.delay_ms(500);
-        blink::spawn().unwrap();
+        blink::spawn_after(BLINK_PERIOD).unwrap();
     }
 }
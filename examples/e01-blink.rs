@@ -4,44 +4,28 @@
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
 use panic_probe as _;
 
 use defmt as log;
-use rp_pico as bsp;
+use pico_bites::board;
 
-use bsp::hal::clocks::Clock;
+use board::hal;
 use embedded_hal::digital::OutputPin;
 
-#[bsp::entry]
+pico_bites::binary_info! {
+    description: "Blinks the LED",
+    pins: [
+        25 => "LED",
+    ],
+}
+
+#[hal::entry]
 fn main() -> ! {
     log::info!("Running");
 
-    let mut pac = bsp::hal::pac::Peripherals::take().unwrap();
-    let core = bsp::hal::pac::CorePeripherals::take().unwrap();
-    let mut watchdog = bsp::hal::watchdog::Watchdog::new(pac.WATCHDOG);
-    let sio = bsp::hal::sio::Sio::new(pac.SIO);
-
-    let clocks = bsp::hal::clocks::init_clocks_and_plls(
-        bsp::XOSC_CRYSTAL_FREQ,
-        pac.XOSC,
-        pac.CLOCKS,
-        pac.PLL_SYS,
-        pac.PLL_USB,
-        &mut pac.RESETS,
-        &mut watchdog,
-    )
-    .ok()
-    .unwrap();
-
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
-
-    let pins = bsp::Pins::new(
-        pac.IO_BANK0,
-        pac.PADS_BANK0,
-        sio.gpio_bank0,
-        &mut pac.RESETS,
-    );
+    let board::Board {
+        pins, mut delay, ..
+    } = board::Board::take();
 
     let mut led_pin = pins.led.into_push_pull_output();
 
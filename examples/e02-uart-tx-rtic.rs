@@ -1,40 +1,54 @@
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
 use panic_probe as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true, dispatchers = [SW0_IRQ])]
+pico_bites::binary_info! {
+    description: "Writes a counter to UART1 from an RTIC task",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
+
+#[rtic::app(device = rp2040_hal::pac, peripherals = true, dispatchers = [SW0_IRQ])]
 mod app {
     use core::fmt::Write;
 
     use defmt as log;
 
-    use rp2040_hal as hal;
-    use rp_pico as bsp;
+    use pico_bites::board;
+
+    use board::hal;
 
     use hal::gpio::Pin;
+    use hal::timer::monotonic::Monotonic;
+    use hal::timer::Alarm0;
+    use hal::timer::Instant;
     use hal::uart::UartPeripheral;
     use hal::Clock;
 
+    use fugit::MicrosDurationU64;
     use fugit::RateExtU32;
     use hal::gpio::FunctionUart;
     use hal::gpio::PullDown;
 
+    const SEND_PERIOD: MicrosDurationU64 = MicrosDurationU64::millis(100);
+
+    #[monotonic(binds = TIMER_IRQ_0, default = true)]
+    type Mono = Monotonic<Alarm0>;
+
     #[shared]
     struct Shared {}
 
+    type UartPins = (
+        Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+        Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+    );
+
     #[local]
     struct Local {
-        uart: UartPeripheral<
-            hal::uart::Enabled,
-            bsp::pac::UART1,
-            (
-                Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
-                Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
-            ),
-        >,
-        delay: cortex_m::delay::Delay,
+        uart: UartPeripheral<hal::uart::Enabled, hal::pac::UART1, UartPins>,
         count: u32,
     }
 
@@ -42,30 +56,13 @@ mod app {
     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         log::info!("RTIC app init");
 
-        let mut pac = cx.device;
-        let core = cx.core;
-
-        let sio = bsp::hal::sio::Sio::new(pac.SIO);
-        let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
-        let clocks = hal::clocks::init_clocks_and_plls(
-            bsp::XOSC_CRYSTAL_FREQ,
-            pac.XOSC,
-            pac.CLOCKS,
-            pac.PLL_SYS,
-            pac.PLL_USB,
-            &mut pac.RESETS,
-            &mut watchdog,
-        )
-        .ok()
-        .unwrap();
-
-        let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
-        let pins = hal::gpio::Pins::new(
-            pac.IO_BANK0,
-            pac.PADS_BANK0,
-            sio.gpio_bank0,
-            &mut pac.RESETS,
-        );
+        let board::Board {
+            clocks,
+            pins,
+            mut timer,
+            mut pac,
+            ..
+        } = board::Board::new(cx.device, cx.core);
 
         let uart_pins = (
             pins.gpio8.into_function::<hal::gpio::FunctionUart>(),
@@ -83,37 +80,36 @@ mod app {
             )
             .unwrap();
 
-        send_count::spawn().unwrap();
+        let alarm = timer.alarm_0().unwrap();
+        send_count::spawn(timer.get_counter()).unwrap();
 
         (
             Shared {},
-            Local {
-                uart,
-                delay,
-                count: 0,
-            },
-            init::Monotonics {},
+            Local { uart, count: 0 },
+            init::Monotonics(Monotonic::new(timer, alarm)),
         )
     }
 
     #[idle]
     fn idle(_: idle::Context) -> ! {
+        log::info!("RTIC idle");
         loop {
-            log::info!("RTIC idle");
-            cortex_m::asm::wfe();
+            cortex_m::asm::wfi();
         }
     }
 
-    #[task(local = [uart, delay, count])]
-    fn send_count(cx: send_count::Context) {
-        let send_count::LocalResources { uart, delay, count } = cx.local;
+    /// Runs every `SEND_PERIOD` counting from `scheduled`, so the time spent
+    /// writing to the UART does not add up.
+    #[task(local = [uart, count])]
+    fn send_count(cx: send_count::Context, scheduled: Instant) {
+        let send_count::LocalResources { uart, count } = cx.local;
 
         uart.write_full_blocking(b"Counter: ");
         writeln!(uart, "{count:02}\r").unwrap();
 
-        delay.delay_ms(100);
-
         *count = count.wrapping_add(1);
-        send_count::spawn().unwrap();
+
+        let next = scheduled + SEND_PERIOD;
+        send_count::spawn_at(next, next).unwrap();
     }
 }
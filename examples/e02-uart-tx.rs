@@ -1,70 +1,163 @@
-//! Writes message to UART1
+//! Writes message to UART1 with DMA
+//!
+//! Streams numbered lines without a pause, the CPU only fills the buffers
+//! the DMA is done with. What comes back on RX is counted, a jumper from
+//! GP8 to GP9 loops the stream back, and the rates are logged every second
+//! along with the ends of the bursts.
+//!
+//! At 1 Mbaud unless `uart.baud` is set, see `e07-usb-shell`.
 #![no_std]
 #![no_main]
 
+use core::cell::RefCell;
 use core::fmt::Write;
-use fugit::RateExtU32;
 
-use defmt_rtt as _;
 use panic_halt as _;
 
+use critical_section::Mutex;
 use defmt as log;
-use rp2040_hal as hal;
-use rp_pico as bsp;
+use heapless::Vec;
+use pico_bites::board;
+use pico_bites::settings;
+use pico_bites::uart;
 
+use board::hal;
 use hal::clocks::Clock;
+use hal::dma::DMAExt;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
 
-#[bsp::entry]
+use uart::dma::Buffers;
+
+pico_bites::binary_info! {
+    description: "Writes a counter to UART1 with DMA",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
+
+const BAUD_RATE: u32 = 1_000_000;
+const REPORT_PERIOD_US: u64 = 1_000_000;
+
+const BUFFER_LEN: usize = 256;
+const BUFFERS: usize = 4;
+/// A line of the stream, `Counter: 00000000\r\n`.
+const LINE_LEN: usize = 19;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::dma::Driver<
+    'static,
+    pac::UART1,
+    UartPins,
+    hal::dma::CH0,
+    hal::dma::CH1,
+    BUFFER_LEN,
+    BUFFERS,
+>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+
+#[hal::entry]
 fn main() -> ! {
     log::info!("Running");
 
-    let mut pac = bsp::pac::Peripherals::take().unwrap();
-    let core = bsp::pac::CorePeripherals::take().unwrap();
-    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
-    let clocks = hal::clocks::init_clocks_and_plls(
-        bsp::XOSC_CRYSTAL_FREQ,
-        pac.XOSC,
-        pac.CLOCKS,
-        pac.PLL_SYS,
-        pac.PLL_USB,
-        &mut pac.RESETS,
-        &mut watchdog,
-    )
-    .ok()
-    .unwrap();
-
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
 
-    let sio = hal::Sio::new(pac.SIO);
-    let pins = hal::gpio::Pins::new(
-        pac.IO_BANK0,
-        pac.PADS_BANK0,
-        sio.gpio_bank0,
-        &mut pac.RESETS,
-    );
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(BAUD_RATE));
 
     let uart_pins = (
-        pins.gpio8.into_function::<hal::gpio::FunctionUart>(),
-        pins.gpio9.into_function::<hal::gpio::FunctionUart>(),
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
     );
-    let mut uart = hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS)
-        .enable(
-            hal::uart::UartConfig::new(
-                115200.Hz(),
-                hal::uart::DataBits::Eight,
-                None,
-                hal::uart::StopBits::One,
-            ),
-            clocks.peripheral_clock.freq(),
-        )
-        .unwrap();
+    let channels = pac.DMA.split(&mut pac.RESETS);
+    let buffers = cortex_m::singleton!(: Buffers<BUFFER_LEN, BUFFERS> = Buffers::new()).unwrap();
+    let (driver, mut port) = uart::dma::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        (channels.ch0, channels.ch1),
+        buffers,
+    )
+    .unwrap();
+
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
 
     let mut count = 0u32;
+    // The buffer the queue had no room for yet
+    let mut pending = None;
+    let mut received = [0u8; 64];
+    let (mut sent_bytes, mut received_bytes, mut burst_bytes) = (0, 0, 0);
+    let mut reported_at = timer.get_counter().ticks();
     loop {
-        uart.write_full_blocking(b"Counter: ");
-        writeln!(uart, "{count:02}\r").unwrap();
+        let buffer = pending.take().unwrap_or_else(|| {
+            let mut buffer = Vec::<u8, BUFFER_LEN>::new();
+            while buffer.len() + LINE_LEN <= BUFFER_LEN {
+                write!(buffer, "Counter: {count:08}\r\n").unwrap();
+                count = (count + 1) % 100_000_000;
+            }
+            buffer
+        });
+        let len = buffer.len();
+        match port.send(buffer) {
+            Ok(()) => sent_bytes += len,
+            Err(full) => pending = Some(full),
+        }
 
-        delay.delay_ms(100);
-        count = count.wrapping_add(1);
+        let len = port.read(&mut received);
+        received_bytes += len;
+        burst_bytes += len;
+
+        let now_us = timer.get_counter().ticks();
+        if port.take_idle(now_us) {
+            log::info!("RX idle after {} bytes", burst_bytes);
+            burst_bytes = 0;
+        }
+        if now_us.wrapping_sub(reported_at) >= REPORT_PERIOD_US {
+            reported_at = now_us;
+            log::info!(
+                "Sent {} B/s, received {} B/s, {} bytes lost",
+                sent_bytes,
+                received_bytes,
+                port.lost()
+            );
+            (sent_bytes, received_bytes) = (0, 0);
+        }
     }
 }
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_dma_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_uart_interrupt();
+        }
+    });
+}
@@ -1,38 +1,113 @@
+//! Echoes lines received on UART1 (GPIO8/GPIO9) back to the sender.
+//!
+//! The `UART1_IRQ` hardware task moves bytes between the UART FIFOs and the
+//! ring buffers, idle reads lines out of them and sleeps in between.
 #![no_main]
 #![no_std]
 
-use defmt_rtt as _;
 use panic_halt as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true)]
+pico_bites::binary_info! {
+    description: "Echoes lines on UART1, RTIC",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
+
+#[rtic::app(device = rp2040_hal::pac, peripherals = true)]
 mod app {
+    use core::fmt::Write;
+
     use defmt as log;
-    use rp2040_hal as hal;
+
+    use heapless::spsc::Queue;
+    use pico_bites::board;
+    use pico_bites::line::LineBuffer;
+    use pico_bites::uart;
+
+    use board::hal;
+
+    use hal::gpio::FunctionUart;
+    use hal::gpio::Pin;
+    use hal::gpio::PullDown;
+    use hal::Clock;
+
+    const QUEUE_LEN: usize = 256;
+
+    type UartPins = (
+        Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+        Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+    );
 
     #[shared]
     struct Shared {}
 
     #[local]
-    struct Local {}
-
-    #[init]
-    fn init(_: init::Context) -> (Shared, Local, init::Monotonics) {
-        log::info!(
-            "Board {}, git revision {:x}, ROM verion {:x}",
-            hal::rom_data::copyright_string(),
-            hal::rom_data::git_revision(),
-            hal::rom_data::rom_version_number(),
-        );
+    struct Local {
+        driver: uart::Driver<'static, hal::pac::UART1, UartPins, QUEUE_LEN, QUEUE_LEN>,
+        port: uart::Port<'static, QUEUE_LEN, QUEUE_LEN>,
+    }
+
+    #[init(local = [
+        rx_queue: Queue<u8, QUEUE_LEN> = Queue::new(),
+        tx_queue: Queue<u8, QUEUE_LEN> = Queue::new(),
+    ])]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         log::info!("RTIC app init");
 
-        (Shared {}, Local {}, init::Monotonics())
+        let board::Board {
+            clocks,
+            pins,
+            mut pac,
+            ..
+        } = board::Board::new(cx.device, cx.core);
+
+        let uart_pins = (
+            pins.gpio8.into_function::<FunctionUart>(),
+            pins.gpio9.into_function::<FunctionUart>(),
+        );
+        // RTIC unmasks UART1_IRQ since a task is bound to it.
+        let (driver, port) = uart::split(
+            hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+            uart::Config::new(115200),
+            clocks.peripheral_clock.freq(),
+            hal::pac::Interrupt::UART1_IRQ,
+            cx.local.rx_queue,
+            cx.local.tx_queue,
+        )
+        .unwrap();
+
+        (Shared {}, Local { driver, port }, init::Monotonics())
     }
 
-    #[idle]
-    fn idle(_: idle::Context) -> ! {
+    #[idle(local = [port, line: LineBuffer<128> = LineBuffer::new()])]
+    fn idle(cx: idle::Context) -> ! {
+        let idle::LocalResources { port, line } = cx.local;
+
+        writeln!(port, "Type a line and press Enter\r").unwrap();
+
         loop {
-            log::info!("RTIC idle");
-            cortex_m::asm::wfe();
+            while let Some(text) = port.read_line(line) {
+                log::info!("Received {} bytes", text.len());
+
+                port.write_all(b"> ");
+                port.write_all(text);
+                port.write_all(b"\r\n");
+            }
+
+            // Sleep with the interrupts masked so that a byte arriving right
+            // after the check still wakes the core up.
+            cortex_m::interrupt::free(|_| {
+                if !port.rx_ready() {
+                    cortex_m::asm::wfi();
+                }
+            });
         }
     }
+
+    #[task(binds = UART1_IRQ, local = [driver])]
+    fn uart1_irq(cx: uart1_irq::Context) {
+        cx.local.driver.on_interrupt();
+    }
 }
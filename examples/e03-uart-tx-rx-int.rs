@@ -1,26 +1,128 @@
-//! Shows off the minimal required structure.
+//! Echoes lines received on UART1 (GPIO8/GPIO9) back to the sender.
 //!
-//! This will print "Running" in the embed console when run with `cargo embed --example e00-alive`.
+//! Bytes are moved between the UART FIFOs and the ring buffers by the
+//! `UART1_IRQ` handler, the main loop only works with the buffers and sleeps
+//! in between. The driver counts the receive errors, such as the framing
+//! errors of a sender at another baud rate, and they are reported back.
+//!
+//! At 115200 baud unless `uart.baud` is set, see `e07-usb-shell`.
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
+use core::cell::RefCell;
+use core::fmt::Write;
+
 use panic_halt as _;
 
+use critical_section::Mutex;
 use defmt as log;
-use rp2040_hal as hal;
-use rp_pico as bsp;
+use heapless::spsc::Queue;
+use pico_bites::board;
+use pico_bites::line::LineBuffer;
+use pico_bites::settings;
+use pico_bites::uart;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
+
+pico_bites::binary_info! {
+    description: "Echoes lines on UART1",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
 
-#[bsp::entry]
+const QUEUE_LEN: usize = 256;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::Driver<'static, pac::UART1, UartPins, QUEUE_LEN, QUEUE_LEN>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+
+#[hal::entry]
 fn main() -> ! {
-    log::info!(
-        "Board {}, git revision {:x}, ROM verion {:x}",
-        hal::rom_data::copyright_string(),
-        hal::rom_data::git_revision(),
-        hal::rom_data::rom_version_number(),
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
     );
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(115200));
 
+    let rx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let tx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let (driver, mut port) = uart::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        pac::Interrupt::UART1_IRQ,
+        rx_queue,
+        tx_queue,
+    )
+    .unwrap();
+
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
+
+    writeln!(port, "Type a line and press Enter\r").unwrap();
+
+    let mut line = LineBuffer::<128>::new();
+    let mut reported = uart::ErrorCounts::default();
     loop {
-        cortex_m::asm::wfe();
+        let errors = critical_section::with(|cs| {
+            UART_DRIVER
+                .borrow_ref(cs)
+                .as_ref()
+                .map(|driver| driver.errors())
+        });
+        if let Some(errors) = errors.filter(|&errors| errors != reported) {
+            writeln!(port, "Receive errors: {errors}\r").unwrap();
+            reported = errors;
+        }
+
+        while let Some(text) = port.read_line(&mut line) {
+            log::info!("Received {} bytes", text.len());
+
+            port.write_all(b"> ");
+            port.write_all(text);
+            port.write_all(b"\r\n");
+        }
+
+        // Sleep with the interrupts masked so that a byte arriving right
+        // after the check still wakes the core up.
+        cortex_m::interrupt::free(|_| {
+            if !port.rx_ready() {
+                cortex_m::asm::wfi();
+            }
+        });
     }
 }
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_interrupt();
+        }
+    });
+}
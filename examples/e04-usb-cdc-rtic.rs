@@ -1,22 +1,54 @@
+//! Converts the text typed into the USB serial port to the upper case.
+//!
+//! The USB device is serviced from the `USBCTRL_IRQ` hardware task, the
+//! received data is handed to a software task which writes the reply, and
+//! idle sleeps in between.
 #![no_main]
 #![no_std]
 
-use defmt_rtt as _;
 use panic_halt as _;
 
-#[rtic::app(device = rp_pico::hal::pac, peripherals = true)]
+pico_bites::binary_info! {
+    description: "Upper-cases the text typed into the USB serial port, RTIC",
+}
+
+#[rtic::app(device = rp2040_hal::pac, peripherals = true, dispatchers = [SW0_IRQ])]
 mod app {
+    use core::fmt::Write;
+
     use defmt as log;
-    use rp2040_hal as hal;
+
+    use heapless::String;
+    use heapless::Vec;
+    use pico_bites::board;
+
+    use board::hal;
+
+    use usb_device::class_prelude::*;
+    use usb_device::prelude::*;
+    use usbd_serial::SerialPort;
+    use usbd_serial::USB_CLASS_CDC;
+
+    const USB_VENDOR_ID: u16 = 0x16c2;
+    const USB_PRODUCT_ID: u16 = 0x27df;
+
+    /// Size of the full speed bulk endpoint packet
+    const PACKET_LEN: usize = 64;
 
     #[shared]
-    struct Shared {}
+    struct Shared {
+        usb_dev: UsbDevice<'static, hal::usb::UsbBus>,
+        serial: SerialPort<'static, hal::usb::UsbBus>,
+    }
 
     #[local]
-    struct Local {}
+    struct Local {
+        timer: hal::Timer,
+        said_hello: bool,
+    }
 
-    #[init]
-    fn init(_: init::Context) -> (Shared, Local, init::Monotonics) {
+    #[init(local = [usb_bus: Option<UsbBusAllocator<hal::usb::UsbBus>> = None])]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
         log::info!(
             "Board {}, git revision {:x}, ROM verion {:x}",
             hal::rom_data::copyright_string(),
@@ -25,14 +57,107 @@ mod app {
         );
         log::info!("RTIC app init");
 
-        (Shared {}, Local {}, init::Monotonics())
+        let board::Board {
+            clocks,
+            timer,
+            mut pac,
+            ..
+        } = board::Board::new(cx.device, cx.core);
+
+        // The USB classes borrow the allocator for the lifetime of the app
+        let usb_bus: &'static _ =
+            cx.local
+                .usb_bus
+                .insert(UsbBusAllocator::new(hal::usb::UsbBus::new(
+                    pac.USBCTRL_REGS,
+                    pac.USBCTRL_DPRAM,
+                    clocks.usb_clock,
+                    true,
+                    &mut pac.RESETS,
+                )));
+
+        let serial = SerialPort::new(usb_bus);
+        let usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID))
+            .device_class(USB_CLASS_CDC)
+            .build();
+
+        (
+            Shared { usb_dev, serial },
+            Local {
+                timer,
+                said_hello: false,
+            },
+            init::Monotonics(),
+        )
     }
 
     #[idle]
     fn idle(_: idle::Context) -> ! {
         loop {
-            log::info!("RTIC idle");
-            cortex_m::asm::wfe();
+            cortex_m::asm::wfi();
+        }
+    }
+
+    #[task(binds = USBCTRL_IRQ, shared = [usb_dev, serial])]
+    fn usb_irq(cx: usb_irq::Context) {
+        (cx.shared.usb_dev, cx.shared.serial).lock(|usb_dev, serial| {
+            if !usb_dev.poll(&mut [serial]) {
+                return;
+            }
+
+            let mut buf = [0u8; PACKET_LEN];
+            match serial.read(&mut buf) {
+                Ok(count) if count > 0 => {
+                    log::info!("Read {} bytes", count);
+
+                    // The capacity is the packet size, the copy always fits
+                    let data = Vec::from_slice(&buf[..count]).unwrap();
+                    if on_data::spawn(data).is_err() {
+                        log::warn!("Dropped {} bytes, the receiver is busy", count);
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+
+    #[task(capacity = 4, shared = [serial], local = [timer, said_hello])]
+    fn on_data(cx: on_data::Context, mut data: Vec<u8, PACKET_LEN>) {
+        let on_data::LocalResources { timer, said_hello } = cx.local;
+        let mut serial = cx.shared.serial;
+
+        if !*said_hello {
+            *said_hello = true;
+
+            let mut text: String<64> = String::new();
+            write!(
+                &mut text,
+                "Timer ticks: {:#x}\r\n",
+                timer.get_counter().ticks()
+            )
+            .unwrap();
+
+            serial.lock(|serial| {
+                write_all(serial, b"Hello, type to convert to the upper case!\r\n");
+                write_all(serial, text.as_bytes());
+            });
+        }
+
+        data.make_ascii_uppercase();
+        serial.lock(|serial| write_all(serial, &data));
+    }
+
+    /// Writes as much as the USB buffers take, drops the rest.
+    fn write_all(serial: &mut SerialPort<'static, hal::usb::UsbBus>, mut data: &[u8]) {
+        while !data.is_empty() {
+            match serial.write(data) {
+                Ok(len) => data = &data[len..],
+                // Err(WouldBlock) means the USB write buffer is full
+                Err(_) => {
+                    log::warn!("Dropped {} bytes, the USB buffer is full", data.len());
+                    break;
+                }
+            }
         }
     }
 }
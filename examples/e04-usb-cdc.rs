@@ -1,26 +1,36 @@
-//! Shows off the minimal required structure.
+//! Converts the text typed into the USB serial port to the upper case.
 //!
-//! This will print "Running" in the embed console when run with `cargo embed --example e00-alive`.
+//! The board reboots into BOOTSEL when the port is opened and closed at
+//! 1200 baud, or when `picotool` asks the reset interface, so it can be
+//! re-flashed without pressing the button.
+//!
+//! The `usb.vid` and `usb.pid` settings, see `e07-usb-shell`, change the
+//! USB IDs.
 #![no_std]
 #![no_main]
 
-use defmt_rtt as _;
 use panic_halt as _;
 
 use defmt as log;
-use rp2040_hal as hal;
-use rp_pico as bsp;
+use pico_bites::board;
+use pico_bites::settings;
+use pico_bites::usb_reset;
+
+use board::hal;
 
 use core::fmt::Write;
 use heapless::String;
 use usb_device::{class_prelude::*, prelude::*};
 use usbd_serial::SerialPort;
-use usbd_serial::USB_CLASS_CDC;
+
+pico_bites::binary_info! {
+    description: "Upper-cases the text typed into the USB serial port",
+}
 
 const USB_VENDOR_ID: u16 = 0x16c2;
 const USB_PRODUCT_ID: u16 = 0x27df;
 
-#[bsp::entry]
+#[hal::entry]
 fn main() -> ! {
     log::info!(
         "Board {}, git revision {:x}, ROM verion {:x}",
@@ -29,28 +39,13 @@ fn main() -> ! {
         hal::rom_data::rom_version_number(),
     );
 
-    // Grab our singleton objects
-    let mut pac = bsp::hal::pac::Peripherals::take().unwrap();
-
-    // Set up the watchdog driver - needed by the clock setup code
-    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
-
-    // Configure the clocks
-    //
-    // The default is to generate a 125 MHz system clock
-    let clocks = hal::clocks::init_clocks_and_plls(
-        rp_pico::XOSC_CRYSTAL_FREQ,
-        pac.XOSC,
-        pac.CLOCKS,
-        pac.PLL_SYS,
-        pac.PLL_USB,
-        &mut pac.RESETS,
-        &mut watchdog,
-    )
-    .ok()
-    .unwrap();
-
-    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    // Bring up the clocks (125 MHz system clock), the timer and the rest
+    let board::Board {
+        clocks,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
 
     // Set up the USB driver
     let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
@@ -63,16 +58,40 @@ fn main() -> ! {
 
     // Set up the USB Communications Class Device driver
     let mut serial = SerialPort::new(&usb_bus);
-
-    // Create a USB device with a fake VID and PID
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID))
-        .device_class(USB_CLASS_CDC)
+    let mut reset_interface = usb_reset::ResetInterface::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID, unless the settings
+    // have other ones.
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let vid_pid = UsbVidPid(
+        store
+            .number(&settings::USB_VENDOR_ID)
+            .map_or(USB_VENDOR_ID, |id| id as u16),
+        store
+            .number(&settings::USB_PRODUCT_ID)
+            .map_or(USB_PRODUCT_ID, |id| id as u16),
+    );
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, vid_pid)
+        .composite_with_iads()
         .build();
 
+    let mut baud_touch = usb_reset::BaudTouch::new();
     let mut said_hello = false;
     loop {
         // Check for new data
-        if usb_dev.poll(&mut [&mut serial]) {
+        let polled = usb_dev.poll(&mut [&mut serial, &mut reset_interface]);
+
+        if let Some(request) = reset_interface.take_request() {
+            log::info!("Reset requested over USB");
+            usb_reset::reset(request);
+        }
+        if baud_touch.poll(polled, serial.line_coding().data_rate(), serial.dtr()) {
+            log::info!("1200 baud touch, rebooting into BOOTSEL");
+            hal::rom_data::reset_to_usb_boot(0, 0);
+        }
+
+        if polled {
             let mut buf = [0u8; 64];
             match serial.read(&mut buf) {
                 Err(_e) => {
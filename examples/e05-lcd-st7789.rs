@@ -0,0 +1,134 @@
+//! Shows off working with the ST7789 TFT display.
+//!
+//! Prints a log on the display through the text console, which scrolls the
+//! panel in hardware instead of redrawing it.
+//!
+//! This will print "Running" in the embed console when run with `cargo embed --example e05-lcd-st7789`.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use core::fmt::Write;
+
+use defmt as log;
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::RgbColor;
+use fugit::RateExtU32;
+use hal::gpio;
+use hal::gpio::FunctionSpi;
+use hal::Clock;
+use mipidsi::models::ST7789;
+use mipidsi::ColorInversion;
+use mipidsi::Orientation;
+use pico_bites::board;
+use pico_bites::console::Console;
+
+use board::hal;
+
+pico_bites::binary_info! {
+    description: "Text console on the ST7789 display",
+    pins: [
+        16 => "LCD MISO",
+        17 => "LCD CS",
+        18 => "LCD SCK",
+        19 => "LCD MOSI",
+        20 => "LCD BL",
+        21 => "LCD RST",
+        22 => "LCD DC",
+    ],
+}
+
+const DISPLAY_WIDTH: u16 = 240;
+const DISPLAY_HEIGHT: u16 = 320;
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut delay,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let cs = pins
+        .gpio17
+        .into_push_pull_output_in_state(gpio::PinState::High);
+    let dc = pins.gpio22.into_push_pull_output();
+    let reset = pins
+        .gpio21
+        .into_push_pull_output_in_state(gpio::PinState::High);
+    let _bl = pins
+        .gpio20
+        .into_push_pull_output_in_state(gpio::PinState::High);
+
+    let mosi = pins.gpio19.into_function::<FunctionSpi>();
+    let miso = pins.gpio16.into_function::<FunctionSpi>();
+    let sclk = pins.gpio18.into_function::<FunctionSpi>();
+
+    let spi = hal::spi::Spi::<_, _, _, 8>::new(pac.SPI0, (mosi, miso, sclk));
+
+    // mipidsi 0.7 is built on display-interface 0.4 and embedded-hal 0.2,
+    // the matching SPI interface takes the chip select pin.
+    let mut display = mipidsi::Builder::with_model(
+        display_interface_spi::SPIInterface::new(
+            spi.init(
+                &mut pac.RESETS,
+                clocks.peripheral_clock.freq(),
+                62u32.MHz(),
+                embedded_hal::spi::MODE_3,
+            ),
+            dc,
+            cs,
+        ),
+        ST7789,
+    )
+    .with_color_order(mipidsi::ColorOrder::Rgb)
+    .with_invert_colors(ColorInversion::Inverted)
+    .with_orientation(Orientation::Portrait(false))
+    .with_display_size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    .with_framebuffer_size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    .init(&mut delay, Some(reset))
+    .unwrap();
+
+    // The 20 pixel high font splits the whole panel into 16 lines, nothing
+    // is left for the fixed areas.
+    display.set_scroll_region(0, DISPLAY_HEIGHT, 0).unwrap();
+
+    let bg_color = Rgb565::new(4, 0, 4);
+    let mut console = Console::new(
+        display,
+        &FONT_10X20,
+        Rgb565::YELLOW,
+        bg_color,
+        |display, offset| display.set_scroll_offset(offset),
+    )
+    .unwrap();
+
+    writeln!(
+        console,
+        "Board {}, git revision {:x}, ROM version {:x}",
+        hal::rom_data::copyright_string(),
+        hal::rom_data::git_revision(),
+        hal::rom_data::rom_version_number(),
+    )
+    .unwrap();
+
+    let mut count = 0u32;
+    loop {
+        write!(
+            console,
+            "\nHello, world! {count:04} at {} us",
+            timer.get_counter().ticks()
+        )
+        .unwrap();
+
+        delay.delay_ms(500);
+        count = count.wrapping_add(1);
+    }
+}
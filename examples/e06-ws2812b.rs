@@ -0,0 +1,113 @@
+//! Scrolls a text across the Waveshare RP2040-Matrix board.
+//!
+//! The LEDs are connected to GPIO 16: https://www.waveshare.com/wiki/RP2040-Matrix
+//!
+//! Build with `--no-default-features --features waveshare-rp2040-zero`.
+//!
+//! The `led.brightness` setting, see `e07-usb-shell`, makes the LEDs
+//! brighter.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use pico_bites::board;
+use pico_bites::color;
+use pico_bites::matrix::Layout;
+use pico_bites::matrix::Marquee;
+use pico_bites::matrix::Matrix;
+use pico_bites::settings;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::pio::PIOExt;
+use smart_leds::brightness;
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWrite;
+use ws2812_pio::Ws2812;
+
+pico_bites::binary_info! {
+    description: "Scrolls a text across the WS2812 LED matrix",
+    pins: [
+        16 => "WS2812 DIN",
+    ],
+}
+
+const MATRIX_WIDTH: usize = 5;
+const MATRIX_HEIGHT: usize = 5;
+const STRIP_LEN: usize = MATRIX_WIDTH * MATRIX_HEIGHT;
+
+/// Frames per one pixel of scrolling
+const SCROLL_FRAMES: u32 = 4;
+
+/// Advance of the colour animation per frame, the full cycle is `0x10000`
+/// so the colour repeats every 10 seconds at 16 ms per frame.
+const ANIMATION_STEP: u16 = 105;
+
+#[hal::entry]
+fn main() -> ! {
+    let board::Board {
+        clocks,
+        pins,
+        delay: mut frame_delay,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut ws = Ws2812::new(
+        pins.neopixel.into_function(),
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    let mut leds: [RGB8; STRIP_LEN] = [(0, 0, 0).into(); STRIP_LEN];
+
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    // Bring down the overall brightness of the strip to not blow
+    // the USB power supply: every LED draws ~60mA, RGB means 3 LEDs per
+    // ws2812 LED, for 3 LEDs that would be: 3 * 3 * 60mA, which is
+    // already 540mA for just 3 white LEDs!
+    let strip_brightness = store
+        .number(&settings::LED_BRIGHTNESS)
+        .map_or(1, |value| value as u8); // Limit brightness to 1/256 by default
+    let mut marquee = Marquee::new(b"* WHAT'S UP, WORLD? * ");
+    let mut frame_num = 0u32;
+    let mut time = 0u16;
+
+    loop {
+        // Prepare frame
+        let fg_color = get_color(time);
+        let bg_color = (0, 0, 0).into();
+        let mut matrix = Matrix::new(&mut leds, MATRIX_WIDTH, MATRIX_HEIGHT, Layout::BottomRight);
+        marquee.render(&mut matrix, fg_color, bg_color);
+
+        // Write frame
+        ws.write(brightness(leds.iter().copied(), strip_brightness))
+            .unwrap();
+
+        frame_num += 1;
+        if frame_num.is_multiple_of(SCROLL_FRAMES) {
+            marquee.step();
+        }
+
+        // The time counter wraps around at the end of the cycle
+        time = time.wrapping_add(ANIMATION_STEP);
+
+        // Wait a bit until calculating the next frame.
+        frame_delay.delay_ms(16);
+    }
+}
+
+/// Swings the hue back and forth across the colour wheel, `t` is the
+/// position in the animation cycle.
+fn get_color(t: u16) -> RGB8 {
+    // Bring 1..=255 sine range to 0..=0xffff hue range
+    let hue = color::sin8((t >> 8) as u8) as u16 * 0x101;
+
+    color::hsv2rgb(hue, 255, 255)
+}
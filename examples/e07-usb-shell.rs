@@ -0,0 +1,368 @@
+//! Command shell on the USB serial port.
+//!
+//! Open the port with a terminal (e.g. `picocom /dev/ttyACM0`) and type
+//! `help`. The shell drives the on-board LED on GP25 and the UART1 on
+//! GPIO8/GPIO9 used by the UART examples, with CTS and RTS on GPIO10 and
+//! GPIO11: `uart` changes its settings while it runs and shows the receive
+//! errors, the received data is dropped. `set` stores the UART baud rate
+//! and the USB IDs in the flash, for this and the other examples to use
+//! from the next boot.
+//!
+//! The second serial port of the device carries the defmt logs when built
+//! with `--features defmt-usb`, decode them with `defmt-print`.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::firmware;
+use pico_bites::flash::Flash;
+use pico_bites::settings;
+use pico_bites::shell;
+use pico_bites::uart;
+use pico_bites::usb_log;
+
+use board::hal;
+
+use core::cell::RefCell;
+use core::fmt;
+use core::fmt::Write;
+use critical_section::Mutex;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::digital::StatefulOutputPin;
+use hal::clocks::Clock;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
+use heapless::spsc::Queue;
+use usb_device::{class_prelude::*, prelude::*};
+use usbd_serial::SerialPort;
+
+pico_bites::binary_info! {
+    description: "Command shell on the USB serial port",
+    pins: [
+        25 => "LED",
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+        10 => "UART1 CTS",
+        11 => "UART1 RTS",
+    ],
+}
+
+/// Unless changed in the settings
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27df;
+const BAUD_RATE: u32 = 115_200;
+
+/// The range of the `uart baud` command
+const BAUD_RATES: core::ops::RangeInclusive<u32> = settings::UART_BAUD.range;
+
+const QUEUE_LEN: usize = 64;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio10, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio11, FunctionUart, PullDown>,
+);
+type UartDriver = uart::Driver<'static, pac::UART1, UartPins, QUEUE_LEN, QUEUE_LEN>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+
+/// Output waiting for room in the USB buffers, the excess is dropped.
+struct Output(heapless::Vec<u8, 1024>);
+
+impl fmt::Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0
+            .extend_from_slice(s.as_bytes())
+            .map_err(|_| fmt::Error)
+    }
+}
+
+impl Output {
+    fn flush<B: UsbBus>(&mut self, serial: &mut SerialPort<B>) {
+        if let Ok(len) = serial.write(&self.0) {
+            self.0.copy_within(len.., 0);
+            self.0.truncate(self.0.len() - len);
+        }
+    }
+}
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running {}", log::Display2Format(&firmware::FIRMWARE_INFO));
+
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    // SAFETY: loaded once, see settings::load
+    let mut store = unsafe { settings::load() }.unwrap();
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
+        pins.gpio10.into_function::<FunctionUart>(),
+        pins.gpio11.into_function::<FunctionUart>(),
+    );
+    let baud_rate = store.number(&settings::UART_BAUD).unwrap_or(BAUD_RATE);
+    let rx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let tx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let (driver, mut uart_port) = uart::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        uart::Config::new(baud_rate),
+        clocks.peripheral_clock.freq(),
+        pac::Interrupt::UART1_IRQ,
+        rx_queue,
+        tx_queue,
+    )
+    .unwrap();
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
+    let mut uart_received = 0u32;
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut serial = SerialPort::new(&usb_bus);
+    let mut log_port = SerialPort::new(&usb_bus);
+    let vid_pid = UsbVidPid(
+        store
+            .number(&settings::USB_VENDOR_ID)
+            .map_or(USB_VENDOR_ID, |id| id as u16),
+        store
+            .number(&settings::USB_PRODUCT_ID)
+            .map_or(USB_PRODUCT_ID, |id| id as u16),
+    );
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, vid_pid)
+        .composite_with_iads()
+        .build();
+
+    let mut editor = shell::Editor::<80, 8>::new("pico> ", shell::COMMANDS);
+    let mut out = Output(heapless::Vec::new());
+    let mut terminal_open = false;
+    let mut blink_period_us: Option<u64> = None;
+    let mut next_toggle_us = 0;
+
+    loop {
+        if let Some(period) = blink_period_us {
+            let now = timer.get_counter().ticks();
+            if now >= next_toggle_us {
+                led_pin.toggle().ok();
+                next_toggle_us = now + period;
+            }
+        }
+
+        // Nothing uses the data, the count shows it arrives
+        let mut uart_buf = [0u8; QUEUE_LEN];
+        uart_received = uart_received.wrapping_add(uart_port.read(&mut uart_buf) as u32);
+
+        let polled = usb_dev.poll(&mut [&mut serial, &mut log_port]);
+        usb_log::flush(&mut log_port);
+        if !polled {
+            continue;
+        }
+
+        // Greet the user when a terminal opens the port
+        if serial.dtr() != terminal_open {
+            terminal_open = serial.dtr();
+            if terminal_open {
+                writeln!(out, "\r\nPico shell, type `help` for the commands\r").ok();
+                editor.prompt(&mut out);
+            }
+        }
+
+        let mut buf = [0u8; 64];
+        let count = serial.read(&mut buf).unwrap_or(0);
+        for &byte in &buf[..count] {
+            let Some(line) = editor.feed(byte, &mut out) else {
+                continue;
+            };
+
+            match shell::parse(line) {
+                Ok(shell::Command::Help) => {
+                    for command in shell::COMMANDS {
+                        writeln!(out, "{:<24}{}\r", command.usage, command.help).ok();
+                    }
+                }
+                Ok(shell::Command::Info) => {
+                    writeln!(out, "Firmware {}\r", firmware::FIRMWARE_INFO).ok();
+                    writeln!(
+                        out,
+                        "Board {}, git revision {:x}, ROM version {:x}\r",
+                        hal::rom_data::copyright_string(),
+                        hal::rom_data::git_revision(),
+                        hal::rom_data::rom_version_number(),
+                    )
+                    .ok();
+                    show_uart(&mut out, uart_received);
+                }
+                Ok(shell::Command::Led(led)) => {
+                    blink_period_us = None;
+                    match led {
+                        shell::Led::On => led_pin.set_high().unwrap(),
+                        shell::Led::Off => led_pin.set_low().unwrap(),
+                        shell::Led::Blink { period_ms } => {
+                            blink_period_us = Some(u64::from(period_ms) * 1000)
+                        }
+                    }
+                }
+                Ok(shell::Command::Uart) => show_uart(&mut out, uart_received),
+                Ok(shell::Command::UartBaud(baud)) if BAUD_RATES.contains(&baud) => {
+                    configure_uart(&mut out, &mut uart_port, |config| config.baud = baud);
+                }
+                Ok(shell::Command::UartBaud(_)) => {
+                    writeln!(
+                        out,
+                        "error: baud rate must be within {}..={}\r",
+                        BAUD_RATES.start(),
+                        BAUD_RATES.end()
+                    )
+                    .ok();
+                }
+                Ok(shell::Command::UartFormat(data_bits, parity, stop_bits)) => {
+                    configure_uart(&mut out, &mut uart_port, |config| {
+                        (config.data_bits, config.parity, config.stop_bits) =
+                            (data_bits, parity, stop_bits)
+                    });
+                }
+                Ok(shell::Command::UartFlow(flow_control)) => {
+                    configure_uart(&mut out, &mut uart_port, |config| {
+                        config.flow_control = flow_control
+                    });
+                }
+                Ok(shell::Command::Settings) => {
+                    for info in settings::KEYS {
+                        write!(out, "{:<16}", info.name).ok();
+                        match store.number(info) {
+                            Some(value) if info.hex => write!(out, "{value:#06x}"),
+                            Some(value) => write!(out, "{value}"),
+                            None => write!(out, "default"),
+                        }
+                        .ok();
+                        writeln!(out, "\r").ok();
+                    }
+                }
+                Ok(shell::Command::Set(key, value)) => match store.set_u32(key, value) {
+                    Ok(()) => save(&mut store, &mut out),
+                    Err(e) => {
+                        writeln!(out, "error: {e}\r").ok();
+                    }
+                },
+                Ok(shell::Command::Unset(key)) => {
+                    store.remove(key);
+                    save(&mut store, &mut out);
+                }
+                Ok(shell::Command::Reboot) => {
+                    writeln!(out, "Rebooting...\r").ok();
+                    drain(&mut usb_dev, &mut serial, &mut log_port, &mut out, &timer);
+                    cortex_m::peripheral::SCB::sys_reset();
+                }
+                Ok(shell::Command::Bootsel) => {
+                    writeln!(out, "Rebooting into BOOTSEL...\r").ok();
+                    drain(&mut usb_dev, &mut serial, &mut log_port, &mut out, &timer);
+                    hal::rom_data::reset_to_usb_boot(0, 0);
+                }
+                Err(shell::Error::Empty) => {}
+                Err(e) => {
+                    writeln!(out, "error: {e}\r").ok();
+                }
+            }
+            editor.prompt(&mut out);
+        }
+
+        out.flush(&mut serial);
+    }
+}
+
+/// Changes the UART settings once the queued bytes went out with the old
+/// ones.
+fn configure_uart<const RX: usize, const TX: usize>(
+    out: &mut Output,
+    port: &mut uart::Port<'_, RX, TX>,
+    change: impl FnOnce(&mut uart::Config),
+) {
+    port.flush();
+    let mut config =
+        critical_section::with(|cs| *UART_DRIVER.borrow_ref(cs).as_ref().unwrap().config());
+    change(&mut config);
+    let configured = loop {
+        let configured = critical_section::with(|cs| {
+            UART_DRIVER
+                .borrow_ref_mut(cs)
+                .as_mut()
+                .unwrap()
+                .configure(config)
+        });
+        // The interrupt runs while the TX FIFO drains
+        if configured != Err(uart::ConfigError::Busy) {
+            break configured;
+        }
+    };
+    match configured {
+        Ok(()) => writeln!(out, "UART1 at {config}\r"),
+        Err(e) => writeln!(out, "error: {e}\r"),
+    }
+    .ok();
+}
+
+fn show_uart(out: &mut Output, received: u32) {
+    let (config, errors, dropped) = critical_section::with(|cs| {
+        let driver = UART_DRIVER.borrow_ref(cs);
+        let driver = driver.as_ref().unwrap();
+        (*driver.config(), driver.errors(), driver.dropped())
+    });
+    writeln!(out, "UART1 at {config}, {received} bytes received\r").ok();
+    writeln!(out, "Errors: {errors}, {dropped} bytes dropped\r").ok();
+}
+
+/// Commits the changed settings.
+fn save<F: Flash>(store: &mut settings::Settings<F>, out: &mut Output) {
+    match store.commit() {
+        Ok(()) => writeln!(out, "Saved, takes effect from the next boot\r"),
+        Err(e) => writeln!(out, "error: {e}\r"),
+    }
+    .ok();
+}
+
+/// Gives the host up to 50 ms to pick up the output before a reset.
+fn drain<B: UsbBus>(
+    usb_dev: &mut UsbDevice<B>,
+    serial: &mut SerialPort<B>,
+    log_port: &mut SerialPort<B>,
+    out: &mut Output,
+    timer: &hal::Timer,
+) {
+    let deadline = timer.get_counter().ticks() + 50_000;
+    while timer.get_counter().ticks() < deadline {
+        usb_dev.poll(&mut [serial, log_port]);
+        out.flush(serial);
+        usb_log::flush(log_port);
+    }
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_interrupt();
+        }
+    });
+}
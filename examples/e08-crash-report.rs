@@ -0,0 +1,85 @@
+//! Keeps the panic message across the reset
+//!
+//! Counts on UART1 and panics after a few seconds. The panic handler stores
+//! the report in RAM and resets the chip, the next boot prints the report
+//! over defmt and UART1 and keeps counting without panicking.
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use fugit::RateExtU32;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::crash;
+
+use board::hal;
+use hal::clocks::Clock;
+
+pico_bites::binary_info! {
+    description: "Keeps the panic message across the reset",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
+
+/// Counter value to panic at, 100 ms per step
+const PANIC_AT: u32 = 30;
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut delay,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<hal::gpio::FunctionUart>(),
+        pins.gpio9.into_function::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::UartConfig::new(
+                115200.Hz(),
+                hal::uart::DataBits::Eight,
+                None,
+                hal::uart::StopBits::One,
+            ),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    let reason = crash::reset_reason();
+    log::info!("Reset reason: {}", reason);
+    writeln!(uart, "Reset reason: {reason:?}\r").unwrap();
+
+    let report = crash::take();
+    if let Some(report) = &report {
+        log::error!("Last boot {}", log::Display2Format(report));
+        writeln!(uart, "Last boot {report}\r").unwrap();
+    }
+
+    let mut count = 0u32;
+    loop {
+        uart.write_full_blocking(b"Counter: ");
+        writeln!(uart, "{count:02}\r").unwrap();
+
+        if report.is_none() && count == PANIC_AT {
+            panic!("Counter reached {count}");
+        }
+
+        delay.delay_ms(100);
+        count = count.wrapping_add(1);
+    }
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crash::record_and_reset(info)
+}
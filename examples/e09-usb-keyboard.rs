@@ -0,0 +1,107 @@
+//! Types the text received on the UART1 or the USB serial port as a USB
+//! keyboard.
+//!
+//! The device has a serial port and a boot keyboard. Whatever arrives on
+//! the port or on GPIO9 (UART1 RX, 115200 baud) is typed into the window
+//! that has the focus, e.g. `echo 'hello' > /dev/ttyACM0` types `hello`
+//! and Enter. Caps Lock lights the on-board LED on GP25.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::keyboard;
+
+use board::hal;
+
+use embedded_hal::digital::OutputPin;
+use fugit::RateExtU32;
+use hal::clocks::Clock;
+use usb_device::{class_prelude::*, prelude::*};
+
+pico_bites::binary_info! {
+    description: "Types the text from UART1 or USB serial as a keyboard",
+    pins: [
+        25 => "Caps Lock LED",
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27da;
+
+const LED_CAPS_LOCK: u8 = 0x02;
+
+#[hal::entry]
+fn main() -> ! {
+    let board::Board {
+        clocks,
+        pins,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<hal::gpio::FunctionUart>(),
+        pins.gpio9.into_function::<hal::gpio::FunctionUart>(),
+    );
+    let uart = hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::UartConfig::new(
+                115200.Hz(),
+                hal::uart::DataBits::Eight,
+                None,
+                hal::uart::StopBits::One,
+            ),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut usb = keyboard::SerialKeyboard::new(&usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID));
+
+    let mut typist = keyboard::Typist::<1024>::new();
+    let mut leds = 0;
+
+    log::info!("Ready to type");
+    loop {
+        let mut buf = [0u8; 64];
+        if usb.poll() {
+            if let Ok(count) = usb.serial.read(&mut buf) {
+                let queued = typist.push(&buf[..count]);
+                if queued < count {
+                    log::warn!("Dropped {} bytes from USB", count - queued);
+                }
+            }
+        }
+        let count = uart.read_raw(&mut buf).unwrap_or(0);
+        let queued = typist.push(&buf[..count]);
+        if queued < count {
+            log::warn!("Dropped {} bytes from UART1", count - queued);
+        }
+
+        if let Some(report) = typist.report() {
+            if usb.keyboard.write_report(report).is_ok() {
+                typist.sent();
+            }
+        }
+
+        if usb.keyboard.leds() != leds {
+            leds = usb.keyboard.leds();
+            led_pin
+                .set_state((leds & LED_CAPS_LOCK != 0).into())
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! A small USB drive of 240 KiB near the end of the flash.
+//!
+//! The first boot formats the drive with a FAT12 volume and a README. Drop
+//! a `CONFIG.TXT` on it and eject the drive: the firmware logs the file.
+//! The on-board LED on GP25 is lit while the drive is mounted.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::flash;
+use pico_bites::msc;
+
+use board::hal;
+
+use embedded_hal::digital::OutputPin;
+use msc::fat;
+use usb_device::{class_prelude::*, prelude::*};
+
+pico_bites::binary_info! {
+    description: "USB drive in the flash",
+    pins: [
+        25 => "Mounted LED",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27db;
+
+const README: &[u8] = b"Files on this drive live in the flash of the Pico.\r\n\
+\r\n\
+Put the settings in CONFIG.TXT and eject the drive to have them read.\r\n";
+
+#[hal::entry]
+fn main() -> ! {
+    let board::Board {
+        clocks,
+        pins,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // SAFETY: the only RomFlash on the disk region, see RomFlash::new
+    let mut disk = msc::FlashDisk::new(unsafe { flash::RomFlash::new(flash::disk_region()) });
+    if fat::Geometry::read(&mut disk).is_err() {
+        log::info!("Formatting the drive");
+        let files = [fat::File {
+            name: "README.TXT",
+            contents: README,
+        }];
+        if let Err(e) = fat::format(&mut disk, "PICO", &files) {
+            log::error!("Formatting failed: {}", log::Debug2Format(&e));
+        }
+    }
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut drive = msc::MscClass::new(
+        &usb_bus,
+        msc::BulkOnly::new(disk, "Pico", "Bites drive", "0.1"),
+    );
+    let mut usb_dev =
+        UsbDeviceBuilder::new(&usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID)).build();
+
+    let mut mounted = false;
+    loop {
+        usb_dev.poll(&mut [&mut drive]);
+
+        let now_mounted =
+            usb_dev.state() == UsbDeviceState::Configured && !drive.drive().is_ejected();
+        if now_mounted == mounted {
+            continue;
+        }
+        mounted = now_mounted;
+        led_pin.set_state(mounted.into()).unwrap();
+        if mounted {
+            continue;
+        }
+
+        // The host is done with the drive
+        let mut config = [0u8; 256];
+        match fat::read_file(drive.drive().disk(), "CONFIG.TXT", &mut config) {
+            Ok(Some(size)) => {
+                let len = size.min(config.len());
+                log::info!(
+                    "CONFIG.TXT, {} bytes: {}",
+                    size,
+                    core::str::from_utf8(&config[..len]).unwrap_or("<not UTF-8>")
+                );
+            }
+            Ok(None) => log::info!("No CONFIG.TXT on the drive"),
+            Err(e) => log::warn!("Cannot read the drive: {}", log::Debug2Format(&e)),
+        }
+    }
+}
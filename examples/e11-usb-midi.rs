@@ -0,0 +1,229 @@
+//! A USB MIDI instrument with a 5x5 WS2812 matrix on GPIO 16.
+//!
+//! Notes from C4 (60) up light the pixels one by one, the velocity sets the
+//! brightness; the on-board LED on GP25 is lit while any note is held.
+//! Volume (CC 7) dims the whole matrix, modulation (CC 1) shifts the hue
+//! and All Notes Off (CC 123) clears it.
+//!
+//! The buttons on GPIO 2 to 5 (to the ground, the pull-ups are internal)
+//! send the notes 60 to 63 back to the host.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::color;
+use pico_bites::matrix::Layout;
+use pico_bites::matrix::Matrix;
+use pico_bites::midi;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::pio::PIOExt;
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::digital::OutputPin;
+use midi::Message;
+use smart_leds::brightness;
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWrite;
+use usb_device::{class_prelude::*, prelude::*};
+use ws2812_pio::Ws2812;
+
+pico_bites::binary_info! {
+    description: "USB MIDI notes on the WS2812 LED matrix",
+    pins: [
+        2 => "Note 60 button",
+        3 => "Note 61 button",
+        4 => "Note 62 button",
+        5 => "Note 63 button",
+        16 => "WS2812 DIN",
+        25 => "Note LED",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27dc;
+
+const MATRIX_WIDTH: usize = 5;
+const MATRIX_HEIGHT: usize = 5;
+const STRIP_LEN: usize = MATRIX_WIDTH * MATRIX_HEIGHT;
+
+/// The note of the first pixel and of the first button.
+const FIRST_NOTE: u8 = 60;
+const BUTTON_VELOCITY: u8 = 100;
+
+/// The strip brightness at the full volume, see `e06-ws2812b.rs` for why
+/// it is kept that low.
+const MAX_BRIGHTNESS: u8 = 32;
+
+/// The hue step from one pixel to the next, the full circle is `0x10000`.
+const PIXEL_HUE_STEP: u16 = (0x10000 / STRIP_LEN) as u16;
+
+/// Microseconds between the scans of the buttons.
+const SCAN_PERIOD: u64 = 5_000;
+
+#[hal::entry]
+fn main() -> ! {
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let mut led_pin = pins.led.into_push_pull_output();
+    let mut buttons = [
+        pins.gpio2.into_pull_up_input().into_dyn_pin(),
+        pins.gpio3.into_pull_up_input().into_dyn_pin(),
+        pins.gpio4.into_pull_up_input().into_dyn_pin(),
+        pins.gpio5.into_pull_up_input().into_dyn_pin(),
+    ];
+
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut ws = Ws2812::new(
+        pins.gpio16.into_function(),
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut midi = midi::MidiClass::new(&usb_bus);
+    let mut usb_dev =
+        UsbDeviceBuilder::new(&usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID)).build();
+
+    // The velocity of the note held on each pixel, zero when released
+    let mut velocities = [0u8; STRIP_LEN];
+    let mut volume = 127u8;
+    let mut hue = 0u16;
+    let mut redraw = true;
+
+    let mut pressed = [false; 4];
+    let mut next_scan = 0;
+
+    loop {
+        if usb_dev.poll(&mut [&mut midi]) {
+            let mut buf = [0u8; 64];
+            let count = midi.read(&mut buf).unwrap_or(0);
+            for packet in buf[..count].as_chunks::<4>().0 {
+                let Some((_, message)) = Message::decode(packet) else {
+                    continue;
+                };
+                log::debug!("{}", log::Debug2Format(&message));
+                redraw |= handle(message, &mut velocities, &mut volume, &mut hue);
+            }
+        }
+
+        if redraw {
+            redraw = false;
+            let mut leds = [RGB8::default(); STRIP_LEN];
+            let mut matrix =
+                Matrix::new(&mut leds, MATRIX_WIDTH, MATRIX_HEIGHT, Layout::BottomRight);
+            for (i, &velocity) in velocities.iter().enumerate() {
+                let pixel_hue = hue.wrapping_add(PIXEL_HUE_STEP * i as u16);
+                let level = color::lightness((velocity << 1) | (velocity >> 6));
+                let color = color::scale(color::hsv2rgb(pixel_hue, 255, 255), level);
+                matrix.set((i % MATRIX_WIDTH) as i32, (i / MATRIX_WIDTH) as i32, color);
+            }
+            let strip_brightness = (volume as u16 * MAX_BRIGHTNESS as u16 / 127) as u8;
+            ws.write(brightness(leds.iter().copied(), strip_brightness))
+                .unwrap();
+
+            let held = velocities.iter().any(|&velocity| velocity != 0);
+            led_pin.set_state(held.into()).unwrap();
+        }
+
+        // Scanning every few milliseconds is enough to skip the bounces
+        let now = timer.get_counter().ticks();
+        if now < next_scan || usb_dev.state() != UsbDeviceState::Configured {
+            continue;
+        }
+        next_scan = now + SCAN_PERIOD;
+        for (i, (button, was_pressed)) in buttons.iter_mut().zip(&mut pressed).enumerate() {
+            let is_pressed = button.is_low().unwrap();
+            if is_pressed == *was_pressed {
+                continue;
+            }
+            let note = FIRST_NOTE + i as u8;
+            let message = if is_pressed {
+                Message::NoteOn {
+                    channel: 0,
+                    note,
+                    velocity: BUTTON_VELOCITY,
+                }
+            } else {
+                Message::NoteOff {
+                    channel: 0,
+                    note,
+                    velocity: 0,
+                }
+            };
+            // Try again on the next scan if the host is behind
+            if midi.write(&message.encode(0)).is_ok() {
+                *was_pressed = is_pressed;
+            }
+        }
+    }
+}
+
+/// Applies the message to the state of the matrix, returns whether it
+/// needs redrawing.
+fn handle(message: Message, velocities: &mut [u8], volume: &mut u8, hue: &mut u16) -> bool {
+    let len = velocities.len();
+    let pixel = |note: u8| {
+        note.checked_sub(FIRST_NOTE)
+            .map(usize::from)
+            .filter(|&i| i < len)
+    };
+    match message {
+        Message::NoteOn { note, velocity, .. } => match pixel(note) {
+            Some(i) => {
+                velocities[i] = velocity;
+                true
+            }
+            None => false,
+        },
+        Message::NoteOff { note, .. } => match pixel(note) {
+            Some(i) => {
+                velocities[i] = 0;
+                true
+            }
+            None => false,
+        },
+        Message::ControlChange {
+            control: midi::CONTROL_VOLUME,
+            value,
+            ..
+        } => {
+            *volume = value;
+            true
+        }
+        Message::ControlChange {
+            control: midi::CONTROL_MODULATION,
+            value,
+            ..
+        } => {
+            *hue = u16::from(value) << 9;
+            true
+        }
+        Message::ControlChange {
+            control: midi::CONTROL_ALL_NOTES_OFF,
+            ..
+        } => {
+            velocities.fill(0);
+            true
+        }
+        _ => false,
+    }
+}
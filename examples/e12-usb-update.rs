@@ -0,0 +1,142 @@
+//! Takes firmware updates over the USB serial port.
+//!
+//! `tools/pico-update` sends the new image, which goes to flash slot B, and
+//! reboots the board into it. The new
+//! image confirms itself once the host configures the USB device; if it
+//! does not within 8 seconds, the watchdog brings the previous one back.
+//!
+//! The on-board LED on GP25 blinks every `BLINK_PERIOD_US`, change it to
+//! tell the builds apart.
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use defmt as log;
+use pico_bites::board;
+use pico_bites::firmware;
+use pico_bites::ota;
+
+use board::hal;
+
+use embedded_hal::digital::StatefulOutputPin;
+use ota::protocol;
+use usb_device::{class_prelude::*, prelude::*};
+use usbd_serial::SerialPort;
+
+pico_bites::binary_info! {
+    description: "Firmware updates over the USB serial port",
+    pins: [
+        25 => "Blinking LED",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27dd;
+
+const BLINK_PERIOD_US: u64 = 500_000;
+
+#[hal::entry]
+fn main() -> ! {
+    // May start the image in the other slot instead
+    ota::boot();
+
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    log::info!(
+        "Running {} from slot {}",
+        log::Display2Format(&firmware::FIRMWARE_INFO),
+        log::Display2Format(&ota::running_slot())
+    );
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut serial = SerialPort::new(&usb_bus);
+    let mut usb_dev =
+        UsbDeviceBuilder::new(&usb_bus, UsbVidPid(USB_VENDOR_ID, USB_PRODUCT_ID)).build();
+
+    // The only updater
+    let (slot, store) = unsafe { ota::update_flash() };
+    let mut updater = ota::Updater::new(slot, store, ota::slot_info());
+    let mut decoder = protocol::Decoder::new();
+
+    let mut confirmed = false;
+    let mut next_toggle_us = 0;
+    loop {
+        let now = timer.get_counter().ticks();
+        if now >= next_toggle_us {
+            led_pin.toggle().unwrap();
+            next_toggle_us = now + BLINK_PERIOD_US;
+        }
+
+        let polled = usb_dev.poll(&mut [&mut serial]);
+        // The host talks to the new image, it works well enough
+        if !confirmed && usb_dev.state() == UsbDeviceState::Configured {
+            confirmed = true;
+            if ota::confirm() {
+                log::info!(
+                    "Slot {} confirmed",
+                    log::Display2Format(&ota::running_slot())
+                );
+            }
+        }
+        if !polled {
+            continue;
+        }
+
+        let mut buf = [0u8; 64];
+        let count = serial.read(&mut buf).unwrap_or(0);
+        for &byte in &buf[..count] {
+            let mut response = [0u8; protocol::MAX_BODY];
+            let len = match decoder.push(byte) {
+                None => continue,
+                Some(Ok(request)) => updater.handle(request, &mut response),
+                Some(Err(e)) => {
+                    log::warn!("Dropped a frame: {}", log::Debug2Format(&e));
+                    response[0] = protocol::Status::BadFrame as u8;
+                    1
+                }
+            };
+
+            let mut frame = [0u8; protocol::MAX_FRAME];
+            let len = protocol::encode(&response[..len], &mut frame);
+            write_all(&mut usb_dev, &mut serial, &frame[..len]);
+
+            if updater.reboot_requested() {
+                log::info!("Rebooting");
+                // Give the host the time to pick up the response
+                let deadline = timer.get_counter().ticks() + 50_000;
+                while timer.get_counter().ticks() < deadline {
+                    usb_dev.poll(&mut [&mut serial]);
+                }
+                ota::reboot();
+            }
+        }
+    }
+}
+
+/// Writes all the bytes, polling the device while the buffer is full.
+fn write_all<B: UsbBus>(usb_dev: &mut UsbDevice<B>, serial: &mut SerialPort<B>, mut data: &[u8]) {
+    while !data.is_empty() {
+        match serial.write(data) {
+            Ok(len) => data = &data[len..],
+            Err(UsbError::WouldBlock) => {}
+            // The port went away, the host asks again
+            Err(_) => return,
+        }
+        usb_dev.poll(&mut [serial]);
+    }
+}
@@ -0,0 +1,287 @@
+//! A USB to UART adapter: the USB serial port of `e04-usb-cdc` bridged to
+//! UART1 of `e02-uart-tx` in both directions.
+//!
+//! The baud rate, the data bits, the parity and the stop bits the host
+//! sets on the port apply to UART1 right away, so a terminal talks to the
+//! device on the other side as through any adapter:
+//!
+//! ```sh
+//! picocom -b 115200 -f n -d 8 -s 1 --imap lfcr /dev/ttyACM0
+//! ```
+//!
+//! DTR and RTS of the port come out on GP14 and GP15, low while asserted
+//! as on the usual adapters, e.g. for the reset of a microcontroller.
+//! Mark and space parity and 1.5 stop bits are not supported, the UART then
+//! keeps its settings.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use panic_halt as _;
+
+use critical_section::Mutex;
+use defmt as log;
+use pico_bites::board;
+use pico_bites::settings;
+use pico_bites::uart;
+use pico_bites::usb_reset;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::dma::DMAExt;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PinState;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
+
+use embedded_hal::digital::OutputPin;
+use uart::dma::Buffers;
+use usb_device::{class_prelude::*, prelude::*};
+use usbd_serial::LineCoding;
+use usbd_serial::ParityType;
+use usbd_serial::SerialPort;
+use usbd_serial::StopBits;
+
+pico_bites::binary_info! {
+    description: "USB to UART1 bridge",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+        14 => "DTR",
+        15 => "RTS",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27de;
+
+/// A USB packet per buffer.
+const BUFFER_LEN: usize = 64;
+const BUFFERS: usize = 8;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::dma::Driver<
+    'static,
+    pac::UART1,
+    UartPins,
+    hal::dma::CH0,
+    hal::dma::CH1,
+    BUFFER_LEN,
+    BUFFERS,
+>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+
+/// Bytes read from one side and not yet taken by the other.
+struct Pending {
+    buf: [u8; BUFFER_LEN],
+    start: usize,
+    end: usize,
+}
+
+impl Pending {
+    const fn new() -> Self {
+        Self {
+            buf: [0; BUFFER_LEN],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Refills the buffer with `read` once it is empty.
+    fn fill(&mut self, read: impl FnOnce(&mut [u8]) -> usize) {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = read(&mut self.buf);
+        }
+    }
+
+    /// Hands the bytes to `write`, which returns how many it took.
+    fn drain(&mut self, write: impl FnOnce(&[u8]) -> usize) {
+        if self.start != self.end {
+            self.start += write(&self.buf[self.start..self.end]);
+        }
+    }
+}
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(115200));
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
+    );
+    let channels = pac.DMA.split(&mut pac.RESETS);
+    let buffers = cortex_m::singleton!(: Buffers<BUFFER_LEN, BUFFERS> = Buffers::new()).unwrap();
+    let (driver, mut port) = uart::dma::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        (channels.ch0, channels.ch1),
+        buffers,
+    )
+    .unwrap();
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
+
+    let mut dtr_pin = pins.gpio14.into_push_pull_output_in_state(PinState::High);
+    let mut rts_pin = pins.gpio15.into_push_pull_output_in_state(PinState::High);
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut serial = SerialPort::new(&usb_bus);
+    // No 1200 baud touch, that is a baud rate like any other here
+    let mut reset_interface = usb_reset::ResetInterface::new(&usb_bus);
+    let vid_pid = UsbVidPid(
+        store
+            .number(&settings::USB_VENDOR_ID)
+            .map_or(USB_VENDOR_ID, |id| id as u16),
+        store
+            .number(&settings::USB_PRODUCT_ID)
+            .map_or(USB_PRODUCT_ID, |id| id as u16),
+    );
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, vid_pid)
+        .composite_with_iads()
+        .build();
+
+    // The coding before the host sets one is made up, the UART keeps the
+    // stored settings until then
+    let mut line_coding = coding(serial.line_coding());
+    // The settings waiting for the bytes before them to go out
+    let mut configuring = None;
+    let mut to_uart = Pending::new();
+    let mut to_usb = Pending::new();
+    loop {
+        usb_dev.poll(&mut [&mut serial, &mut reset_interface]);
+
+        if let Some(request) = reset_interface.take_request() {
+            log::info!("Reset requested over USB");
+            usb_reset::reset(request);
+        }
+
+        if coding(serial.line_coding()) != line_coding {
+            line_coding = coding(serial.line_coding());
+            configuring = uart_config(serial.line_coding());
+            if configuring.is_none() {
+                log::warn!("Line coding not supported");
+            }
+        }
+        if configuring.is_some_and(|config| apply(config, &port)) {
+            configuring = None;
+        }
+        dtr_pin.set_state(PinState::from(!serial.dtr())).unwrap();
+        rts_pin.set_state(PinState::from(!serial.rts())).unwrap();
+
+        to_uart.fill(|buf| serial.read(buf).unwrap_or(0));
+        // What the host sent after the change waits for the new settings
+        if configuring.is_none() {
+            to_uart.drain(|bytes| port.write(bytes));
+        }
+
+        to_usb.fill(|buf| port.read(buf));
+        // Err(WouldBlock) while the host does not read
+        to_usb.drain(|bytes| serial.write(bytes).unwrap_or(0));
+    }
+}
+
+/// Compares line codings.
+fn coding(line_coding: &LineCoding) -> (u32, u8, ParityType, StopBits) {
+    (
+        line_coding.data_rate(),
+        line_coding.data_bits(),
+        line_coding.parity_type(),
+        line_coding.stop_bits(),
+    )
+}
+
+/// Switches the UART to the line coding the host set once what the host
+/// sent before went out with the old settings, returns whether it is
+/// done. Never waits, the USB device needs polling meanwhile.
+fn apply<const N: usize, const Q: usize>(
+    config: uart::Config,
+    port: &uart::dma::Port<'_, N, Q>,
+) -> bool {
+    if !port.is_flushed() {
+        return false;
+    }
+    let result = critical_section::with(|cs| {
+        UART_DRIVER
+            .borrow_ref_mut(cs)
+            .as_mut()
+            .map(|driver| driver.configure(config))
+    });
+    match result {
+        Some(Err(uart::ConfigError::Busy)) => return false,
+        Some(Ok(())) => log::info!("UART at {} baud", config.baud),
+        Some(Err(e)) => log::warn!("UART not changed: {}", log::Display2Format(&e)),
+        None => {}
+    }
+    true
+}
+
+fn uart_config(line_coding: &LineCoding) -> Option<uart::Config> {
+    let mut config = uart::Config::new(line_coding.data_rate());
+    config.data_bits = match line_coding.data_bits() {
+        5 => uart::DataBits::Five,
+        6 => uart::DataBits::Six,
+        7 => uart::DataBits::Seven,
+        8 => uart::DataBits::Eight,
+        _ => return None,
+    };
+    config.parity = match line_coding.parity_type() {
+        ParityType::None => uart::Parity::None,
+        ParityType::Even => uart::Parity::Even,
+        ParityType::Odd => uart::Parity::Odd,
+        ParityType::Mark | ParityType::Space => return None,
+    };
+    config.stop_bits = match line_coding.stop_bits() {
+        StopBits::One => uart::StopBits::One,
+        StopBits::Two => uart::StopBits::Two,
+        StopBits::OnePointFive => return None,
+    };
+    Some(config)
+}
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_dma_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_uart_interrupt();
+        }
+    });
+}
@@ -0,0 +1,293 @@
+//! Typed messages over UART1 and the USB serial port, see `pico_bites::link`.
+//!
+//! Each port runs a link of its own. The board answers a ping with a pong,
+//! switches the LED as told, logs the text it gets, and sends the count and
+//! the temperature of the chip every second. The host end is the tool in
+//! `tools/pico-link`:
+//!
+//! ```sh
+//! cd tools/pico-link && cargo run --release -- /dev/ttyACM0 listen
+//! cd tools/pico-link && cargo run --release -- /dev/ttyUSB0 led on
+//! ```
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use panic_halt as _;
+
+use critical_section::Mutex;
+use defmt as log;
+use pico_bites::board;
+use pico_bites::link;
+use pico_bites::settings;
+use pico_bites::uart;
+use pico_bites::usb_reset;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::dma::DMAExt;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PinState;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_0_2::adc::OneShot;
+use heapless::Deque;
+use link::Event;
+use link::Link;
+use link::Message;
+use uart::dma::Buffers;
+use usb_device::{class_prelude::*, prelude::*};
+use usbd_serial::SerialPort;
+
+pico_bites::binary_info! {
+    description: "Typed messages over UART1 and USB",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+        25 => "LED",
+    ],
+}
+
+const USB_VENDOR_ID: u16 = 0x16c2;
+const USB_PRODUCT_ID: u16 = 0x27d9;
+
+const REPORT_PERIOD_US: u64 = 1_000_000;
+
+const BUFFER_LEN: usize = 64;
+/// Room for the longest frame and the ACKs.
+const BUFFERS: usize = 8;
+/// The frames waiting for the host to read the USB serial port.
+const USB_OUT_LEN: usize = 2 * link::MAX_ENCODED;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::dma::Driver<
+    'static,
+    pac::UART1,
+    UartPins,
+    hal::dma::CH0,
+    hal::dma::CH1,
+    BUFFER_LEN,
+    BUFFERS,
+>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+
+/// One end of a link and the messages it has yet to send.
+struct Peer {
+    name: &'static str,
+    link: Link,
+    outbox: Deque<Message<'static>, 4>,
+}
+
+impl Peer {
+    fn new(name: &'static str, session: u32) -> Self {
+        Self {
+            name,
+            link: Link::new(link::Config::default(), session),
+            outbox: Deque::new(),
+        }
+    }
+
+    /// Queues a message, drops it while the other side does not keep up.
+    fn post(&mut self, message: Message<'static>) {
+        if self.outbox.push_back(message).is_err() {
+            log::debug!("{}: outbox full", self.name);
+        }
+    }
+
+    /// Takes the received bytes, writes the frames in response.
+    fn receive(
+        &mut self,
+        bytes: &[u8],
+        led_pin: &mut impl OutputPin,
+        mut write: impl FnMut(&[u8]),
+    ) {
+        for &byte in bytes {
+            match self.link.push(byte, &mut write) {
+                Some(Event::Message(Message::Ping(number))) => self.post(Message::Pong(number)),
+                Some(Event::Message(Message::Led(on))) => {
+                    led_pin.set_state(PinState::from(on)).unwrap();
+                }
+                Some(Event::Message(Message::Text(text))) => log::info!("{}: {}", self.name, text),
+                Some(Event::Message(message)) => {
+                    log::info!("{}: {}", self.name, log::Debug2Format(&message));
+                }
+                Some(Event::Dropped(e)) => {
+                    log::warn!("{}: dropped a frame, {}", self.name, log::Debug2Format(&e));
+                }
+                Some(Event::Acked | Event::Failed) | None => {}
+            }
+        }
+    }
+
+    /// Sends the next message once the one before is through, and sends
+    /// again the one the ACK is late for.
+    fn poll(&mut self, now_us: u64, mut write: impl FnMut(&[u8])) {
+        if let Some(Event::Failed) = self.link.poll(now_us, &mut write) {
+            log::warn!("{}: no ACK", self.name);
+        }
+        if self.link.is_ready() {
+            if let Some(message) = self.outbox.pop_front() {
+                // Neither busy nor too long
+                self.link.send(&message, now_us, &mut write).unwrap();
+            }
+        }
+    }
+}
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(115200));
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
+    );
+    let channels = pac.DMA.split(&mut pac.RESETS);
+    let buffers = cortex_m::singleton!(: Buffers<BUFFER_LEN, BUFFERS> = Buffers::new()).unwrap();
+    let (driver, mut port) = uart::dma::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        (channels.ch0, channels.ch1),
+        buffers,
+    )
+    .unwrap();
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
+
+    let mut led_pin = pins.led.into_push_pull_output();
+    let mut adc = hal::adc::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temperature_sensor = adc.take_temp_sensor().unwrap();
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    let mut serial = SerialPort::new(&usb_bus);
+    let mut reset_interface = usb_reset::ResetInterface::new(&usb_bus);
+    let vid_pid = UsbVidPid(
+        store
+            .number(&settings::USB_VENDOR_ID)
+            .map_or(USB_VENDOR_ID, |id| id as u16),
+        store
+            .number(&settings::USB_PRODUCT_ID)
+            .map_or(USB_PRODUCT_ID, |id| id as u16),
+    );
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, vid_pid)
+        .composite_with_iads()
+        .build();
+
+    // The sessions tell the host this start from the ones before
+    let rosc = hal::rosc::RingOscillator::new(pac.ROSC).initialize();
+    let random_u32 = || (0..32).fold(0, |n, _| n << 1 | u32::from(rosc.get_random_bit()));
+    let mut uart_peer = Peer::new("UART", random_u32());
+    let mut usb_peer = Peer::new("USB", random_u32());
+    let mut usb_out = Deque::<u8, USB_OUT_LEN>::new();
+    let mut baud_touch = usb_reset::BaudTouch::new();
+    let mut count = 0u32;
+    let mut reported_at = timer.get_counter().ticks();
+    loop {
+        let polled = usb_dev.poll(&mut [&mut serial, &mut reset_interface]);
+
+        if let Some(request) = reset_interface.take_request() {
+            log::info!("Reset requested over USB");
+            usb_reset::reset(request);
+        }
+        if baud_touch.poll(polled, serial.line_coding().data_rate(), serial.dtr()) {
+            log::info!("1200 baud touch, rebooting into BOOTSEL");
+            hal::rom_data::reset_to_usb_boot(0, 0);
+        }
+
+        let now_us = timer.get_counter().ticks();
+        if now_us.wrapping_sub(reported_at) >= REPORT_PERIOD_US {
+            reported_at = now_us;
+            count = count.wrapping_add(1);
+            let raw: u16 = adc.read(&mut temperature_sensor).unwrap();
+            let temperature = tenths_of_celsius(raw);
+            for peer in [&mut uart_peer, &mut usb_peer] {
+                peer.post(Message::Counter(count));
+                peer.post(Message::Temperature(temperature));
+            }
+        }
+
+        let mut buf = [0u8; BUFFER_LEN];
+        let len = port.read(&mut buf);
+        uart_peer.receive(&buf[..len], &mut led_pin, |frame| port.write_all(frame));
+        uart_peer.poll(now_us, |frame| port.write_all(frame));
+
+        let len = serial.read(&mut buf).unwrap_or(0);
+        usb_peer.receive(&buf[..len], &mut led_pin, |frame| {
+            queue(&mut usb_out, frame)
+        });
+        usb_peer.poll(now_us, |frame| queue(&mut usb_out, frame));
+
+        let (bytes, _) = usb_out.as_slices();
+        // Err(WouldBlock) while the host does not read
+        let written = serial.write(bytes).unwrap_or(0);
+        for _ in 0..written {
+            usb_out.pop_front();
+        }
+    }
+}
+
+/// Queues a whole frame for the USB serial port, or drops it if it does
+/// not fit: the link sends it again.
+fn queue(out: &mut Deque<u8, USB_OUT_LEN>, frame: &[u8]) {
+    if out.capacity() - out.len() >= frame.len() {
+        for &byte in frame {
+            out.push_back(byte).unwrap();
+        }
+    }
+}
+
+/// The temperature sensor reads 0.706 V at 27 °C and drops 1.721 mV per
+/// degree, the ADC reference is 3.3 V.
+fn tenths_of_celsius(raw: u16) -> i32 {
+    let millivolts = i32::from(raw) * 3300 / 4096;
+    270 - (millivolts - 706) * 10_000 / 1721
+}
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_dma_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_uart_interrupt();
+        }
+    });
+}
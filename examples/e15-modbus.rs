@@ -0,0 +1,300 @@
+//! A Modbus RTU server on UART1, for the test rigs.
+//!
+//! | Address | Coil      | Discrete input | Input register            |
+//! |---------|-----------|----------------|---------------------------|
+//! | 0       | LED, GP25 | GP18           | ADC0, GP26, raw           |
+//! | 1       | GP10      | GP19           | ADC1, GP27, raw           |
+//! | 2       | GP11      | GP20           | ADC2, GP28, raw           |
+//! | 3       | GP12      | GP21           | temperature sensor, raw   |
+//! | 4       | GP13      |                | temperature, 0.1 °C, signed |
+//!
+//! The inputs are pulled down. The server answers at address 1 unless
+//! `modbus.addr` is set, at 19200 baud 8E1 unless `uart.baud` is set, see
+//! `e07-usb-shell`. With an RS-485 transceiver in auto-direction mode on
+//! GP8/GP9, or straight from a USB adapter:
+//!
+//! ```sh
+//! mbpoll -m rtu -a 1 -b 19200 -P even -t 0 -r 1 -c 5 /dev/ttyUSB0 1 0 1 0 1
+//! mbpoll -m rtu -a 1 -b 19200 -P even -t 3 -r 1 -c 5 -1 /dev/ttyUSB0
+//! ```
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use panic_halt as _;
+
+use critical_section::Mutex;
+use defmt as log;
+use heapless::spsc::Producer;
+use heapless::spsc::Queue;
+use pico_bites::board;
+use pico_bites::modbus;
+use pico_bites::settings;
+use pico_bites::uart;
+
+use board::hal;
+use hal::adc::AdcPin;
+use hal::clocks::Clock;
+use hal::gpio::DynFunction;
+use hal::gpio::DynPinId;
+use hal::gpio::FunctionSioInput;
+use hal::gpio::FunctionSioOutput;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PinState;
+use hal::gpio::PullDown;
+use hal::gpio::PullNone;
+use hal::pac;
+use hal::pac::interrupt;
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::digital::StatefulOutputPin;
+use embedded_hal_0_2::adc::OneShot;
+use modbus::Exception;
+
+pico_bites::binary_info! {
+    description: "Modbus RTU server on UART1",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX",
+        10 => "Coil 1",
+        11 => "Coil 2",
+        12 => "Coil 3",
+        13 => "Coil 4",
+        18 => "Discrete input 0",
+        19 => "Discrete input 1",
+        20 => "Discrete input 2",
+        21 => "Discrete input 3",
+        25 => "Coil 0, LED",
+        26 => "Input register 0",
+        27 => "Input register 1",
+        28 => "Input register 2",
+    ],
+}
+
+const BAUD_RATE: u32 = 19_200;
+const ADDRESS: u8 = 1;
+
+const QUEUE_LEN: usize = 256;
+
+type UartPins = (
+    Pin<hal::gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<hal::gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::Driver<'static, pac::UART1, UartPins, QUEUE_LEN, QUEUE_LEN>;
+
+/// The UART driver and the arrival times of the bytes it received, in the
+/// order of the RX queue.
+struct TimedDriver {
+    driver: UartDriver,
+    timer: hal::Timer,
+    arrivals: Producer<'static, u64, QUEUE_LEN>,
+}
+
+static UART_DRIVER: Mutex<RefCell<Option<TimedDriver>>> = Mutex::new(RefCell::new(None));
+
+/// The pins and the ADC behind the Modbus addresses.
+struct Rig {
+    coils: [Pin<DynPinId, FunctionSioOutput, PullDown>; 5],
+    inputs: [Pin<DynPinId, FunctionSioInput, PullDown>; 4],
+    adc: hal::adc::Adc,
+    adc_pins: [AdcPin<Pin<DynPinId, DynFunction, PullNone>>; 3],
+    temperature_sensor: hal::adc::TempSense,
+}
+
+impl Rig {
+    fn read_temperature_sensor(&mut self) -> Result<u16, Exception> {
+        self.adc
+            .read(&mut self.temperature_sensor)
+            .map_err(|_| Exception::ServerDeviceFailure)
+    }
+}
+
+impl modbus::Device for Rig {
+    fn coils(&self) -> u16 {
+        self.coils.len() as u16
+    }
+
+    fn coil(&mut self, index: u16) -> bool {
+        self.coils[usize::from(index)].is_set_high().unwrap()
+    }
+
+    fn set_coil(&mut self, index: u16, on: bool) {
+        self.coils[usize::from(index)]
+            .set_state(PinState::from(on))
+            .unwrap();
+    }
+
+    fn discrete_inputs(&self) -> u16 {
+        self.inputs.len() as u16
+    }
+
+    fn discrete_input(&mut self, index: u16) -> bool {
+        self.inputs[usize::from(index)].is_high().unwrap()
+    }
+
+    fn input_registers(&self) -> u16 {
+        self.adc_pins.len() as u16 + 2
+    }
+
+    fn input_register(&mut self, index: u16) -> Result<u16, Exception> {
+        match usize::from(index) {
+            channel @ 0..=2 => self
+                .adc
+                .read(&mut self.adc_pins[channel])
+                .map_err(|_| Exception::ServerDeviceFailure),
+            3 => self.read_temperature_sensor(),
+            _ => Ok(tenths_of_celsius(self.read_temperature_sensor()?) as u16),
+        }
+    }
+}
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        timer,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
+    );
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let mut config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(BAUD_RATE));
+    config.parity = uart::Parity::Even;
+    let address = store
+        .number(&settings::MODBUS_ADDRESS)
+        .map_or(ADDRESS, |address| address as u8);
+
+    let rx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let tx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let arrivals = cortex_m::singleton!(: Queue<u64, QUEUE_LEN> = Queue::new()).unwrap();
+    let (arrivals, mut received_at) = arrivals.split();
+    let (mut driver, mut port) = uart::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        pac::Interrupt::UART1_IRQ,
+        rx_queue,
+        tx_queue,
+    )
+    .unwrap();
+    // An interrupt for each byte to time it, nothing sent yet
+    driver.set_fifos(false).unwrap();
+    critical_section::with(|cs| {
+        UART_DRIVER.borrow_ref_mut(cs).replace(TimedDriver {
+            driver,
+            timer,
+            arrivals,
+        })
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+    }
+
+    let mut adc = hal::adc::Adc::new(pac.ADC, &mut pac.RESETS);
+    let temperature_sensor = adc.take_temp_sensor().unwrap();
+    let mut rig = Rig {
+        coils: [
+            pins.led.into_push_pull_output().into_dyn_pin(),
+            pins.gpio10.into_push_pull_output().into_dyn_pin(),
+            pins.gpio11.into_push_pull_output().into_dyn_pin(),
+            pins.gpio12.into_push_pull_output().into_dyn_pin(),
+            pins.gpio13.into_push_pull_output().into_dyn_pin(),
+        ],
+        inputs: [
+            pins.gpio18.into_pull_down_input().into_dyn_pin(),
+            pins.gpio19.into_pull_down_input().into_dyn_pin(),
+            pins.gpio20.into_pull_down_input().into_dyn_pin(),
+            pins.gpio21.into_pull_down_input().into_dyn_pin(),
+        ],
+        adc,
+        adc_pins: [
+            AdcPin::new(
+                pins.gpio26
+                    .into_floating_input()
+                    .into_function::<DynFunction>()
+                    .into_dyn_pin(),
+            )
+            .unwrap(),
+            AdcPin::new(
+                pins.gpio27
+                    .into_floating_input()
+                    .into_function::<DynFunction>()
+                    .into_dyn_pin(),
+            )
+            .unwrap(),
+            AdcPin::new(
+                pins.gpio28
+                    .into_floating_input()
+                    .into_function::<DynFunction>()
+                    .into_dyn_pin(),
+            )
+            .unwrap(),
+        ],
+        temperature_sensor,
+    };
+
+    log::info!(
+        "Modbus server at address {}, {}",
+        address,
+        log::Display2Format(&config)
+    );
+    let mut framer = modbus::Framer::new(modbus::frame_gap_us(&config));
+    let mut response = [0u8; modbus::MAX_ADU];
+    loop {
+        // Read first, a byte not in the queue yet came after it
+        let now_us = timer.get_counter().ticks();
+        let byte = received_at.dequeue().map(|at| {
+            // In the RX queue before its time was
+            let mut byte = [0u8];
+            port.read(&mut byte);
+            (byte[0], at)
+        });
+
+        // The frame ends at the gap before the next byte, or with the
+        // silence up to now
+        let polled_at = byte.map_or(now_us, |(_, at)| at);
+        if let Some(request) = framer.poll(polled_at) {
+            log::debug!("Request {=[u8]:x}", request);
+            if let Some(len) = modbus::respond(address, &mut rig, request, &mut response) {
+                port.write_all(&response[..len]);
+            }
+        }
+        if let Some((byte, at)) = byte {
+            framer.push(byte, at);
+        }
+    }
+}
+
+/// The temperature sensor reads 0.706 V at 27 °C and drops 1.721 mV per
+/// degree, the ADC reference is 3.3 V.
+fn tenths_of_celsius(raw: u16) -> i16 {
+    let millivolts = i32::from(raw) * 3300 / 4096;
+    (270 - (millivolts - 706) * 10_000 / 1721) as i16
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(uart) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            let received = uart.driver.received();
+            uart.driver.on_interrupt();
+            // Without the FIFOs this runs within a character of each byte
+            let now_us = uart.timer.get_counter().ticks();
+            for _ in 0..uart.driver.received().wrapping_sub(received) {
+                // Never fuller than the RX queue, which took the byte
+                uart.arrivals.enqueue(now_us).ok();
+            }
+        }
+    });
+}
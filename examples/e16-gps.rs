@@ -0,0 +1,302 @@
+//! Shows the fix of a GPS receiver on the ST7789 display of
+//! `e05-lcd-st7789`, and keeps the RTC on the GPS time.
+//!
+//! The receiver sends NMEA sentences to UART1 RX on GP9, at 9600 baud
+//! unless `uart.baud` is set (see `e07-usb-shell`). Its PPS output on GP10
+//! is optional: the receivers tell the time of a pulse in the sentences
+//! after it, so at the next pulse the RTC is set to one second later,
+//! restarting its count of the second with the pulse. Without the pulses
+//! the RTC is set as the sentences come, a fraction of a second late.
+#![no_std]
+#![no_main]
+
+use core::cell::Cell;
+use core::cell::RefCell;
+use core::fmt::Write;
+
+use panic_halt as _;
+
+use critical_section::Mutex;
+use defmt as log;
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::RgbColor;
+use fugit::RateExtU32;
+use heapless::spsc::Queue;
+use mipidsi::models::ST7789;
+use mipidsi::ColorInversion;
+use mipidsi::Orientation;
+use pico_bites::board;
+use pico_bites::console::Console;
+use pico_bites::line::LineBuffer;
+use pico_bites::nmea;
+use pico_bites::settings;
+use pico_bites::uart;
+
+use board::hal;
+use hal::gpio;
+use hal::gpio::FunctionSioInput;
+use hal::gpio::FunctionSpi;
+use hal::gpio::FunctionUart;
+use hal::gpio::Pin;
+use hal::gpio::PullDown;
+use hal::pac;
+use hal::pac::interrupt;
+use hal::rtc::DateTime;
+use hal::rtc::DayOfWeek;
+use hal::rtc::RealTimeClock;
+use hal::Clock;
+
+pico_bites::binary_info! {
+    description: "GPS fix on the ST7789 display",
+    pins: [
+        8 => "UART1 TX",
+        9 => "UART1 RX, GPS TX",
+        10 => "GPS PPS",
+        16 => "LCD MISO",
+        17 => "LCD CS",
+        18 => "LCD SCK",
+        19 => "LCD MOSI",
+        20 => "LCD BL",
+        21 => "LCD RST",
+        22 => "LCD DC",
+    ],
+}
+
+const DISPLAY_WIDTH: u16 = 240;
+const DISPLAY_HEIGHT: u16 = 320;
+
+const BAUD_RATE: u32 = 9600;
+const QUEUE_LEN: usize = 256;
+
+/// Where the RTC starts until the GPS tells the time.
+const START_DATETIME: DateTime = DateTime {
+    year: 2000,
+    month: 1,
+    day: 1,
+    day_of_week: DayOfWeek::Saturday,
+    hour: 0,
+    minute: 0,
+    second: 0,
+};
+
+type UartPins = (
+    Pin<gpio::bank0::Gpio8, FunctionUart, PullDown>,
+    Pin<gpio::bank0::Gpio9, FunctionUart, PullDown>,
+);
+type UartDriver = uart::Driver<'static, pac::UART1, UartPins, QUEUE_LEN, QUEUE_LEN>;
+type PpsPin = Pin<gpio::bank0::Gpio10, FunctionSioInput, PullDown>;
+
+static UART_DRIVER: Mutex<RefCell<Option<UartDriver>>> = Mutex::new(RefCell::new(None));
+static PPS_PIN: Mutex<RefCell<Option<PpsPin>>> = Mutex::new(RefCell::new(None));
+/// Set by the rising edge of the PPS, taken by the main loop.
+static PULSED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut delay,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    let cs = pins
+        .gpio17
+        .into_push_pull_output_in_state(gpio::PinState::High);
+    let dc = pins.gpio22.into_push_pull_output();
+    let reset = pins
+        .gpio21
+        .into_push_pull_output_in_state(gpio::PinState::High);
+    let _bl = pins
+        .gpio20
+        .into_push_pull_output_in_state(gpio::PinState::High);
+
+    let mosi = pins.gpio19.into_function::<FunctionSpi>();
+    let miso = pins.gpio16.into_function::<FunctionSpi>();
+    let sclk = pins.gpio18.into_function::<FunctionSpi>();
+
+    let spi = hal::spi::Spi::<_, _, _, 8>::new(pac.SPI0, (mosi, miso, sclk));
+
+    let mut display = mipidsi::Builder::with_model(
+        display_interface_spi::SPIInterface::new(
+            spi.init(
+                &mut pac.RESETS,
+                clocks.peripheral_clock.freq(),
+                62u32.MHz(),
+                embedded_hal::spi::MODE_3,
+            ),
+            dc,
+            cs,
+        ),
+        ST7789,
+    )
+    .with_color_order(mipidsi::ColorOrder::Rgb)
+    .with_invert_colors(ColorInversion::Inverted)
+    .with_orientation(Orientation::Portrait(false))
+    .with_display_size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    .with_framebuffer_size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    .init(&mut delay, Some(reset))
+    .unwrap();
+    display.set_scroll_region(0, DISPLAY_HEIGHT, 0).unwrap();
+
+    let mut console = Console::new(
+        display,
+        &FONT_10X20,
+        Rgb565::GREEN,
+        Rgb565::BLACK,
+        |display, offset| display.set_scroll_offset(offset),
+    )
+    .unwrap();
+    write!(console, "Waiting for the GPS").unwrap();
+
+    let uart_pins = (
+        pins.gpio8.into_function::<FunctionUart>(),
+        pins.gpio9.into_function::<FunctionUart>(),
+    );
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(BAUD_RATE));
+
+    let rx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let tx_queue = cortex_m::singleton!(: Queue<u8, QUEUE_LEN> = Queue::new()).unwrap();
+    let (driver, mut port) = uart::split(
+        hal::uart::UartPeripheral::new(pac.UART1, uart_pins, &mut pac.RESETS),
+        config,
+        clocks.peripheral_clock.freq(),
+        pac::Interrupt::UART1_IRQ,
+        rx_queue,
+        tx_queue,
+    )
+    .unwrap();
+    critical_section::with(|cs| UART_DRIVER.borrow_ref_mut(cs).replace(driver));
+
+    let pps_pin = pins.gpio10.into_pull_down_input();
+    pps_pin.set_interrupt_enabled(gpio::Interrupt::EdgeHigh, true);
+    critical_section::with(|cs| PPS_PIN.borrow_ref_mut(cs).replace(pps_pin));
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::UART1_IRQ);
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    let mut rtc =
+        RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, START_DATETIME).unwrap();
+
+    let mut line = LineBuffer::<{ nmea::MAX_SENTENCE }>::new();
+    let mut fix = nmea::Fix::default();
+    // The time of the last pulse, from the sentences after it
+    let mut pulse_time = None;
+    let mut pulses = false;
+    loop {
+        while let Some(sentence) = port.read_line(&mut line) {
+            let sentence = match nmea::parse(sentence) {
+                Ok(sentence) => sentence,
+                Err(nmea::Error::Unsupported) => continue,
+                Err(e) => {
+                    log::warn!("{}: {=[u8]:a}", log::Display2Format(&e), sentence);
+                    continue;
+                }
+            };
+            fix.update(&sentence);
+
+            match sentence {
+                nmea::Sentence::Gga(_) => show(&mut console, &fix),
+                nmea::Sentence::Rmc(rmc) if rmc.valid => {
+                    let Some((date, time)) = rmc.date.zip(rmc.time) else {
+                        continue;
+                    };
+                    if pulses {
+                        pulse_time = Some((date, time));
+                    } else {
+                        set_rtc(&mut rtc, date, time);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if critical_section::with(|cs| PULSED.borrow(cs).replace(false)) {
+            pulses = true;
+            // Nothing set for a pulse the sentences did not tell the time of
+            if let Some((date, time)) = pulse_time.take() {
+                let (date, time) = nmea::next_second(date, time);
+                set_rtc(&mut rtc, date, time);
+                log::debug!("RTC set to {}", log::Display2Format(&time));
+            }
+        }
+
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Adds the time, the satellites and the position to the console.
+fn show(console: &mut impl Write, fix: &nmea::Fix) {
+    let time = fix.time.unwrap_or_default();
+    write!(
+        console,
+        "\n{time} {} of {} sats",
+        fix.satellites_used, fix.satellites_in_view
+    )
+    .unwrap();
+    match fix.position {
+        Some(position) => {
+            write!(console, "\n{position}").unwrap();
+            let altitude = fix.altitude_mm.unwrap_or(0) / 1000;
+            let speed = fix.speed_mm_s.unwrap_or(0) * 36 / 10_000;
+            write!(console, "\n{altitude} m {speed} km/h").unwrap();
+        }
+        None => write!(console, "\nNo fix").unwrap(),
+    }
+}
+
+/// Sets the RTC, unless the receiver sent a date out of the calendar.
+fn set_rtc(rtc: &mut RealTimeClock, date: nmea::Date, time: nmea::Time) {
+    match rtc_datetime(date, time) {
+        Some(datetime) => rtc.set_datetime(datetime).unwrap(),
+        None => log::warn!("Invalid date {}", log::Display2Format(&date)),
+    }
+}
+
+fn rtc_datetime(date: nmea::Date, time: nmea::Time) -> Option<DateTime> {
+    const DAYS: [DayOfWeek; 7] = [
+        DayOfWeek::Sunday,
+        DayOfWeek::Monday,
+        DayOfWeek::Tuesday,
+        DayOfWeek::Wednesday,
+        DayOfWeek::Thursday,
+        DayOfWeek::Friday,
+        DayOfWeek::Saturday,
+    ];
+    // The RTC has no leap seconds
+    Some(DateTime {
+        year: date.year,
+        month: date.month,
+        day: date.day,
+        day_of_week: DAYS[usize::from(date.day_of_week()?)],
+        hour: time.hour,
+        minute: time.minute,
+        second: time.second.min(59),
+    })
+}
+
+#[interrupt]
+fn UART1_IRQ() {
+    critical_section::with(|cs| {
+        if let Some(driver) = UART_DRIVER.borrow_ref_mut(cs).as_mut() {
+            driver.on_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        if let Some(pin) = PPS_PIN.borrow_ref_mut(cs).as_mut() {
+            pin.clear_interrupt(gpio::Interrupt::EdgeHigh);
+            PULSED.borrow(cs).set(true);
+        }
+    });
+}
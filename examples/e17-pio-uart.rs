@@ -0,0 +1,77 @@
+//! Bridges two serial devices through two UARTs on PIO0.
+//!
+//! What comes in on one UART goes out of the other, and is logged. At
+//! 115200 baud 8N1 unless `uart.baud` is set, see `e07-usb-shell`; UART1
+//! stays free for `e02-uart-tx` and the like.
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_halt as _;
+
+use defmt as log;
+use embedded_io::Read;
+use embedded_io::ReadReady;
+use pico_bites::board;
+use pico_bites::settings;
+use pico_bites::uart;
+
+use board::hal;
+use hal::clocks::Clock;
+use hal::pio::PIOExt;
+
+pico_bites::binary_info! {
+    description: "Bridges two PIO UARTs",
+    pins: [
+        2 => "PIO UART A TX",
+        3 => "PIO UART A RX",
+        4 => "PIO UART B TX",
+        5 => "PIO UART B RX",
+    ],
+}
+
+const BAUD_RATE: u32 = 115_200;
+
+#[hal::entry]
+fn main() -> ! {
+    log::info!("Running");
+
+    let board::Board {
+        clocks,
+        pins,
+        mut pac,
+        ..
+    } = board::Board::take();
+
+    // SAFETY: loaded once, see settings::load
+    let store = unsafe { settings::load() }.unwrap();
+    let config = uart::Config::new(store.number(&settings::UART_BAUD).unwrap_or(BAUD_RATE));
+    let clock = clocks.system_clock.freq();
+
+    let (mut pio, sm0, sm1, sm2, sm3) = pac.PIO0.split(&mut pac.RESETS);
+    let programs = uart::pio::Programs::install(&mut pio).unwrap();
+    let mut a_tx = uart::pio::Tx::new(&programs, sm0, pins.gpio2, config, clock).unwrap();
+    let mut a_rx = uart::pio::Rx::new(&programs, sm1, pins.gpio3, config, clock).unwrap();
+    let mut b_tx = uart::pio::Tx::new(&programs, sm2, pins.gpio4, config, clock).unwrap();
+    let mut b_rx = uart::pio::Rx::new(&programs, sm3, pins.gpio5, config, clock).unwrap();
+
+    writeln!(a_tx, "Bridged to PIO UART B at {config}\r").unwrap();
+    writeln!(b_tx, "Bridged to PIO UART A at {config}\r").unwrap();
+    log::info!("PIO UARTs at {}", log::Display2Format(&config));
+
+    // The RX FIFOs hold 8 characters, polled all the time
+    let mut buf = [0u8; 8];
+    loop {
+        if a_rx.read_ready().unwrap() {
+            let len = a_rx.read(&mut buf).unwrap();
+            b_tx.write_all(&buf[..len]);
+            log::debug!("A: {=[u8]:a}", &buf[..len]);
+        }
+        if b_rx.read_ready().unwrap() {
+            let len = b_rx.read(&mut buf).unwrap();
+            a_tx.write_all(&buf[..len]);
+            log::debug!("B: {=[u8]:a}", &buf[..len]);
+        }
+    }
+}
@@ -1,15 +1,80 @@
 MEMORY {
-    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
-    RAM   : ORIGIN = 0x20000000, LENGTH = 256K
+    /* The firmware slots, see `pico_bites::ota`. Both start with boot2
+     * and the image is linked for one of them, build.rs makes BOOT2 and
+     * FLASH the regions of slot B when PICO_BITES_SLOT=b */
+    BOOT2_A : ORIGIN = 0x10000000, LENGTH = 0x100
+    FLASH_A : ORIGIN = 0x10000100, LENGTH = 892K - 0x100
+    BOOT2_B : ORIGIN = 0x10000000 + 892K, LENGTH = 0x100
+    FLASH_B : ORIGIN = 0x10000100 + 892K, LENGTH = 892K - 0x100
+    /* Which slot runs, see `pico_bites::ota::StateStore` */
+    OTA_STATE : ORIGIN = 0x10000000 + 2048K - 256K - 8K, LENGTH = 8K
+    /* The USB drive, see `pico_bites::msc` */
+    DISK  : ORIGIN = 0x10000000 + 2048K - 256K, LENGTH = 240K
+    /* See `pico_bites::settings` */
+    SETTINGS : ORIGIN = 0x10000000 + 2048K - 16K, LENGTH = 16K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 256K - 1K
+    /* Survives the watchdog reset, see `pico_bites::crash` */
+    CRASH : ORIGIN = 0x20000000 + 256K - 1K, LENGTH = 1K
 }
 
+INCLUDE slot.x
+
 EXTERN(BOOT2_FIRMWARE)
 
+__slot_a_start = ORIGIN(BOOT2_A);
+__slot_b_start = ORIGIN(BOOT2_B);
+__slot_b_end = ORIGIN(FLASH_B) + LENGTH(FLASH_B);
+__ota_state_start = ORIGIN(OTA_STATE);
+__ota_state_end = ORIGIN(OTA_STATE) + LENGTH(OTA_STATE);
+__disk_start = ORIGIN(DISK);
+__disk_end = ORIGIN(DISK) + LENGTH(DISK);
+__settings_start = ORIGIN(SETTINGS);
+__settings_end = ORIGIN(SETTINGS) + LENGTH(SETTINGS);
+
 SECTIONS {
     /* ### Boot loader */
     .boot2 ORIGIN(BOOT2) :
     {
         KEEP(*(.boot2));
     } > BOOT2
-} INSERT BEFORE .text;
\ No newline at end of file
+} INSERT BEFORE .text;
+
+SECTIONS {
+    /* ### Crash report, neither zeroed nor initialised at startup */
+    .crash_report (NOLOAD) : ALIGN(8)
+    {
+        KEEP(*(.crash_report));
+    } > CRASH
+} INSERT AFTER .uninit;
+
+SECTIONS {
+    /* ### Picotool binary info header
+     *
+     * Goes after .vector_table, to keep it in the first 256 bytes of the
+     * image, where picotool looks for it
+     */
+    .boot_info : ALIGN(4)
+    {
+        KEEP(*(.boot_info));
+    } > FLASH
+} INSERT AFTER .vector_table;
+
+/* Move .text to start after the binary info header */
+_stext = ADDR(.boot_info) + SIZEOF(.boot_info);
+
+SECTIONS {
+    /* ### Picotool binary info entries, see `pico_bites::binary_info` */
+    .bi_entries : ALIGN(4)
+    {
+        __bi_entries_start = .;
+        KEEP(*(.bi_entries));
+        . = ALIGN(4);
+        __bi_entries_end = .;
+    } > FLASH
+} INSERT AFTER .text;
+
+SECTIONS {
+    .flash_end : {
+        __flash_binary_end = .;
+    } > FLASH
+} INSERT AFTER .uninit;
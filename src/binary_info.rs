@@ -0,0 +1,339 @@
+//! Metadata `picotool info` reads from the image without running it.
+//!
+//! `memory.x` puts a [`Header`] right after the vector table, where picotool
+//! looks for it, and collects the entries the header points to in
+//! `.bi_entries`. The [`binary_info!`](crate::binary_info!) macro emits both,
+//! with the program name, the description, the version and build details of
+//! [`crate::firmware`] and the pins the example uses:
+//!
+//! ```ignore
+//! pico_bites::binary_info! {
+//!     description: "Writes message to UART1",
+//!     pins: [8 => "UART1 TX", 9 => "UART1 RX"],
+//! }
+//! ```
+//!
+//! The layout follows `binary_info/structure.h` of the Pico SDK.
+
+use crate::firmware;
+
+const TAG_RASPBERRY_PI: u16 = u16::from_le_bytes(*b"RP");
+
+pub const ID_RP_PROGRAM_NAME: u32 = 0x02031c86;
+pub const ID_RP_PROGRAM_VERSION_STRING: u32 = 0x11a9bc3a;
+pub const ID_RP_PROGRAM_BUILD_DATE_STRING: u32 = 0x9da22254;
+pub const ID_RP_BINARY_END: u32 = 0x68f465de;
+pub const ID_RP_PROGRAM_DESCRIPTION: u32 = 0xb6a07c19;
+pub const ID_RP_PROGRAM_BUILD_ATTRIBUTE: u32 = 0x4275f0d3;
+pub const ID_RP_PICO_BOARD: u32 = 0xb63cffbb;
+
+const TYPE_ID_AND_INT: u16 = 5;
+const TYPE_ID_AND_STRING: u16 = 6;
+const TYPE_PINS_WITH_NAME: u16 = 9;
+
+/// Picotool finds the entries through this block.
+#[repr(C)]
+pub struct Header {
+    marker_start: u32,
+    entries_start: *const EntryAddr,
+    entries_end: *const EntryAddr,
+    mapping_table: *const MappingTableEntry,
+    marker_end: u32,
+}
+
+impl Header {
+    pub const fn new(
+        entries_start: *const EntryAddr,
+        entries_end: *const EntryAddr,
+        mapping_table: &'static [MappingTableEntry],
+    ) -> Self {
+        Self {
+            marker_start: 0x7188ebf2,
+            entries_start,
+            entries_end,
+            mapping_table: mapping_table.as_ptr(),
+            marker_end: 0xe71aa390,
+        }
+    }
+}
+
+/// Tells picotool where in the flash to find the data copied to the RAM,
+/// the table ends with [`MappingTableEntry::END`].
+#[repr(C)]
+pub struct MappingTableEntry {
+    source_addr_start: *const u32,
+    dest_addr_start: *const u32,
+    dest_addr_end: *const u32,
+}
+
+impl MappingTableEntry {
+    pub const END: Self = Self {
+        source_addr_start: core::ptr::null(),
+        dest_addr_start: core::ptr::null(),
+        dest_addr_end: core::ptr::null(),
+    };
+
+    pub const fn new(
+        source_addr_start: *const u32,
+        dest_addr_start: *const u32,
+        dest_addr_end: *const u32,
+    ) -> Self {
+        Self {
+            source_addr_start,
+            dest_addr_start,
+            dest_addr_end,
+        }
+    }
+}
+
+/// Address of an entry, the entry type is in its first half-word.
+#[repr(transparent)]
+pub struct EntryAddr(*const ());
+
+/// A NUL-terminated string with an id.
+#[repr(C)]
+pub struct StringEntry {
+    data_type: u16,
+    tag: u16,
+    id: u32,
+    value: *const u8,
+}
+
+impl StringEntry {
+    /// Panics at compile time if `value` is not NUL-terminated.
+    pub const fn new(id: u32, value: &'static [u8]) -> Self {
+        assert!(!value.is_empty() && value[value.len() - 1] == 0);
+
+        Self {
+            data_type: TYPE_ID_AND_STRING,
+            tag: TAG_RASPBERRY_PI,
+            id,
+            value: value.as_ptr(),
+        }
+    }
+
+    pub const fn addr(&'static self) -> EntryAddr {
+        EntryAddr(self as *const Self as *const ())
+    }
+}
+
+/// An address with an id, e.g. the end of the image.
+#[repr(C)]
+pub struct PointerEntry {
+    data_type: u16,
+    tag: u16,
+    id: u32,
+    value: *const (),
+}
+
+impl PointerEntry {
+    pub const fn new(id: u32, value: *const ()) -> Self {
+        Self {
+            data_type: TYPE_ID_AND_INT,
+            tag: TAG_RASPBERRY_PI,
+            id,
+            value,
+        }
+    }
+
+    pub const fn addr(&'static self) -> EntryAddr {
+        EntryAddr(self as *const Self as *const ())
+    }
+}
+
+/// What the GPIO pins are used for.
+#[repr(C)]
+pub struct PinsEntry {
+    data_type: u16,
+    tag: u16,
+    pin_mask: u32,
+    label: *const u8,
+}
+
+impl PinsEntry {
+    /// Panics at compile time if `label` is not NUL-terminated.
+    pub const fn new(pin_mask: u32, label: &'static [u8]) -> Self {
+        assert!(!label.is_empty() && label[label.len() - 1] == 0);
+
+        Self {
+            data_type: TYPE_PINS_WITH_NAME,
+            tag: TAG_RASPBERRY_PI,
+            pin_mask,
+            label: label.as_ptr(),
+        }
+    }
+
+    pub const fn addr(&'static self) -> EntryAddr {
+        EntryAddr(self as *const Self as *const ())
+    }
+}
+
+// Only ever built from the statics above, picotool reads them from the flash.
+unsafe impl Sync for Header {}
+unsafe impl Sync for MappingTableEntry {}
+unsafe impl Sync for EntryAddr {}
+unsafe impl Sync for StringEntry {}
+unsafe impl Sync for PointerEntry {}
+unsafe impl Sync for PinsEntry {}
+
+const VERSION_PARTS: &[&str] = &[
+    env!("CARGO_PKG_VERSION"),
+    "-",
+    firmware::GIT_COMMIT,
+    if firmware::GIT_DIRTY { "-dirty" } else { "" },
+];
+
+static VERSION: [u8; joined_len(VERSION_PARTS)] = join(VERSION_PARTS);
+static BUILD_DATE: [u8; joined_len(&[firmware::BUILD_DATE])] = join(&[firmware::BUILD_DATE]);
+static PROFILE: [u8; joined_len(&[firmware::PROFILE])] = join(&[firmware::PROFILE]);
+static BOARD: [u8; joined_len(&[firmware::BOARD])] = join(&[firmware::BOARD]);
+
+/// `0.1.0-0317e06a1b2c-dirty`
+pub static PROGRAM_VERSION: StringEntry = StringEntry::new(ID_RP_PROGRAM_VERSION_STRING, &VERSION);
+pub static PROGRAM_BUILD_DATE: StringEntry =
+    StringEntry::new(ID_RP_PROGRAM_BUILD_DATE_STRING, &BUILD_DATE);
+/// `debug` or `release`
+pub static PROGRAM_BUILD_ATTRIBUTE: StringEntry =
+    StringEntry::new(ID_RP_PROGRAM_BUILD_ATTRIBUTE, &PROFILE);
+pub static PICO_BOARD: StringEntry = StringEntry::new(ID_RP_PICO_BOARD, &BOARD);
+
+/// Length of the parts joined together and NUL-terminated.
+pub const fn joined_len(parts: &[&str]) -> usize {
+    let mut len = 1;
+    let mut i = 0;
+    while i < parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// Joins the parts into a NUL-terminated string, `N` is [`joined_len`].
+pub const fn join<const N: usize>(parts: &[&str]) -> [u8; N] {
+    assert!(N == joined_len(parts));
+
+    let mut buf = [0; N];
+    let mut pos = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let part = parts[i].as_bytes();
+        let mut j = 0;
+        while j < part.len() {
+            buf[pos] = part[j];
+            pos += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    buf
+}
+
+/// Emits the picotool header and the entries describing the example.
+///
+/// Each `pin => label` pair names what a GPIO pin is used for.
+#[macro_export]
+macro_rules! binary_info {
+    (
+        description: $description:literal
+        $(, pins: [$($pin:literal => $label:literal),* $(,)?])?
+        $(,)?
+    ) => {
+        #[cfg(all(target_arch = "arm", target_os = "none"))]
+        mod binary_info {
+            use $crate::binary_info::*;
+
+            extern "C" {
+                static __bi_entries_start: EntryAddr;
+                static __bi_entries_end: EntryAddr;
+                static __sidata: u32;
+                static __sdata: u32;
+                static __edata: u32;
+                static __flash_binary_end: u32;
+            }
+
+            #[link_section = ".boot_info"]
+            #[used]
+            #[allow(unused_unsafe)]
+            static PICOTOOL_HEADER: Header = unsafe {
+                Header::new(
+                    core::ptr::addr_of!(__bi_entries_start),
+                    core::ptr::addr_of!(__bi_entries_end),
+                    &MAPPING_TABLE,
+                )
+            };
+
+            #[allow(unused_unsafe)]
+            static MAPPING_TABLE: [MappingTableEntry; 2] = unsafe {
+                [
+                    MappingTableEntry::new(
+                        core::ptr::addr_of!(__sidata),
+                        core::ptr::addr_of!(__sdata),
+                        core::ptr::addr_of!(__edata),
+                    ),
+                    MappingTableEntry::END,
+                ]
+            };
+
+            static PROGRAM_NAME: StringEntry = StringEntry::new(
+                ID_RP_PROGRAM_NAME,
+                concat!(env!("CARGO_BIN_NAME"), "\0").as_bytes(),
+            );
+            static PROGRAM_DESCRIPTION: StringEntry = StringEntry::new(
+                ID_RP_PROGRAM_DESCRIPTION,
+                concat!($description, "\0").as_bytes(),
+            );
+            #[allow(unused_unsafe)]
+            static BINARY_END: PointerEntry = PointerEntry::new(
+                ID_RP_BINARY_END,
+                unsafe { core::ptr::addr_of!(__flash_binary_end) }.cast(),
+            );
+
+            #[link_section = ".bi_entries"]
+            #[used]
+            static PICOTOOL_ENTRIES: [EntryAddr; 7 + <[u32]>::len(&[$($($pin),*)?])] = [
+                PROGRAM_NAME.addr(),
+                PROGRAM_DESCRIPTION.addr(),
+                PROGRAM_VERSION.addr(),
+                PROGRAM_BUILD_DATE.addr(),
+                PROGRAM_BUILD_ATTRIBUTE.addr(),
+                PICO_BOARD.addr(),
+                BINARY_END.addr(),
+                $($({
+                    static PIN: PinsEntry =
+                        PinsEntry::new(1 << $pin, concat!($label, "\0").as_bytes());
+                    PIN.addr()
+                },)*)?
+            ];
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_strings() {
+        const PARTS: &[&str] = &["0.1.0", "-", "abc"];
+        let joined: [u8; joined_len(PARTS)] = join(PARTS);
+
+        assert_eq!(&joined, b"0.1.0-abc\0");
+        assert_eq!(joined_len(&[]), 1);
+    }
+
+    #[test]
+    fn describes_the_build() {
+        let version = core::ffi::CStr::from_bytes_with_nul(&VERSION).unwrap();
+        let version = version.to_str().unwrap();
+
+        assert!(version.starts_with(concat!(env!("CARGO_PKG_VERSION"), "-")));
+        assert!(version.contains(firmware::GIT_COMMIT));
+        assert_eq!(BUILD_DATE.len(), "YYYY-MM-DD\0".len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn needs_nul() {
+        PinsEntry::new(1 << 25, b"LED");
+    }
+}
@@ -0,0 +1,182 @@
+//! Board bring-up shared by the examples.
+//!
+//! The board support package is selected with the `rp-pico` (default) or
+//! `waveshare-rp2040-zero` cargo feature. [`Board::take`] (or [`Board::new`]
+//! from an RTIC `init`) runs the watchdog, clock, SIO and GPIO setup every
+//! example used to repeat, and hands back whatever was not consumed.
+
+#[cfg(all(feature = "rp-pico", feature = "waveshare-rp2040-zero"))]
+compile_error!("Features `rp-pico` and `waveshare-rp2040-zero` are mutually exclusive");
+
+#[cfg(not(any(feature = "rp-pico", feature = "waveshare-rp2040-zero")))]
+compile_error!("Select a board with either the `rp-pico` or `waveshare-rp2040-zero` feature");
+
+#[cfg(feature = "rp-pico")]
+pub use rp_pico as bsp;
+#[cfg(feature = "waveshare-rp2040-zero")]
+pub use waveshare_rp2040_zero as bsp;
+
+pub use bsp::hal;
+pub use bsp::Pins;
+pub use bsp::XOSC_CRYSTAL_FREQ;
+
+use hal::pac;
+use hal::Clock;
+
+/// Device peripherals left over after the bring-up.
+#[allow(non_snake_case)]
+pub struct Peripherals {
+    pub ADC: pac::ADC,
+    pub BUSCTRL: pac::BUSCTRL,
+    pub DMA: pac::DMA,
+    pub I2C0: pac::I2C0,
+    pub I2C1: pac::I2C1,
+    pub IO_QSPI: pac::IO_QSPI,
+    pub PADS_QSPI: pac::PADS_QSPI,
+    pub PIO0: pac::PIO0,
+    pub PIO1: pac::PIO1,
+    pub PPB: pac::PPB,
+    pub PSM: pac::PSM,
+    pub PWM: pac::PWM,
+    pub RESETS: pac::RESETS,
+    pub ROSC: pac::ROSC,
+    pub RTC: pac::RTC,
+    pub SPI0: pac::SPI0,
+    pub SPI1: pac::SPI1,
+    pub SYSCFG: pac::SYSCFG,
+    pub SYSINFO: pac::SYSINFO,
+    pub TBMAN: pac::TBMAN,
+    pub UART0: pac::UART0,
+    pub UART1: pac::UART1,
+    pub USBCTRL_DPRAM: pac::USBCTRL_DPRAM,
+    pub USBCTRL_REGS: pac::USBCTRL_REGS,
+    pub VREG_AND_CHIP_RESET: pac::VREG_AND_CHIP_RESET,
+    pub XIP_CTRL: pac::XIP_CTRL,
+    pub XIP_SSI: pac::XIP_SSI,
+}
+
+/// Core peripherals left over after the bring-up, `SYST` drives [`Board::delay`].
+#[allow(non_snake_case)]
+pub struct CorePeripherals {
+    pub CPUID: pac::CPUID,
+    pub DCB: pac::DCB,
+    pub DWT: pac::DWT,
+    pub MPU: pac::MPU,
+    pub NVIC: pac::NVIC,
+    pub SCB: pac::SCB,
+}
+
+/// SIO parts left over after the bring-up, `gpio_bank0` drives [`Board::pins`].
+pub struct SioParts {
+    pub gpio_qspi: hal::sio::SioGpioQspi,
+    pub hwdivider: hal::sio::HwDivider,
+    pub fifo: hal::sio::SioFifo,
+    pub interp0: hal::sio::Interp0,
+    pub interp1: hal::sio::Interp1,
+}
+
+/// Everything an example needs to start with.
+pub struct Board {
+    pub clocks: hal::clocks::ClocksManager,
+    pub pins: Pins,
+    pub delay: cortex_m::delay::Delay,
+    pub timer: hal::Timer,
+    pub watchdog: hal::Watchdog,
+    pub pac: Peripherals,
+    pub core: CorePeripherals,
+    pub sio: SioParts,
+}
+
+impl Board {
+    /// Takes the peripheral singletons and brings the board up.
+    ///
+    /// Panics if the peripherals have already been taken.
+    pub fn take() -> Self {
+        let pac = pac::Peripherals::take().unwrap();
+        let core = pac::CorePeripherals::take().unwrap();
+
+        Self::new(pac, core)
+    }
+
+    /// Brings the board up from the peripherals RTIC passes to `init`.
+    ///
+    /// The system clock runs at 125 MHz, the peripheral clock follows it,
+    /// and the watchdog ticks every microsecond.
+    pub fn new(mut pac: pac::Peripherals, core: pac::CorePeripherals) -> Self {
+        let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+        let clocks = hal::clocks::init_clocks_and_plls(
+            XOSC_CRYSTAL_FREQ,
+            pac.XOSC,
+            pac.CLOCKS,
+            pac.PLL_SYS,
+            pac.PLL_USB,
+            &mut pac.RESETS,
+            &mut watchdog,
+        )
+        .ok()
+        .unwrap();
+
+        let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+        let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+
+        let sio = hal::Sio::new(pac.SIO);
+        let pins = Pins::new(
+            pac.IO_BANK0,
+            pac.PADS_BANK0,
+            sio.gpio_bank0,
+            &mut pac.RESETS,
+        );
+
+        Self {
+            clocks,
+            pins,
+            delay,
+            timer,
+            watchdog,
+            pac: Peripherals {
+                ADC: pac.ADC,
+                BUSCTRL: pac.BUSCTRL,
+                DMA: pac.DMA,
+                I2C0: pac.I2C0,
+                I2C1: pac.I2C1,
+                IO_QSPI: pac.IO_QSPI,
+                PADS_QSPI: pac.PADS_QSPI,
+                PIO0: pac.PIO0,
+                PIO1: pac.PIO1,
+                PPB: pac.PPB,
+                PSM: pac.PSM,
+                PWM: pac.PWM,
+                RESETS: pac.RESETS,
+                ROSC: pac.ROSC,
+                RTC: pac.RTC,
+                SPI0: pac.SPI0,
+                SPI1: pac.SPI1,
+                SYSCFG: pac.SYSCFG,
+                SYSINFO: pac.SYSINFO,
+                TBMAN: pac.TBMAN,
+                UART0: pac.UART0,
+                UART1: pac.UART1,
+                USBCTRL_DPRAM: pac.USBCTRL_DPRAM,
+                USBCTRL_REGS: pac.USBCTRL_REGS,
+                VREG_AND_CHIP_RESET: pac.VREG_AND_CHIP_RESET,
+                XIP_CTRL: pac.XIP_CTRL,
+                XIP_SSI: pac.XIP_SSI,
+            },
+            core: CorePeripherals {
+                CPUID: core.CPUID,
+                DCB: core.DCB,
+                DWT: core.DWT,
+                MPU: core.MPU,
+                NVIC: core.NVIC,
+                SCB: core.SCB,
+            },
+            sio: SioParts {
+                gpio_qspi: sio.gpio_qspi,
+                hwdivider: sio.hwdivider,
+                fifo: sio.fifo,
+                interp0: sio.interp0,
+                interp1: sio.interp1,
+            },
+        }
+    }
+}
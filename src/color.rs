@@ -0,0 +1,549 @@
+//! Integer-only colour math for the LED animations.
+//!
+//! The Cortex-M0+ has no FPU, so everything here works on 8 and 16 bit
+//! fixed-point values and lookup tables. The hue is a `u16` where the full
+//! circle is `0x10000`: adding to it rotates the colour and wraps around
+//! for free.
+
+use smart_leds::RGB8;
+
+/// Hue of pure red, green and blue.
+pub const HUE_RED: u16 = 0;
+pub const HUE_GREEN: u16 = 0x5555;
+pub const HUE_BLUE: u16 = 0xaaaa;
+
+/// Converts the degrees (`0..360`) to the fixed-point hue.
+pub const fn hue_from_degrees(degrees: u16) -> u16 {
+    ((degrees as u32 % 360) * 0x10000 / 360) as u16
+}
+
+/// `a * b / 255`, rounded.
+pub const fn mul8(a: u8, b: u8) -> u8 {
+    ((a as u16 * b as u16 + 127) / 255) as u8
+}
+
+/// Splits the hue into the sector of the colour wheel (`0..6`) and the
+/// position within it (`0..0x10000`).
+fn sector(hue: u16) -> (u32, u32) {
+    let scaled = hue as u32 * 6;
+    (scaled >> 16, scaled & 0xffff)
+}
+
+/// The RGB of the given chroma `c` at the hue, to which the caller adds the
+/// lightness offset.
+fn hue_chroma(hue: u16, c: u8) -> (u8, u8, u8) {
+    let (sector, frac) = sector(hue);
+    let rising = ((c as u32 * frac + 0x8000) >> 16) as u8;
+    let falling = c - rising;
+
+    match sector {
+        0 => (c, rising, 0),
+        1 => (falling, c, 0),
+        2 => (0, c, rising),
+        3 => (0, falling, c),
+        4 => (rising, 0, c),
+        _ => (c, 0, falling),
+    }
+}
+
+/// HSV to RGB, saturation and value are `0..=255`.
+pub fn hsv2rgb(hue: u16, sat: u8, val: u8) -> RGB8 {
+    let c = mul8(val, sat);
+    let m = val - c;
+    let (r, g, b) = hue_chroma(hue, c);
+
+    RGB8::new(r + m, g + m, b + m)
+}
+
+/// HSL to RGB, saturation and lightness are `0..=255`.
+pub fn hsl2rgb(hue: u16, sat: u8, light: u8) -> RGB8 {
+    // c = (1 - |2L - 1|) * S
+    let doubled = 2 * light as i16;
+    let c = mul8((255 - (doubled - 255).abs()) as u8, sat);
+    let m = light - c / 2;
+    let (r, g, b) = hue_chroma(hue, c);
+
+    RGB8::new(r + m, g + m, b + m)
+}
+
+/// The colour of a black body at `kelvin` degrees, clamped to
+/// `1000..=40000` K.
+///
+/// Interpolates the table computed with Tanner Helland's approximation.
+pub fn kelvin2rgb(kelvin: u16) -> RGB8 {
+    let kelvin = kelvin.clamp(KELVIN_MIN, KELVIN_MAX);
+    let offset = kelvin - KELVIN_MIN;
+    let index = (offset / KELVIN_STEP) as usize;
+    let t = ((offset % KELVIN_STEP) as u32 * 255 / KELVIN_STEP as u32) as u8;
+
+    let [r0, g0, b0] = KELVIN[index];
+    let [r1, g1, b1] = KELVIN[(index + 1).min(KELVIN.len() - 1)];
+    RGB8::new(lerp(r0, r1, t), lerp(g0, g1, t), lerp(b0, b1, t))
+}
+
+/// Goes from `a` at `t == 0` to `b` at `t == 255`.
+pub fn lerp(a: u8, b: u8, t: u8) -> u8 {
+    if b >= a {
+        a + mul8(b - a, t)
+    } else {
+        a - mul8(a - b, t)
+    }
+}
+
+/// Mixes the colours, `t == 0` gives `a`, `t == 255` gives `b`.
+pub fn blend(a: RGB8, b: RGB8, t: u8) -> RGB8 {
+    RGB8::new(lerp(a.r, b.r, t), lerp(a.g, b.g, t), lerp(a.b, b.b, t))
+}
+
+/// Dims the colour, `level == 255` keeps it as is.
+pub fn scale(color: RGB8, level: u8) -> RGB8 {
+    RGB8::new(
+        mul8(color.r, level),
+        mul8(color.g, level),
+        mul8(color.b, level),
+    )
+}
+
+/// Corrects the colour for the LED response with [`GAMMA`].
+pub fn gamma(color: RGB8) -> RGB8 {
+    RGB8::new(
+        GAMMA[color.r as usize],
+        GAMMA[color.g as usize],
+        GAMMA[color.b as usize],
+    )
+}
+
+/// The PWM level for the perceived brightness `level`, see [`CIE_LIGHTNESS`].
+pub fn lightness(level: u8) -> u8 {
+    CIE_LIGHTNESS[level as usize]
+}
+
+/// Sine of `angle` (the full circle is 256) mapped to `1..=255`, `128` is zero.
+pub fn sin8(angle: u8) -> u8 {
+    let i = (angle & 0x3f) as usize;
+    let (quarter, sign) = match angle >> 6 {
+        0 => (QUARTER_SINE[i], 1),
+        1 => (QUARTER_SINE[64 - i], 1),
+        2 => (QUARTER_SINE[i], -1),
+        _ => (QUARTER_SINE[64 - i], -1),
+    };
+
+    (128 + sign * quarter as i16) as u8
+}
+
+/// `127 * sin(i * 2 * PI / 256)` for the first quarter of the circle.
+const QUARTER_SINE: [u8; 65] = [
+    0, 3, 6, 9, 12, 16, 19, 22, 25, 28, 31, 34, 37, 40, 43, 46, 49, 51, 54, 57, 60, 63, 65, 68, 71,
+    73, 76, 78, 81, 83, 85, 88, 90, 92, 94, 96, 98, 100, 102, 104, 106, 107, 109, 111, 112, 113,
+    115, 116, 117, 118, 120, 121, 122, 122, 123, 124, 125, 125, 126, 126, 126, 127, 127, 127, 127,
+];
+
+/// `255 * (i / 255) ^ 2.8`, makes the LED brightness steps look even.
+pub const GAMMA: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// The CIE 1931 lightness curve: maps the perceived brightness to the
+/// luminance, which is linear in the PWM duty cycle.
+pub const CIE_LIGHTNESS: [u8; 256] = [
+    0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 3, 4,
+    4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 8, 9, 9, 9, 10, 10, 10, 10,
+    11, 11, 11, 12, 12, 12, 13, 13, 13, 14, 14, 15, 15, 15, 16, 16, 17, 17, 17, 18, 18, 19, 19, 20,
+    20, 21, 21, 22, 22, 23, 23, 24, 24, 25, 25, 26, 26, 27, 28, 28, 29, 29, 30, 31, 31, 32, 32, 33,
+    34, 34, 35, 36, 37, 37, 38, 39, 39, 40, 41, 42, 43, 43, 44, 45, 46, 47, 47, 48, 49, 50, 51, 52,
+    53, 54, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 70, 71, 72, 73, 74, 75, 76,
+    77, 79, 80, 81, 82, 83, 85, 86, 87, 88, 90, 91, 92, 94, 95, 96, 98, 99, 100, 102, 103, 105,
+    106, 108, 109, 110, 112, 113, 115, 116, 118, 120, 121, 123, 124, 126, 128, 129, 131, 132, 134,
+    136, 138, 139, 141, 143, 145, 146, 148, 150, 152, 154, 155, 157, 159, 161, 163, 165, 167, 169,
+    171, 173, 175, 177, 179, 181, 183, 185, 187, 189, 191, 193, 196, 198, 200, 202, 204, 207, 209,
+    211, 214, 216, 218, 220, 223, 225, 228, 230, 232, 235, 237, 240, 242, 245, 247, 250, 252, 255,
+];
+
+const KELVIN_MIN: u16 = 1000;
+const KELVIN_MAX: u16 = 40000;
+const KELVIN_STEP: u16 = 200;
+
+/// Black body colours from `KELVIN_MIN` to `KELVIN_MAX` every `KELVIN_STEP`.
+const KELVIN: [[u8; 3]; 196] = [
+    [255, 68, 0],
+    [255, 86, 0],
+    [255, 101, 0],
+    [255, 115, 0],
+    [255, 126, 0],
+    [255, 137, 14],
+    [255, 146, 39],
+    [255, 155, 61],
+    [255, 163, 79],
+    [255, 170, 95],
+    [255, 177, 110],
+    [255, 184, 123],
+    [255, 190, 135],
+    [255, 195, 146],
+    [255, 201, 157],
+    [255, 206, 166],
+    [255, 211, 175],
+    [255, 215, 183],
+    [255, 220, 191],
+    [255, 224, 199],
+    [255, 228, 206],
+    [255, 232, 213],
+    [255, 236, 219],
+    [255, 239, 225],
+    [255, 243, 231],
+    [255, 246, 237],
+    [255, 249, 242],
+    [255, 253, 248],
+    [255, 255, 255],
+    [250, 246, 255],
+    [243, 242, 255],
+    [237, 239, 255],
+    [232, 236, 255],
+    [228, 234, 255],
+    [224, 232, 255],
+    [221, 230, 255],
+    [218, 228, 255],
+    [216, 227, 255],
+    [214, 225, 255],
+    [212, 224, 255],
+    [210, 223, 255],
+    [208, 222, 255],
+    [206, 221, 255],
+    [205, 220, 255],
+    [203, 219, 255],
+    [202, 218, 255],
+    [200, 217, 255],
+    [199, 217, 255],
+    [198, 216, 255],
+    [197, 215, 255],
+    [196, 214, 255],
+    [195, 214, 255],
+    [194, 213, 255],
+    [193, 213, 255],
+    [192, 212, 255],
+    [191, 211, 255],
+    [190, 211, 255],
+    [189, 210, 255],
+    [189, 210, 255],
+    [188, 210, 255],
+    [187, 209, 255],
+    [187, 209, 255],
+    [186, 208, 255],
+    [185, 208, 255],
+    [185, 207, 255],
+    [184, 207, 255],
+    [183, 207, 255],
+    [183, 206, 255],
+    [182, 206, 255],
+    [182, 205, 255],
+    [181, 205, 255],
+    [181, 205, 255],
+    [180, 204, 255],
+    [180, 204, 255],
+    [179, 204, 255],
+    [179, 203, 255],
+    [178, 203, 255],
+    [178, 203, 255],
+    [177, 203, 255],
+    [177, 202, 255],
+    [176, 202, 255],
+    [176, 202, 255],
+    [175, 201, 255],
+    [175, 201, 255],
+    [175, 201, 255],
+    [174, 201, 255],
+    [174, 200, 255],
+    [173, 200, 255],
+    [173, 200, 255],
+    [173, 200, 255],
+    [172, 199, 255],
+    [172, 199, 255],
+    [172, 199, 255],
+    [171, 199, 255],
+    [171, 199, 255],
+    [171, 198, 255],
+    [170, 198, 255],
+    [170, 198, 255],
+    [170, 198, 255],
+    [169, 198, 255],
+    [169, 197, 255],
+    [169, 197, 255],
+    [169, 197, 255],
+    [168, 197, 255],
+    [168, 197, 255],
+    [168, 196, 255],
+    [167, 196, 255],
+    [167, 196, 255],
+    [167, 196, 255],
+    [167, 196, 255],
+    [166, 195, 255],
+    [166, 195, 255],
+    [166, 195, 255],
+    [166, 195, 255],
+    [165, 195, 255],
+    [165, 195, 255],
+    [165, 194, 255],
+    [165, 194, 255],
+    [164, 194, 255],
+    [164, 194, 255],
+    [164, 194, 255],
+    [164, 194, 255],
+    [163, 194, 255],
+    [163, 193, 255],
+    [163, 193, 255],
+    [163, 193, 255],
+    [163, 193, 255],
+    [162, 193, 255],
+    [162, 193, 255],
+    [162, 193, 255],
+    [162, 192, 255],
+    [162, 192, 255],
+    [161, 192, 255],
+    [161, 192, 255],
+    [161, 192, 255],
+    [161, 192, 255],
+    [161, 192, 255],
+    [160, 191, 255],
+    [160, 191, 255],
+    [160, 191, 255],
+    [160, 191, 255],
+    [160, 191, 255],
+    [159, 191, 255],
+    [159, 191, 255],
+    [159, 191, 255],
+    [159, 190, 255],
+    [159, 190, 255],
+    [159, 190, 255],
+    [158, 190, 255],
+    [158, 190, 255],
+    [158, 190, 255],
+    [158, 190, 255],
+    [158, 190, 255],
+    [158, 190, 255],
+    [157, 189, 255],
+    [157, 189, 255],
+    [157, 189, 255],
+    [157, 189, 255],
+    [157, 189, 255],
+    [157, 189, 255],
+    [156, 189, 255],
+    [156, 189, 255],
+    [156, 189, 255],
+    [156, 188, 255],
+    [156, 188, 255],
+    [156, 188, 255],
+    [156, 188, 255],
+    [155, 188, 255],
+    [155, 188, 255],
+    [155, 188, 255],
+    [155, 188, 255],
+    [155, 188, 255],
+    [155, 188, 255],
+    [155, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [154, 187, 255],
+    [153, 187, 255],
+    [153, 187, 255],
+    [153, 187, 255],
+    [153, 186, 255],
+    [153, 186, 255],
+    [153, 186, 255],
+    [153, 186, 255],
+    [153, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+    [152, 186, 255],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The float conversion the matrix example used.
+    fn hsv2rgb_f32(hue: f32, sat: f32, val: f32) -> (f32, f32, f32) {
+        let c = val * sat;
+        let v = (hue / 60.0) % 2.0 - 1.0;
+        let v = if v < 0.0 { -v } else { v };
+        let x = c * (1.0 - v);
+        let m = val - c;
+        let (r, g, b) = if hue < 60.0 {
+            (c, x, 0.0)
+        } else if hue < 120.0 {
+            (x, c, 0.0)
+        } else if hue < 180.0 {
+            (0.0, c, x)
+        } else if hue < 240.0 {
+            (0.0, x, c)
+        } else if hue < 300.0 {
+            (x, 0.0, c)
+        } else {
+            (c, 0.0, x)
+        };
+        (r + m, g + m, b + m)
+    }
+
+    fn assert_close(actual: RGB8, expected: (f64, f64, f64), tolerance: f64, what: &str) {
+        let actual = [actual.r, actual.g, actual.b];
+        let expected = [expected.0, expected.1, expected.2];
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (*a as f64 - e).abs() <= tolerance,
+                "{what}: got {actual:?}, expected {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hsv_matches_float() {
+        for degrees in 0..360 {
+            for sat in (0..=255).step_by(15) {
+                for val in (0..=255).step_by(15) {
+                    let (r, g, b) =
+                        hsv2rgb_f32(degrees as f32, sat as f32 / 255.0, val as f32 / 255.0);
+                    let expected = (r as f64 * 255.0, g as f64 * 255.0, b as f64 * 255.0);
+                    let actual = hsv2rgb(hue_from_degrees(degrees), sat as u8, val as u8);
+
+                    assert_close(actual, expected, 1.5, &format!("{degrees} {sat} {val}"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hsv_primaries() {
+        assert_eq!(hsv2rgb(HUE_RED, 255, 255), RGB8::new(255, 0, 0));
+        assert_eq!(hsv2rgb(HUE_GREEN, 255, 255), RGB8::new(0, 255, 0));
+        assert_eq!(hsv2rgb(HUE_BLUE, 255, 255), RGB8::new(0, 0, 255));
+        assert_eq!(hsv2rgb(0x1234, 0, 200), RGB8::new(200, 200, 200));
+        assert_eq!(hsv2rgb(0xffff, 255, 255), RGB8::new(255, 0, 0));
+    }
+
+    #[test]
+    fn hsl_matches_float() {
+        for degrees in (0..360).step_by(5) {
+            for sat in (0..=255).step_by(15) {
+                for light in (0..=255).step_by(15) {
+                    let s = sat as f64 / 255.0;
+                    let l = light as f64 / 255.0;
+                    // HSL is HSV with v = l + s * min(l, 1 - l), s_v = 2 * (1 - l / v)
+                    let v = l + s * l.min(1.0 - l);
+                    let s_v = if v == 0.0 { 0.0 } else { 2.0 * (1.0 - l / v) };
+                    let (r, g, b) = hsv2rgb_f32(degrees as f32, s_v as f32, v as f32);
+                    let expected = (r as f64 * 255.0, g as f64 * 255.0, b as f64 * 255.0);
+                    let actual = hsl2rgb(hue_from_degrees(degrees), sat as u8, light as u8);
+
+                    assert_close(actual, expected, 2.0, &format!("{degrees} {sat} {light}"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kelvin_matches_float() {
+        fn kelvin_f64(kelvin: f64) -> (f64, f64, f64) {
+            let t = kelvin / 100.0;
+            let r = if t <= 66.0 {
+                255.0
+            } else {
+                329.698727446 * (t - 60.0).powf(-0.1332047592)
+            };
+            let g = if t <= 66.0 {
+                99.4708025861 * t.ln() - 161.1195681661
+            } else {
+                288.1221695283 * (t - 60.0).powf(-0.0755148492)
+            };
+            let b = if t >= 66.0 {
+                255.0
+            } else if t <= 19.0 {
+                0.0
+            } else {
+                138.5177312231 * (t - 10.0).ln() - 305.0447927307
+            };
+            let clamp = |v: f64| v.clamp(0.0, 255.0);
+            (clamp(r), clamp(g), clamp(b))
+        }
+
+        // The formula has kinks at 1905 K (blue) and 6600 K (red and green),
+        // the interpolation cuts the corners next to them.
+        for kelvin in (1000..=40000u16).step_by(50) {
+            let near_kink = [1905, 6600]
+                .iter()
+                .any(|k| kelvin.abs_diff(*k) < KELVIN_STEP);
+            let tolerance = if near_kink { 8.0 } else { 2.0 };
+            let expected = kelvin_f64(kelvin as f64);
+            assert_close(
+                kelvin2rgb(kelvin),
+                expected,
+                tolerance,
+                &format!("{kelvin}"),
+            );
+        }
+        assert_eq!(kelvin2rgb(0), kelvin2rgb(1000));
+        assert_eq!(kelvin2rgb(u16::MAX), kelvin2rgb(40000));
+    }
+
+    #[test]
+    fn tables_match_formulas() {
+        for i in 0..=255u8 {
+            let x = i as f64 / 255.0;
+            let gamma = 255.0 * x.powf(2.8);
+            assert!((GAMMA[i as usize] as f64 - gamma).abs() <= 0.5, "{i}");
+
+            let l = x * 100.0;
+            let y = if l > 8.0 {
+                ((l + 16.0) / 116.0).powi(3)
+            } else {
+                l / 903.3
+            };
+            assert!((lightness(i) as f64 - y * 255.0).abs() <= 0.5, "{i}");
+        }
+        assert_eq!(gamma(RGB8::new(0, 128, 255)), RGB8::new(0, 37, 255));
+    }
+
+    #[test]
+    fn sine_matches_float() {
+        for angle in 0..=255u8 {
+            let expected = 128.0 + 127.0 * (angle as f64 * core::f64::consts::TAU / 256.0).sin();
+            assert!((sin8(angle) as f64 - expected).abs() <= 0.5, "{angle}");
+        }
+        assert_eq!(sin8(0), 128);
+        assert_eq!(sin8(64), 255);
+        assert_eq!(sin8(192), 1);
+    }
+
+    #[test]
+    fn interpolates() {
+        assert_eq!(lerp(10, 200, 0), 10);
+        assert_eq!(lerp(10, 200, 255), 200);
+        assert_eq!(lerp(200, 10, 255), 10);
+        assert_eq!(lerp(0, 254, 128), 127);
+
+        let a = RGB8::new(255, 0, 100);
+        let b = RGB8::new(0, 255, 100);
+        assert_eq!(blend(a, b, 0), a);
+        assert_eq!(blend(a, b, 255), b);
+        assert_eq!(blend(a, b, 51), RGB8::new(204, 51, 100));
+
+        assert_eq!(scale(RGB8::new(255, 100, 1), 255), RGB8::new(255, 100, 1));
+        assert_eq!(scale(RGB8::new(255, 100, 1), 0), RGB8::new(0, 0, 0));
+        assert_eq!(scale(RGB8::new(255, 100, 2), 128), RGB8::new(128, 50, 1));
+    }
+}
@@ -0,0 +1,275 @@
+//! Text console on a display with the hardware vertical scrolling.
+//!
+//! The console splits the scroll area into text lines used as a ring:
+//! when the cursor goes past the last line, the oldest line is cleared,
+//! reused for the new text and the display is told to start the picture
+//! one line lower. Only that line is redrawn instead of the whole panel.
+
+use core::fmt;
+
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::PixelColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Baseline;
+use embedded_graphics::text::Text;
+
+/// Sets the first line of the scroll area shown at the top of the display.
+pub type ScrollFn<D> = fn(&mut D, u16) -> Result<(), <D as DrawTarget>::Error>;
+
+pub struct Console<D: DrawTarget> {
+    display: D,
+    style: MonoTextStyle<'static, D::Color>,
+    background: D::Color,
+    scroll: ScrollFn<D>,
+    char_size: Size,
+    cols: usize,
+    rows: usize,
+    /// The line of the ring shown at the top
+    top: usize,
+    /// Cursor position on the screen
+    row: usize,
+    col: usize,
+}
+
+impl<D> Console<D>
+where
+    D: DrawTarget,
+    D::Color: PixelColor,
+{
+    /// Clears the display and resets the scrolling.
+    ///
+    /// The scroll area has to start at the top of the display and be
+    /// [`Console::scroll_area_height`] pixels high.
+    pub fn new(
+        mut display: D,
+        font: &'static MonoFont<'static>,
+        foreground: D::Color,
+        background: D::Color,
+        scroll: ScrollFn<D>,
+    ) -> Result<Self, D::Error> {
+        let style = MonoTextStyleBuilder::new()
+            .font(font)
+            .text_color(foreground)
+            .background_color(background)
+            .build();
+        let char_size = Size::new(
+            font.character_size.width + font.character_spacing,
+            font.character_size.height,
+        );
+        let size = display.bounding_box().size;
+
+        display.clear(background)?;
+        scroll(&mut display, 0)?;
+
+        Ok(Self {
+            display,
+            style,
+            background,
+            scroll,
+            char_size,
+            cols: (size.width / char_size.width) as usize,
+            rows: (size.height / char_size.height) as usize,
+            top: 0,
+            row: 0,
+            col: 0,
+        })
+    }
+
+    /// Height of the part of the display holding the text lines.
+    pub fn scroll_area_height(&self) -> u16 {
+        (self.rows as u32 * self.char_size.height) as u16
+    }
+
+    /// Console size in characters.
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Cursor position on the screen, column and row.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.col, self.row)
+    }
+
+    pub fn release(self) -> D {
+        self.display
+    }
+
+    /// Prints the character, `\n` starts a new line and `\r` returns to
+    /// the beginning of the current one. Long lines are wrapped.
+    pub fn put_char(&mut self, c: char) -> Result<(), D::Error> {
+        match c {
+            '\n' => return self.new_line(),
+            '\r' => {
+                self.col = 0;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        if self.col == self.cols {
+            self.new_line()?;
+        }
+
+        let position = Point::new(
+            (self.col as u32 * self.char_size.width) as i32,
+            self.line_y(self.row),
+        );
+        Text::with_baseline(
+            c.encode_utf8(&mut [0; 4]),
+            position,
+            self.style,
+            Baseline::Top,
+        )
+        .draw(&mut self.display)?;
+        self.col += 1;
+
+        Ok(())
+    }
+
+    fn new_line(&mut self) -> Result<(), D::Error> {
+        self.col = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+            return Ok(());
+        }
+
+        // Reuse the top line for the new text at the bottom
+        self.top = (self.top + 1) % self.rows;
+        let area = Rectangle::new(
+            Point::new(0, self.line_y(self.row)),
+            Size::new(
+                self.display.bounding_box().size.width,
+                self.char_size.height,
+            ),
+        );
+        self.display.fill_solid(&area, self.background)?;
+
+        let offset = (self.top as u32 * self.char_size.height) as u16;
+        (self.scroll)(&mut self.display, offset)
+    }
+
+    /// Position of the screen row in the display memory.
+    fn line_y(&self, row: usize) -> i32 {
+        (((self.top + row) % self.rows) as u32 * self.char_size.height) as i32
+    }
+}
+
+impl<D> fmt::Write for Console<D>
+where
+    D: DrawTarget,
+    D::Color: PixelColor,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars()
+            .try_for_each(|c| self.put_char(c))
+            .map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::fmt::Write;
+    use embedded_graphics::mock_display::MockDisplay;
+    use embedded_graphics::mono_font::ascii::FONT_6X10;
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    /// Display memory plus the scroll offset, 64x60 pixels hold 10x6 characters.
+    struct Panel {
+        memory: MockDisplay<BinaryColor>,
+        offset: u16,
+    }
+
+    impl Dimensions for Panel {
+        fn bounding_box(&self) -> Rectangle {
+            Rectangle::new(Point::zero(), Size::new(64, 60))
+        }
+    }
+
+    impl DrawTarget for Panel {
+        type Color = BinaryColor;
+        type Error = core::convert::Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.memory.draw_iter(pixels)
+        }
+    }
+
+    fn console() -> Console<Panel> {
+        let mut memory = MockDisplay::new();
+        memory.set_allow_overdraw(true);
+        let panel = Panel { memory, offset: 0 };
+
+        Console::new(
+            panel,
+            &FONT_6X10,
+            BinaryColor::On,
+            BinaryColor::Off,
+            |panel, offset| {
+                panel.offset = offset;
+                Ok(())
+            },
+        )
+        .unwrap()
+    }
+
+    /// Is anything lit in the display memory between the rows?
+    fn lit(console: &Console<Panel>, rows: core::ops::Range<i32>) -> bool {
+        rows.flat_map(|y| (0..64).map(move |x| Point::new(x, y)))
+            .any(|p| console.display.memory.get_pixel(p) == Some(BinaryColor::On))
+    }
+
+    #[test]
+    fn fits_the_font() {
+        let console = console();
+
+        assert_eq!(console.size(), (10, 6));
+        assert_eq!(console.scroll_area_height(), 60);
+        assert!(!lit(&console, 0..60));
+    }
+
+    #[test]
+    fn prints_and_wraps() {
+        let mut console = console();
+        write!(console, "0123456789AB").unwrap();
+
+        assert_eq!(console.cursor(), (2, 1));
+        assert!(lit(&console, 0..20));
+        assert!(!lit(&console, 20..60));
+
+        write!(console, "\rX\nY").unwrap();
+        assert_eq!(console.cursor(), (1, 2));
+    }
+
+    #[test]
+    fn scrolls_by_one_line() {
+        let mut console = console();
+        for i in 0..6 {
+            writeln!(console, "line {i}").unwrap();
+        }
+
+        // The 7th line reuses the memory of the first one
+        assert_eq!(console.cursor(), (0, 5));
+        assert_eq!(console.display.offset, 10);
+        assert!(!lit(&console, 0..10));
+        assert!(lit(&console, 10..50));
+
+        write!(console, "line 6").unwrap();
+        assert!(lit(&console, 0..10));
+
+        for i in 7..12 {
+            write!(console, "\nline {i}").unwrap();
+        }
+        assert_eq!(console.display.offset, 0);
+
+        writeln!(console).unwrap();
+        assert_eq!(console.display.offset, 10);
+    }
+}
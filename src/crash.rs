@@ -0,0 +1,246 @@
+//! Panic reports that survive the reset.
+//!
+//! The panic handler fills a [`Report`] in a RAM region `memory.x` keeps
+//! out of the way of the runtime (`.crash_report`) and resets the chip with
+//! the watchdog. The RAM is not cleared by the reset, so the next
+//! boot finds the report, prints it and clears it.
+//!
+//! ```ignore
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     pico_bites::crash::record_and_reset(info)
+//! }
+//! ```
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod persist;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use persist::record_and_reset;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub(crate) use persist::reset;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use persist::reset_reason;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub(crate) use persist::select_watchdog_reset;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use persist::take;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use persist::ResetReason;
+
+use core::fmt;
+
+/// Marks a written report, anything else in the RAM is garbage.
+const MAGIC: u32 = 0xdead_c0de;
+
+const FILE_LEN: usize = 64;
+const MESSAGE_LEN: usize = 160;
+
+/// What the panic left behind.
+///
+/// Only plain integers and bytes, so whatever the RAM holds after the
+/// power-on is still a value of this type; [`Report::is_valid`] tells the
+/// real reports from the garbage.
+#[repr(C)]
+pub struct Report {
+    magic: u32,
+    line: u32,
+    column: u32,
+    file_len: u16,
+    message_len: u16,
+    timestamp_us: u64,
+    file: [u8; FILE_LEN],
+    message: [u8; MESSAGE_LEN],
+    checksum: u32,
+}
+
+impl Report {
+    /// Formats the report, the message is cut short and the file path
+    /// keeps its end if they do not fit.
+    pub fn new(
+        timestamp_us: u64,
+        file: &str,
+        line: u32,
+        column: u32,
+        message: impl fmt::Display,
+    ) -> Self {
+        let mut report = Self {
+            magic: MAGIC,
+            line,
+            column,
+            file_len: 0,
+            message_len: 0,
+            timestamp_us,
+            file: [0; FILE_LEN],
+            message: [0; MESSAGE_LEN],
+            checksum: 0,
+        };
+
+        let mut start = file.len().saturating_sub(FILE_LEN);
+        while !file.is_char_boundary(start) {
+            start += 1;
+        }
+        let file = &file.as_bytes()[start..];
+        report.file[..file.len()].copy_from_slice(file);
+        report.file_len = file.len() as u16;
+
+        let mut writer = Truncate {
+            buf: &mut report.message,
+            len: 0,
+        };
+        // Running out of room is not an error here
+        let _ = fmt::write(&mut writer, format_args!("{message}"));
+        report.message_len = writer.len as u16;
+
+        report.checksum = report.compute_checksum();
+        report
+    }
+
+    /// Was the report written by [`Report::new`] and left intact?
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && usize::from(self.file_len) <= FILE_LEN
+            && usize::from(self.message_len) <= MESSAGE_LEN
+            && self.checksum == self.compute_checksum()
+    }
+
+    pub fn file(&self) -> &str {
+        text(&self.file, self.file_len)
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        text(&self.message, self.message_len)
+    }
+
+    /// Time since the boot when the panic happened.
+    pub fn timestamp_us(&self) -> u64 {
+        self.timestamp_us
+    }
+
+    /// FNV-1a over the fields.
+    fn compute_checksum(&self) -> u32 {
+        let file = &self.file[..usize::from(self.file_len).min(FILE_LEN)];
+        let message = &self.message[..usize::from(self.message_len).min(MESSAGE_LEN)];
+
+        [
+            &self.magic.to_le_bytes()[..],
+            &self.line.to_le_bytes(),
+            &self.column.to_le_bytes(),
+            &self.file_len.to_le_bytes(),
+            &self.message_len.to_le_bytes(),
+            &self.timestamp_us.to_le_bytes(),
+            file,
+            message,
+        ]
+        .iter()
+        .flat_map(|bytes| bytes.iter())
+        .fold(0x811c_9dc5, |hash, &byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        })
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "panicked at {}:{}:{} after {}.{:06} s: {}",
+            self.file(),
+            self.line,
+            self.column,
+            self.timestamp_us / 1_000_000,
+            self.timestamp_us % 1_000_000,
+            self.message()
+        )
+    }
+}
+
+fn text(buf: &[u8], len: u16) -> &str {
+    let bytes = &buf[..usize::from(len).min(buf.len())];
+    match core::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+    }
+}
+
+/// Writes as much as fits, never splitting a character.
+struct Truncate<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for Truncate<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = self.buf.len() - self.len;
+        let mut count = s.len().min(room);
+        while !s.is_char_boundary(count) {
+            count -= 1;
+        }
+        self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+
+        if count < s.len() {
+            Err(fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_panic() {
+        let report = Report::new(3_000_042, "src/main.rs", 12, 5, format_args!("boom {}", 7));
+
+        assert!(report.is_valid());
+        assert_eq!(report.file(), "src/main.rs");
+        assert_eq!(report.message(), "boom 7");
+        assert_eq!(
+            report.to_string(),
+            "panicked at src/main.rs:12:5 after 3.000042 s: boom 7"
+        );
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        let file = format!("/home/{}/src/main.rs", "x".repeat(100));
+        let message = "é".repeat(100);
+        let report = Report::new(0, &file, 1, 1, &message);
+
+        assert!(report.is_valid());
+        assert_eq!(report.file().len(), FILE_LEN);
+        assert!(report.file().ends_with("/src/main.rs"));
+        assert_eq!(report.message(), "é".repeat(MESSAGE_LEN / 2));
+
+        // A multi-byte character right at the edge is dropped whole
+        let report = Report::new(0, &format!("é{}", "x".repeat(FILE_LEN - 1)), 1, 1, "");
+        assert_eq!(report.file(), "x".repeat(FILE_LEN - 1));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let mut report = Report::new(1, "src/lib.rs", 2, 3, "message");
+        report.line = 4;
+        assert!(!report.is_valid());
+
+        let mut report = Report::new(1, "src/lib.rs", 2, 3, "message");
+        report.message_len = 1000;
+        assert!(!report.is_valid());
+        assert!(report.message().starts_with("message"));
+
+        let mut report = Report::new(1, "src/lib.rs", 2, 3, "message");
+        report.magic = 0;
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,107 @@
+//! The RAM slot for the report and the watchdog reset.
+
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::ptr::addr_of_mut;
+
+use rp2040_hal as hal;
+
+use hal::pac;
+
+use super::Report;
+
+/// Placed by `memory.x` in its own region past `RAM`, so neither the stack
+/// nor the startup code touch it.
+#[link_section = ".crash_report"]
+static mut REPORT: MaybeUninit<Report> = MaybeUninit::uninit();
+
+/// Why the chip came out of the last reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ResetReason {
+    /// Power-on, brown-out or the RUN pin.
+    PowerOn,
+    /// The watchdog was not fed in time.
+    WatchdogTimeout,
+    /// The watchdog reset was forced, e.g. after a panic.
+    WatchdogForced,
+}
+
+/// Reads the reason of the last reset from the watchdog.
+pub fn reset_reason() -> ResetReason {
+    // Read-only access to a register nothing else writes
+    let reason = unsafe { (*pac::WATCHDOG::ptr()).reason().read() };
+    if reason.force().bit_is_set() {
+        ResetReason::WatchdogForced
+    } else if reason.timer().bit_is_set() {
+        ResetReason::WatchdogTimeout
+    } else {
+        ResetReason::PowerOn
+    }
+}
+
+/// Takes the report the previous boot left, the slot is cleared.
+pub fn take() -> Option<Report> {
+    let slot = addr_of_mut!(REPORT).cast::<Report>();
+    // The slot is only written by the panic handler, which never returns
+    let report = unsafe { slot.read_volatile() };
+    if !report.is_valid() {
+        return None;
+    }
+    unsafe { addr_of_mut!((*slot).magic).write_volatile(0) };
+
+    Some(report)
+}
+
+/// Records the panic and resets the chip, call from the `#[panic_handler]`.
+pub fn record_and_reset(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+
+    let (file, line, column) = info
+        .location()
+        .map_or(("<unknown>", 0, 0), |l| (l.file(), l.line(), l.column()));
+    let report = Report::new(now_us(), file, line, column, info.message());
+
+    // Interrupts are off and this is the only writer
+    unsafe { addr_of_mut!(REPORT).cast::<Report>().write_volatile(report) };
+
+    reset()
+}
+
+/// Microseconds since the boot, zero if the timer was never started.
+fn now_us() -> u64 {
+    let timer = unsafe { &*pac::TIMER::ptr() };
+    // The raw registers do not latch, re-read if the low word wrapped
+    loop {
+        let hi = timer.timerawh().read().bits();
+        let lo = timer.timerawl().read().bits();
+        if timer.timerawh().read().bits() == hi {
+            return (u64::from(hi) << 32) | u64::from(lo);
+        }
+    }
+}
+
+/// Makes the watchdog reset everything but the oscillators, the same way
+/// the Pico SDK does it.
+pub(crate) fn select_watchdog_reset() {
+    const ROSC: u32 = 1 << 0;
+    const XOSC: u32 = 1 << 1;
+    const ALL: u32 = 0x1_ffff;
+
+    unsafe {
+        let psm = &*pac::PSM::ptr();
+        psm.wdsel().write(|w| w.bits(ALL & !(ROSC | XOSC)));
+    }
+}
+
+/// Resets the chip through the watchdog.
+pub(crate) fn reset() -> ! {
+    select_watchdog_reset();
+    unsafe {
+        let watchdog = &*pac::WATCHDOG::ptr();
+        watchdog.ctrl().modify(|_, w| w.trigger().set_bit());
+    }
+
+    loop {
+        cortex_m::asm::nop();
+    }
+}
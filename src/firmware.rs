@@ -0,0 +1,73 @@
+//! Which build of the firmware this is.
+//!
+//! `build.rs` records the git commit, the build profile and the board, so a
+//! unit in the field can tell exactly what it runs:
+//!
+//! ```ignore
+//! log::info!("{}", log::Display2Format(&firmware::FIRMWARE_INFO));
+//! ```
+
+use core::fmt;
+
+include!(concat!(env!("OUT_DIR"), "/firmware_info.rs"));
+
+pub struct FirmwareInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Abbreviated commit hash, `unknown` when built outside a git checkout.
+    pub git_commit: &'static str,
+    /// Were the tracked files modified since the commit?
+    pub git_dirty: bool,
+    /// `debug` or `release`.
+    pub profile: &'static str,
+    /// The board feature, `none` if there was none.
+    pub board: &'static str,
+    /// `YYYY-MM-DD`, when `build.rs` last ran.
+    pub build_date: &'static str,
+}
+
+pub static FIRMWARE_INFO: FirmwareInfo = FirmwareInfo {
+    name: env!("CARGO_PKG_NAME"),
+    version: env!("CARGO_PKG_VERSION"),
+    git_commit: GIT_COMMIT,
+    git_dirty: GIT_DIRTY,
+    profile: PROFILE,
+    board: BOARD,
+    build_date: BUILD_DATE,
+};
+
+/// `pico-bites 0.1.0 (0317e06a1b2c-dirty, release, rp-pico)`
+impl fmt::Display for FirmwareInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({}", self.name, self.version, self.git_commit)?;
+        if self.git_dirty {
+            f.write_str("-dirty")?;
+        }
+        write!(f, ", {}, {})", self.profile, self.board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_build() {
+        let info = FirmwareInfo {
+            name: "pico-bites",
+            version: "1.2.3",
+            git_commit: "0123456789ab",
+            git_dirty: true,
+            profile: "release",
+            board: "rp-pico",
+            build_date: "2024-02-29",
+        };
+        assert_eq!(
+            info.to_string(),
+            "pico-bites 1.2.3 (0123456789ab-dirty, release, rp-pico)"
+        );
+
+        assert_eq!(FIRMWARE_INFO.version, env!("CARGO_PKG_VERSION"));
+        assert!(!FIRMWARE_INFO.git_commit.is_empty());
+    }
+}
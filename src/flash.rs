@@ -0,0 +1,151 @@
+//! Storing data in the on-board flash, next to the firmware.
+//!
+//! The QSPI flash is NOR flash: an erase sets a whole 4 KiB sector to
+//! `0xff`, programming can only clear bits, a page of 256 bytes at a time.
+//! [`Flash`] is a region of it, addressed from the start of the region;
+//! `memory.x` keeps the regions out of the way of the firmware.
+//!
+//! On the target [`RomFlash`] erases and programs with the boot ROM
+//! routines. Code must not run from the flash meanwhile, so the interrupts
+//! are disabled for the duration and the second core must not be running.
+//!
+//! A [`Journal`] keeps small records in a region, spreading the wear and
+//! surviving power failures.
+
+pub mod journal;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod rom;
+
+pub use journal::Journal;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use rom::disk_region;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use rom::settings_region;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use rom::RomFlash;
+
+use core::fmt;
+
+/// Where the flash is mapped for reading.
+pub const XIP_BASE: usize = 0x1000_0000;
+
+pub const SECTOR_SIZE: usize = 4096;
+pub const PAGE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The access goes past the end of the region.
+    OutOfBounds,
+    /// An erase not on a sector boundary, or a program not on a page one.
+    Unaligned,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::OutOfBounds => "access out of bounds",
+            Error::Unaligned => "unaligned access",
+        })
+    }
+}
+
+/// A region of NOR flash.
+pub trait Flash {
+    /// Size of the region in bytes, a multiple of [`SECTOR_SIZE`].
+    fn capacity(&self) -> usize;
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error>;
+
+    /// Erases the sector starting at `offset`.
+    fn erase_sector(&mut self, offset: usize) -> Result<(), Error>;
+
+    /// Programs whole pages starting at `offset`, the bytes must have been
+    /// erased before.
+    fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error>;
+}
+
+/// Checks an access of `len` bytes at `offset` to a region of `capacity`
+/// bytes, with `offset` and `len` multiples of `align`.
+pub fn check_access(capacity: usize, offset: usize, len: usize, align: usize) -> Result<(), Error> {
+    if !offset.is_multiple_of(align) || !len.is_multiple_of(align) {
+        return Err(Error::Unaligned);
+    }
+    match offset.checked_add(len) {
+        Some(end) if end <= capacity => Ok(()),
+        _ => Err(Error::OutOfBounds),
+    }
+}
+
+/// Flash in RAM for the tests, programming checks that only bits that
+/// were set get cleared, as with the real chip.
+#[cfg(test)]
+pub(crate) struct SimFlash {
+    pub data: Vec<u8>,
+    pub erases: Vec<u32>,
+}
+
+#[cfg(test)]
+impl SimFlash {
+    pub fn new(sectors: usize) -> Self {
+        Self {
+            data: vec![0xff; sectors * SECTOR_SIZE],
+            erases: vec![0; sectors],
+        }
+    }
+
+    pub fn total_erases(&self) -> u32 {
+        self.erases.iter().sum()
+    }
+}
+
+#[cfg(test)]
+impl Flash for SimFlash {
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error> {
+        check_access(self.data.len(), offset, buf.len(), 1)?;
+        buf.copy_from_slice(&self.data[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    fn erase_sector(&mut self, offset: usize) -> Result<(), Error> {
+        check_access(self.data.len(), offset, SECTOR_SIZE, SECTOR_SIZE)?;
+        self.data[offset..offset + SECTOR_SIZE].fill(0xff);
+        self.erases[offset / SECTOR_SIZE] += 1;
+        Ok(())
+    }
+
+    fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        check_access(self.data.len(), offset, data.len(), PAGE_SIZE)?;
+        for (cell, &byte) in self.data[offset..].iter_mut().zip(data) {
+            assert_eq!(*cell & byte, byte, "programming unerased flash");
+            *cell = byte;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_accesses() {
+        assert_eq!(check_access(8192, 4096, 4096, SECTOR_SIZE), Ok(()));
+        assert_eq!(
+            check_access(8192, 100, 4096, SECTOR_SIZE),
+            Err(Error::Unaligned)
+        );
+        assert_eq!(
+            check_access(8192, 8192, 256, PAGE_SIZE),
+            Err(Error::OutOfBounds)
+        );
+        assert_eq!(
+            check_access(8192, usize::MAX - 255, 256, 1),
+            Err(Error::OutOfBounds)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Records that survive a power failure at any point.
+
+use crate::crc::crc32;
+
+use super::Error;
+use super::Flash;
+use super::PAGE_SIZE;
+use super::SECTOR_SIZE;
+
+/// Marks a record, the rest of the flash is garbage or erased.
+const MAGIC: u32 = 0x4c4e_524a;
+const HEADER_LEN: usize = 16;
+
+/// The longest record, a page less the header.
+pub const MAX_RECORD: usize = PAGE_SIZE - HEADER_LEN;
+
+/// Keeps the newest of the records written to a flash region.
+///
+/// Every record goes to the next erased page, with a sequence number and a
+/// CRC; the valid record with the highest number is the current one. The
+/// pages are used in turn and a sector is erased only when the records move
+/// into it, so all sectors wear the same, and the current record survives
+/// a power failure at any point: a torn record does not pass the CRC and
+/// the one before it still counts.
+pub struct Journal<F: Flash> {
+    flash: F,
+}
+
+impl<F: Flash> Journal<F> {
+    /// The region must be two sectors or more, so erasing a sector never
+    /// takes the current record with it.
+    pub fn new(flash: F) -> Self {
+        assert!(flash.capacity() >= 2 * SECTOR_SIZE);
+        Self { flash }
+    }
+
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Reads the current record into `buf`, returns its length or `None`
+    /// if nothing was written yet.
+    pub fn read(&mut self, buf: &mut [u8; MAX_RECORD]) -> Result<Option<usize>, Error> {
+        let Some((page, _, len)) = self.newest()? else {
+            return Ok(None);
+        };
+        self.flash
+            .read(page * PAGE_SIZE + HEADER_LEN, &mut buf[..len])?;
+        Ok(Some(len))
+    }
+
+    /// Makes `record` the current one.
+    ///
+    /// Panics if the record is longer than [`MAX_RECORD`].
+    pub fn write(&mut self, record: &[u8]) -> Result<(), Error> {
+        assert!(record.len() <= MAX_RECORD);
+        let (mut page, sequence) = match self.newest()? {
+            Some((page, sequence, _)) => (page + 1, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+        let pages = self.flash.capacity() / PAGE_SIZE;
+        let pages_per_sector = SECTOR_SIZE / PAGE_SIZE;
+
+        // Skip the pages a torn write left behind, the sectors are erased
+        // when entered
+        let mut buf = [0u8; PAGE_SIZE];
+        loop {
+            page %= pages;
+            if page.is_multiple_of(pages_per_sector) {
+                self.flash.erase_sector(page * PAGE_SIZE)?;
+                break;
+            }
+            self.flash.read(page * PAGE_SIZE, &mut buf)?;
+            if buf.iter().all(|&b| b == 0xff) {
+                break;
+            }
+            page += 1;
+        }
+
+        // The magic, the CRC of the rest, the sequence number, the length
+        buf.fill(0xff);
+        let end = HEADER_LEN + record.len();
+        buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[8..12].copy_from_slice(&sequence.to_le_bytes());
+        buf[12..14].copy_from_slice(&(record.len() as u16).to_le_bytes());
+        buf[HEADER_LEN..end].copy_from_slice(record);
+        let crc = crc32(&buf[8..end]);
+        buf[4..8].copy_from_slice(&crc.to_le_bytes());
+        self.flash.program(page * PAGE_SIZE, &buf)
+    }
+
+    /// The page, the sequence number and the length of the newest record.
+    fn newest(&mut self) -> Result<Option<(usize, u32, usize)>, Error> {
+        let mut newest: Option<(usize, u32, usize)> = None;
+        let mut buf = [0u8; PAGE_SIZE];
+        for page in 0..self.flash.capacity() / PAGE_SIZE {
+            self.flash.read(page * PAGE_SIZE, &mut buf[..HEADER_LEN])?;
+            let word = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+            let len = usize::from(u16::from_le_bytes([buf[12], buf[13]]));
+            if word(0) != MAGIC || len > MAX_RECORD {
+                continue;
+            }
+            let (crc, sequence) = (word(4), word(8));
+            let end = HEADER_LEN + len;
+            self.flash
+                .read(page * PAGE_SIZE + HEADER_LEN, &mut buf[HEADER_LEN..end])?;
+            if crc != crc32(&buf[8..end]) {
+                continue;
+            }
+
+            // The sequence numbers wrap around, the newest one is ahead of
+            // the others by less than half the range
+            let is_newer = newest.is_none_or(|(_, newest_sequence, _)| {
+                (sequence.wrapping_sub(newest_sequence) as i32) > 0
+            });
+            if is_newer {
+                newest = Some((page, sequence, len));
+            }
+        }
+        Ok(newest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+
+    fn read(journal: &mut Journal<SimFlash>) -> Option<Vec<u8>> {
+        let mut buf = [0u8; MAX_RECORD];
+        let len = journal.read(&mut buf).unwrap()?;
+        Some(buf[..len].to_vec())
+    }
+
+    #[test]
+    fn keeps_the_newest_record() {
+        let mut journal = Journal::new(SimFlash::new(3));
+        assert_eq!(read(&mut journal), None);
+
+        // Through all the sectors a few times
+        for i in 0..100u32 {
+            let record = vec![i as u8; i as usize % (MAX_RECORD + 1)];
+            journal.write(&record).unwrap();
+            assert_eq!(read(&mut journal), Some(record));
+        }
+        let flash = journal.into_inner();
+        assert_eq!(flash.total_erases(), 100 / 16 + 1);
+        assert!(flash.erases.iter().max().unwrap() - flash.erases.iter().min().unwrap() <= 1);
+
+        // A new journal finds it again
+        let mut journal = Journal::new(flash);
+        assert_eq!(read(&mut journal), Some(vec![99; 99]));
+    }
+
+    #[test]
+    fn survives_torn_writes() {
+        let mut journal = Journal::new(SimFlash::new(2));
+        journal.write(b"old").unwrap();
+        journal.write(b"new").unwrap();
+        let good = journal.flash.data.clone();
+
+        // Power lost at every step of programming the next record: the
+        // page gets programmed from its start
+        for cut in 0..=HEADER_LEN + 5 {
+            journal.flash.data.copy_from_slice(&good);
+            let mut torn = Journal::new(SimFlash::new(2));
+            torn.write(b"old").unwrap();
+            torn.write(b"new").unwrap();
+            torn.write(b"newer").unwrap();
+            let page = 2 * PAGE_SIZE;
+            journal.flash.data[page..page + cut]
+                .copy_from_slice(&torn.flash.data[page..page + cut]);
+
+            let expected: &[u8] = if cut == HEADER_LEN + 5 {
+                b"newer"
+            } else {
+                b"new"
+            };
+            assert_eq!(read(&mut journal).as_deref(), Some(expected));
+
+            // The next record goes past the garbage
+            journal.write(b"next").unwrap();
+            assert_eq!(read(&mut journal).as_deref(), Some(&b"next"[..]));
+        }
+    }
+
+    #[test]
+    fn survives_an_interrupted_erase() {
+        let mut journal = Journal::new(SimFlash::new(2));
+        for i in 0..32u8 {
+            journal.write(&[i]).unwrap();
+        }
+        // Both sectors are full, the next write erases the first one and
+        // the power fails halfway through
+        journal.flash.data[..SECTOR_SIZE / 2].fill(0xff);
+        assert_eq!(read(&mut journal), Some(vec![31]));
+        journal.write(&[32]).unwrap();
+        assert_eq!(read(&mut journal), Some(vec![32]));
+        assert_eq!(journal.flash.data[0..4], MAGIC.to_le_bytes());
+    }
+}
@@ -0,0 +1,160 @@
+//! Erasing and programming with the boot ROM routines.
+
+use core::ops::Range;
+
+use rp2040_hal as hal;
+
+use hal::rom_data;
+
+use super::check_access;
+use super::Error;
+use super::Flash;
+use super::PAGE_SIZE;
+use super::SECTOR_SIZE;
+use super::XIP_BASE;
+
+/// What the SDK passes to `flash_range_erase`: 64 KiB block erases where
+/// the range allows, sector erases elsewhere.
+const BLOCK_SIZE: u32 = 1 << 16;
+const BLOCK_ERASE_CMD: u8 = 0xd8;
+
+extern "C" {
+    static __disk_start: u8;
+    static __disk_end: u8;
+    static __settings_start: u8;
+    static __settings_end: u8;
+}
+
+/// Offsets of the `DISK` region of `memory.x` from the start of the flash.
+pub fn disk_region() -> Range<usize> {
+    // Only the addresses of the linker symbols are used
+    region(
+        core::ptr::addr_of!(__disk_start),
+        core::ptr::addr_of!(__disk_end),
+    )
+}
+
+/// Offsets of the `SETTINGS` region of `memory.x` from the start of the
+/// flash.
+pub fn settings_region() -> Range<usize> {
+    region(
+        core::ptr::addr_of!(__settings_start),
+        core::ptr::addr_of!(__settings_end),
+    )
+}
+
+fn region(start: *const u8, end: *const u8) -> Range<usize> {
+    start as usize - XIP_BASE..end as usize - XIP_BASE
+}
+
+/// A region of the flash the firmware does not occupy.
+pub struct RomFlash {
+    start: usize,
+    capacity: usize,
+}
+
+impl RomFlash {
+    /// # Safety
+    ///
+    /// `region`, in offsets from the start of the flash, must be sector
+    /// aligned and hold neither code nor data of the firmware, and only
+    /// one `RomFlash` may cover it.
+    pub unsafe fn new(region: Range<usize>) -> Self {
+        assert!(region.start.is_multiple_of(SECTOR_SIZE) && region.end.is_multiple_of(SECTOR_SIZE));
+        Self {
+            start: region.start,
+            capacity: region.len(),
+        }
+    }
+}
+
+impl Flash for RomFlash {
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Error> {
+        check_access(self.capacity, offset, buf.len(), 1)?;
+        let src = (XIP_BASE + self.start + offset) as *const u8;
+        // Within the region, which is mapped read-only
+        unsafe { core::ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
+
+    fn erase_sector(&mut self, offset: usize) -> Result<(), Error> {
+        check_access(self.capacity, offset, SECTOR_SIZE, SECTOR_SIZE)?;
+        // The region is not executed from
+        unsafe { write((self.start + offset) as u32, None, SECTOR_SIZE) };
+        Ok(())
+    }
+
+    fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        check_access(self.capacity, offset, data.len(), PAGE_SIZE)?;
+        // The ROM reads the data with the flash out of the XIP mode
+        let mut page = [0u8; PAGE_SIZE];
+        for (i, chunk) in data.chunks(PAGE_SIZE).enumerate() {
+            page.copy_from_slice(chunk);
+            let addr = (self.start + offset + i * PAGE_SIZE) as u32;
+            unsafe { write(addr, Some(&page), PAGE_SIZE) };
+        }
+        Ok(())
+    }
+}
+
+/// The ROM routines, looked up while the flash can still be read.
+struct RomFunctions {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+    enter_xip: unsafe extern "C" fn(),
+}
+
+/// Erases (`data` is `None`) or programs `len` bytes at `addr`.
+///
+/// # Safety
+///
+/// Nothing may be executed from the affected range.
+unsafe fn write(addr: u32, data: Option<&[u8; PAGE_SIZE]>, len: usize) {
+    // boot2 brings the flash back to the fast XIP mode it was in, the ROM
+    // would only restore the slow one. It runs from a copy in RAM.
+    let mut boot2 = [0u32; 64];
+    core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), boot2.len());
+
+    let functions = RomFunctions {
+        connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+        flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+        flash_range_erase: rom_data::flash_range_erase::ptr(),
+        flash_range_program: rom_data::flash_range_program::ptr(),
+        flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+        // Thumb code, the lowest bit of the address is set
+        enter_xip: core::mem::transmute::<usize, unsafe extern "C" fn()>(
+            boot2.as_ptr() as usize + 1,
+        ),
+    };
+
+    cortex_m::interrupt::free(|_| {
+        write_from_ram(
+            &functions,
+            addr,
+            data.map_or(core::ptr::null(), |page| page.as_ptr()),
+            len,
+        )
+    });
+}
+
+/// Runs from RAM: the flash cannot be read until `enter_xip` returns.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn write_from_ram(functions: &RomFunctions, addr: u32, data: *const u8, len: usize) {
+    (functions.connect_internal_flash)();
+    (functions.flash_exit_xip)();
+    if data.is_null() {
+        (functions.flash_range_erase)(addr, len, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    } else {
+        (functions.flash_range_program)(addr, data, len);
+    }
+    (functions.flash_flush_cache)();
+    (functions.enter_xip)();
+}
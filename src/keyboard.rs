@@ -0,0 +1,302 @@
+//! Typing text as a USB keyboard.
+//!
+//! The keyboard is a HID boot keyboard, it sends the 8-byte boot reports
+//! (modifiers, a reserved byte, up to six keys) and works in the BIOS too.
+//! [`Typist`] turns text into those reports with the US layout: every
+//! character is a key press followed by a release, so repeated letters
+//! come out as repeated keystrokes.
+//!
+//! On the target [`SerialKeyboard`] builds a composite device with a CDC
+//! ACM serial port next to the keyboard:
+//!
+//! ```ignore
+//! let mut usb = SerialKeyboard::new(&usb_bus, UsbVidPid(0x16c2, 0x27dd));
+//! loop {
+//!     usb.poll();
+//!     if let Some(report) = typist.report() {
+//!         if usb.keyboard.write_report(report).is_ok() {
+//!             typist.sent();
+//!         }
+//!     }
+//! }
+//! ```
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod usb;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use usb::HidKeyboard;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use usb::SerialKeyboard;
+
+use heapless::Deque;
+
+/// Modifier bits of the report.
+pub const MODIFIER_LEFT_CTRL: u8 = 0x01;
+pub const MODIFIER_LEFT_SHIFT: u8 = 0x02;
+pub const MODIFIER_LEFT_ALT: u8 = 0x04;
+pub const MODIFIER_LEFT_GUI: u8 = 0x08;
+
+/// Keyboard page usages that are not characters.
+pub const USAGE_ENTER: u8 = 0x28;
+pub const USAGE_ESCAPE: u8 = 0x29;
+pub const USAGE_BACKSPACE: u8 = 0x2a;
+pub const USAGE_TAB: u8 = 0x2b;
+pub const USAGE_SPACE: u8 = 0x2c;
+
+/// Report descriptor of the boot keyboard, as in appendix B.1 of the HID
+/// specification: modifiers, a reserved byte, five LED outputs padded to a
+/// byte, and six key slots.
+pub const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x06, // Usage (Keyboard)
+    0xa1, 0x01, // Collection (Application)
+    0x05, 0x07, //   Usage Page (Keyboard)
+    0x19, 0xe0, //   Usage Minimum (Left Control)
+    0x29, 0xe7, //   Usage Maximum (Right GUI)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x08, //   Report Count (8)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x08, //   Report Size (8)
+    0x81, 0x01, //   Input (Constant)
+    0x95, 0x05, //   Report Count (5)
+    0x75, 0x01, //   Report Size (1)
+    0x05, 0x08, //   Usage Page (LEDs)
+    0x19, 0x01, //   Usage Minimum (Num Lock)
+    0x29, 0x05, //   Usage Maximum (Kana)
+    0x91, 0x02, //   Output (Data, Variable, Absolute)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x03, //   Report Size (3)
+    0x91, 0x01, //   Output (Constant)
+    0x95, 0x06, //   Report Count (6)
+    0x75, 0x08, //   Report Size (8)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x65, //   Logical Maximum (101)
+    0x05, 0x07, //   Usage Page (Keyboard)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x29, 0x65, //   Usage Maximum (101)
+    0x81, 0x00, //   Input (Data, Array, Absolute)
+    0xc0, // End Collection
+];
+
+/// The boot keyboard input report.
+pub type Report = [u8; 8];
+
+/// All keys up.
+pub const RELEASED: Report = [0; 8];
+
+/// A key with the modifiers held down along with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub modifiers: u8,
+    pub usage: u8,
+}
+
+impl Key {
+    const fn plain(usage: u8) -> Self {
+        Self {
+            modifiers: 0,
+            usage,
+        }
+    }
+
+    const fn shifted(usage: u8) -> Self {
+        Self {
+            modifiers: MODIFIER_LEFT_SHIFT,
+            usage,
+        }
+    }
+
+    /// The report with this key pressed.
+    pub fn report(self) -> Report {
+        [self.modifiers, 0, self.usage, 0, 0, 0, 0, 0]
+    }
+}
+
+/// Finds the key that types `c` on a US keyboard. Both `\r` and `\n` are
+/// Enter; control characters other than tab, backspace and escape have no
+/// key.
+pub fn us_key(c: u8) -> Option<Key> {
+    let key = match c {
+        b'a'..=b'z' => Key::plain(0x04 + (c - b'a')),
+        b'A'..=b'Z' => Key::shifted(0x04 + (c - b'A')),
+        b'1'..=b'9' => Key::plain(0x1e + (c - b'1')),
+        b'0' => Key::plain(0x27),
+        b'\r' | b'\n' => Key::plain(USAGE_ENTER),
+        0x1b => Key::plain(USAGE_ESCAPE),
+        0x08 | 0x7f => Key::plain(USAGE_BACKSPACE),
+        b'\t' => Key::plain(USAGE_TAB),
+        b' ' => Key::plain(USAGE_SPACE),
+        b'!' => Key::shifted(0x1e),
+        b'@' => Key::shifted(0x1f),
+        b'#' => Key::shifted(0x20),
+        b'$' => Key::shifted(0x21),
+        b'%' => Key::shifted(0x22),
+        b'^' => Key::shifted(0x23),
+        b'&' => Key::shifted(0x24),
+        b'*' => Key::shifted(0x25),
+        b'(' => Key::shifted(0x26),
+        b')' => Key::shifted(0x27),
+        b'-' => Key::plain(0x2d),
+        b'_' => Key::shifted(0x2d),
+        b'=' => Key::plain(0x2e),
+        b'+' => Key::shifted(0x2e),
+        b'[' => Key::plain(0x2f),
+        b'{' => Key::shifted(0x2f),
+        b']' => Key::plain(0x30),
+        b'}' => Key::shifted(0x30),
+        b'\\' => Key::plain(0x31),
+        b'|' => Key::shifted(0x31),
+        b';' => Key::plain(0x33),
+        b':' => Key::shifted(0x33),
+        b'\'' => Key::plain(0x34),
+        b'"' => Key::shifted(0x34),
+        b'`' => Key::plain(0x35),
+        b'~' => Key::shifted(0x35),
+        b',' => Key::plain(0x36),
+        b'<' => Key::shifted(0x36),
+        b'.' => Key::plain(0x37),
+        b'>' => Key::shifted(0x37),
+        b'/' => Key::plain(0x38),
+        b'?' => Key::shifted(0x38),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Queue of text to type, handing out one report at a time.
+pub struct Typist<const N: usize> {
+    text: Deque<u8, N>,
+    report: Option<Report>,
+    key_down: bool,
+}
+
+impl<const N: usize> Typist<N> {
+    pub const fn new() -> Self {
+        Self {
+            text: Deque::new(),
+            report: None,
+            key_down: false,
+        }
+    }
+
+    /// Queues text, returns how many bytes fit. Characters the layout has
+    /// no key for are skipped when their turn comes.
+    pub fn push(&mut self, text: &[u8]) -> usize {
+        text.iter()
+            .take_while(|&&c| self.text.push_back(c).is_ok())
+            .count()
+    }
+
+    /// The report to send next, if any. It stays the same until [`sent`]
+    /// is called, so a report the endpoint had no room for is retried.
+    ///
+    /// [`sent`]: Typist::sent
+    pub fn report(&mut self) -> Option<&Report> {
+        if self.report.is_none() {
+            self.report = if self.key_down {
+                Some(RELEASED)
+            } else {
+                core::iter::from_fn(|| self.text.pop_front())
+                    .find_map(us_key)
+                    .map(Key::report)
+            };
+        }
+        self.report.as_ref()
+    }
+
+    /// The report from [`report`](Typist::report) went out.
+    pub fn sent(&mut self) {
+        if self.report.take().is_some() {
+            self.key_down = !self.key_down;
+        }
+    }
+
+    /// Nothing left to type and all keys are up.
+    pub fn is_idle(&self) -> bool {
+        self.text.is_empty() && self.report.is_none() && !self.key_down
+    }
+}
+
+impl<const N: usize> Default for Typist<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_out<const N: usize>(typist: &mut Typist<N>) -> Vec<Report> {
+        let mut reports = Vec::new();
+        while let Some(&report) = typist.report() {
+            reports.push(report);
+            typist.sent();
+        }
+        reports
+    }
+
+    #[test]
+    fn maps_the_us_layout() {
+        assert_eq!(us_key(b'a'), Some(Key::plain(0x04)));
+        assert_eq!(us_key(b'Z'), Some(Key::shifted(0x1d)));
+        assert_eq!(us_key(b'1'), Some(Key::plain(0x1e)));
+        assert_eq!(us_key(b'0'), Some(Key::plain(0x27)));
+        assert_eq!(us_key(b')'), Some(Key::shifted(0x27)));
+        assert_eq!(us_key(b'\n'), Some(Key::plain(USAGE_ENTER)));
+        assert_eq!(us_key(b'?'), Some(Key::shifted(0x38)));
+        assert_eq!(us_key(0x00), None);
+        assert_eq!(us_key(0x80), None);
+
+        // Every printable character has a key, and no two share one
+        let mut seen = Vec::new();
+        for c in 0x20..0x7f {
+            let key = us_key(c).unwrap();
+            assert!(!seen.contains(&key), "{:?} typed twice", c as char);
+            seen.push(key);
+        }
+    }
+
+    #[test]
+    fn describes_the_boot_keyboard() {
+        assert_eq!(REPORT_DESCRIPTOR.len(), 63);
+        assert_eq!(REPORT_DESCRIPTOR.last(), Some(&0xc0));
+    }
+
+    #[test]
+    fn presses_and_releases_every_key() {
+        let mut typist = Typist::<8>::new();
+        assert!(typist.is_idle());
+        assert_eq!(typist.push(b"aA\x01a"), 4);
+
+        assert_eq!(
+            type_out(&mut typist),
+            [
+                [0, 0, 0x04, 0, 0, 0, 0, 0],
+                RELEASED,
+                [MODIFIER_LEFT_SHIFT, 0, 0x04, 0, 0, 0, 0, 0],
+                RELEASED,
+                [0, 0, 0x04, 0, 0, 0, 0, 0],
+                RELEASED,
+            ]
+        );
+        assert!(typist.is_idle());
+    }
+
+    #[test]
+    fn retries_unsent_reports() {
+        let mut typist = Typist::<4>::new();
+        assert_eq!(typist.push(b"xyz!?"), 4);
+
+        assert_eq!(typist.report(), Some(&[0, 0, 0x1b, 0, 0, 0, 0, 0]));
+        assert_eq!(typist.report(), Some(&[0, 0, 0x1b, 0, 0, 0, 0, 0]));
+        typist.sent();
+        assert_eq!(typist.report(), Some(&RELEASED));
+        typist.sent();
+        assert_eq!(type_out(&mut typist).len(), 6);
+    }
+}
@@ -0,0 +1,198 @@
+//! The boot keyboard as a USB class, and the serial + keyboard device.
+
+use usb_device::class_prelude::*;
+use usb_device::control::Recipient;
+use usb_device::control::Request;
+use usb_device::control::RequestType;
+use usb_device::prelude::*;
+use usbd_serial::SerialPort;
+
+use super::Report;
+use super::REPORT_DESCRIPTOR;
+
+const INTERFACE_CLASS_HID: u8 = 0x03;
+const INTERFACE_SUB_CLASS_BOOT: u8 = 0x01;
+const INTERFACE_PROTOCOL_KEYBOARD: u8 = 0x01;
+
+const DESCRIPTOR_TYPE_HID: u8 = 0x21;
+const DESCRIPTOR_TYPE_REPORT: u8 = 0x22;
+
+const REQUEST_GET_REPORT: u8 = 0x01;
+const REQUEST_GET_IDLE: u8 = 0x02;
+const REQUEST_GET_PROTOCOL: u8 = 0x03;
+const REQUEST_SET_REPORT: u8 = 0x09;
+const REQUEST_SET_IDLE: u8 = 0x0a;
+const REQUEST_SET_PROTOCOL: u8 = 0x0b;
+
+/// HID 1.11, no country code, one report descriptor.
+const HID_DESCRIPTOR: [u8; 7] = [
+    0x11,
+    0x01,
+    0x00,
+    0x01,
+    DESCRIPTOR_TYPE_REPORT,
+    REPORT_DESCRIPTOR.len() as u8,
+    (REPORT_DESCRIPTOR.len() >> 8) as u8,
+];
+
+/// A HID boot keyboard with one interrupt IN endpoint.
+///
+/// The report protocol and the boot protocol send the same reports, so
+/// `SET_PROTOCOL` is only remembered for `GET_PROTOCOL`.
+pub struct HidKeyboard<'a, B: UsbBus> {
+    interface: InterfaceNumber,
+    endpoint: EndpointIn<'a, B>,
+    report: Report,
+    leds: u8,
+    idle: u8,
+    protocol: u8,
+}
+
+impl<'a, B: UsbBus> HidKeyboard<'a, B> {
+    /// Polled by the host every 10 ms.
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            endpoint: alloc.interrupt(8, 10),
+            report: [0; 8],
+            leds: 0,
+            idle: 0,
+            protocol: 1,
+        }
+    }
+
+    /// Queues the report, fails with `WouldBlock` while the previous one
+    /// has not been picked up.
+    pub fn write_report(&mut self, report: &Report) -> usb_device::Result<()> {
+        self.endpoint.write(report)?;
+        self.report = *report;
+        Ok(())
+    }
+
+    /// The LEDs the host wants lit: bit 0 is Num Lock, 1 Caps Lock, 2
+    /// Scroll Lock.
+    pub fn leds(&self) -> u8 {
+        self.leds
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for HidKeyboard<'_, B> {
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        writer.interface(
+            self.interface,
+            INTERFACE_CLASS_HID,
+            INTERFACE_SUB_CLASS_BOOT,
+            INTERFACE_PROTOCOL_KEYBOARD,
+        )?;
+        writer.write(DESCRIPTOR_TYPE_HID, &HID_DESCRIPTOR)?;
+        writer.endpoint(&self.endpoint)
+    }
+
+    fn reset(&mut self) {
+        self.report = [0; 8];
+        self.leds = 0;
+        self.idle = 0;
+        self.protocol = 1;
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        let req = *xfer.request();
+        if req.recipient != Recipient::Interface || req.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        match (req.request_type, req.request) {
+            (RequestType::Standard, Request::GET_DESCRIPTOR) => {
+                match req.descriptor_type_index() {
+                    (DESCRIPTOR_TYPE_REPORT, _) => xfer.accept_with_static(REPORT_DESCRIPTOR).ok(),
+                    (DESCRIPTOR_TYPE_HID, _) => {
+                        let mut descriptor = [0u8; 9];
+                        descriptor[0] = descriptor.len() as u8;
+                        descriptor[1] = DESCRIPTOR_TYPE_HID;
+                        descriptor[2..].copy_from_slice(&HID_DESCRIPTOR);
+                        xfer.accept_with(&descriptor).ok()
+                    }
+                    _ => xfer.reject().ok(),
+                };
+            }
+            (RequestType::Class, REQUEST_GET_REPORT) => {
+                xfer.accept_with(&self.report).ok();
+            }
+            (RequestType::Class, REQUEST_GET_IDLE) => {
+                xfer.accept_with(&[self.idle]).ok();
+            }
+            (RequestType::Class, REQUEST_GET_PROTOCOL) => {
+                xfer.accept_with(&[self.protocol]).ok();
+            }
+            _ => {}
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let req = *xfer.request();
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        match req.request {
+            REQUEST_SET_REPORT => {
+                if let Some(&leds) = xfer.data().first() {
+                    self.leds = leds;
+                }
+                xfer.accept().ok();
+            }
+            REQUEST_SET_IDLE => {
+                // Reports are only sent on changes, whatever the rate
+                self.idle = (req.value >> 8) as u8;
+                xfer.accept().ok();
+            }
+            REQUEST_SET_PROTOCOL => {
+                self.protocol = req.value as u8;
+                xfer.accept().ok();
+            }
+            _ => {
+                xfer.reject().ok();
+            }
+        }
+    }
+}
+
+/// A composite device with a CDC ACM serial port and a boot keyboard.
+///
+/// The classes are public so the firmware can read the port and send
+/// the reports between the polls.
+pub struct SerialKeyboard<'a, B: UsbBus> {
+    pub serial: SerialPort<'a, B>,
+    pub keyboard: HidKeyboard<'a, B>,
+    pub device: UsbDevice<'a, B>,
+}
+
+impl<'a, B: UsbBus> SerialKeyboard<'a, B> {
+    pub fn new(alloc: &'a UsbBusAllocator<B>, vid_pid: UsbVidPid) -> Self {
+        // The classes take their interfaces and endpoints before the device
+        let serial = SerialPort::new(alloc);
+        let keyboard = HidKeyboard::new(alloc);
+        let device = UsbDeviceBuilder::new(alloc, vid_pid)
+            .composite_with_iads()
+            .build();
+
+        Self {
+            serial,
+            keyboard,
+            device,
+        }
+    }
+
+    /// Polls the device, returns true if either class may have data.
+    pub fn poll(&mut self) -> bool {
+        self.device
+            .poll(&mut [&mut self.serial, &mut self.keyboard])
+    }
+}
@@ -0,0 +1,57 @@
+//! Code shared by the examples.
+//!
+//! Modules that touch the hardware are only built for the RP2040 target,
+//! the rest is plain `no_std` code that can be unit-tested on the host with
+//! `cargo test --lib --target <host triple>`.
+//!
+//! The crate also picks the defmt transport for the examples: RTT by
+//! default, USB CDC with the `defmt-usb` feature (see [`usb_log`]).
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(all(target_arch = "arm", target_os = "none", not(feature = "defmt-usb")))]
+use defmt_rtt as _;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub mod board;
+
+pub mod line;
+
+pub mod link;
+
+pub mod modbus;
+
+pub mod nmea;
+
+pub mod uart;
+
+pub mod shell;
+
+pub mod matrix;
+
+pub mod color;
+
+pub mod console;
+
+pub mod crash;
+
+pub mod firmware;
+
+pub mod binary_info;
+
+pub mod usb_log;
+
+pub mod usb_reset;
+
+pub mod keyboard;
+
+pub mod flash;
+
+pub mod msc;
+
+pub mod midi;
+
+pub mod crc;
+
+pub mod ota;
+
+pub mod settings;
@@ -0,0 +1,113 @@
+//! Assembles lines out of a byte stream.
+
+/// Collects bytes until a `\r` or `\n` arrives.
+///
+/// Empty lines (e.g. the `\n` of a `\r\n` pair) are skipped, bytes past
+/// the capacity are dropped and the line is reported as truncated.
+pub struct LineBuffer<const N: usize> {
+    buf: heapless::Vec<u8, N>,
+    complete: bool,
+    truncated: bool,
+}
+
+impl<const N: usize> LineBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: heapless::Vec::new(),
+            complete: false,
+            truncated: false,
+        }
+    }
+
+    /// Feeds one byte, returns the line without the terminator once it is complete.
+    pub fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        if self.complete {
+            self.clear();
+        }
+
+        match byte {
+            b'\r' | b'\n' if self.buf.is_empty() => None,
+            b'\r' | b'\n' => {
+                self.complete = true;
+                Some(&self.buf)
+            }
+            _ => {
+                if self.buf.push(byte).is_err() {
+                    self.truncated = true;
+                }
+                None
+            }
+        }
+    }
+
+    /// The line completed by the last pushed byte.
+    pub fn line(&self) -> Option<&[u8]> {
+        self.complete.then_some(&self.buf)
+    }
+
+    /// Did the last line overflow the buffer?
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.complete = false;
+        self.truncated = false;
+    }
+}
+
+impl<const N: usize> Default for LineBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed<const N: usize>(line: &mut LineBuffer<N>, bytes: &[u8]) -> Vec<Vec<u8>> {
+        bytes
+            .iter()
+            .filter_map(|&b| line.push(b).map(<[u8]>::to_vec))
+            .collect()
+    }
+
+    #[test]
+    fn splits_on_any_terminator() {
+        let mut line = LineBuffer::<16>::new();
+        let lines = feed(&mut line, b"one\rtwo\nthree\r\n");
+
+        assert_eq!(lines, [b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]);
+    }
+
+    #[test]
+    fn skips_empty_lines() {
+        let mut line = LineBuffer::<16>::new();
+
+        assert!(feed(&mut line, b"\r\n\n\r").is_empty());
+    }
+
+    #[test]
+    fn keeps_partial_line() {
+        let mut line = LineBuffer::<16>::new();
+
+        assert!(feed(&mut line, b"par").is_empty());
+        assert_eq!(line.line(), None);
+        assert_eq!(feed(&mut line, b"tial\r"), [b"partial".to_vec()]);
+        assert_eq!(line.line(), Some(&b"partial"[..]));
+    }
+
+    #[test]
+    fn truncates_long_lines() {
+        let mut line = LineBuffer::<4>::new();
+        let lines = feed(&mut line, b"abcdefgh\r");
+
+        assert_eq!(lines, [b"abcd".to_vec()]);
+        assert!(line.truncated());
+
+        assert_eq!(feed(&mut line, b"xy\r"), [b"xy".to_vec()]);
+        assert!(!line.truncated());
+    }
+}
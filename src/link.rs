@@ -0,0 +1,577 @@
+//! Typed messages over a serial line, delivered once and in order.
+//!
+//! Both the firmware, over UART1 or the USB serial port, and the host tool
+//! in `tools/pico-link` use the same [`Link`]. A [`Message`] goes out in a
+//! frame:
+//!
+//! | sequence number, u8 | flags, u8 | session, u32, `START` only | message | CRC-16 or CRC-32 |
+//!
+//! The session and the CRC are little-endian, the CRC covers the rest and
+//! the [`CRC32`] flag picks the longer one. The frame is COBS-encoded and
+//! ends with a zero (see [`cobs`]), so the receiver finds the next frame
+//! after garbage or lost bytes.
+//!
+//! Every message frame is acknowledged with an [`ACK`] frame of the same
+//! sequence number and no message. The sender has one message on its way
+//! at a time and sends it again until the ACK comes, the receiver drops the
+//! copies it already has and acknowledges them again. The messages after a
+//! start carry [`START`] and the session of the sender until one is
+//! acknowledged, so the other side does not take them for copies of the
+//! messages from the session before, numbered from 0 too.
+//!
+//! ```ignore
+//! let mut link = Link::new(link::Config::default(), random_u32);
+//! link.send(&Message::Counter(1), now_us, |frame| port.write_all(frame))?;
+//! loop {
+//!     for byte in received {
+//!         match link.push(byte, |frame| port.write_all(frame)) {
+//!             Some(Event::Message(message)) => ...,
+//!             ...
+//!         }
+//!     }
+//!     link.poll(now_us, |frame| port.write_all(frame));
+//! }
+//! ```
+
+pub mod cobs;
+pub mod message;
+
+pub use message::Message;
+
+use crate::crc::crc16;
+use crate::crc::crc32;
+
+/// The frame acknowledges the message with its sequence number.
+pub const ACK: u8 = 0x01;
+/// The frame ends with a CRC-32 instead of a CRC-16.
+pub const CRC32: u8 = 0x02;
+/// The frame is from the start of a session and carries its number.
+pub const START: u8 = 0x04;
+
+/// The longest encoded message.
+pub const MAX_MESSAGE: usize = 250;
+/// The longest frame before the COBS encoding.
+pub const MAX_FRAME: usize = 2 + 4 + MAX_MESSAGE + 4;
+/// The longest frame on the wire, with the terminating zero.
+pub const MAX_ENCODED: usize = cobs::max_encoded_len(MAX_FRAME) + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// How long to wait for the ACK before sending the message again.
+    pub timeout_us: u64,
+    /// Sends of a message before giving up on it.
+    pub attempts: u8,
+    /// Checks the frames with CRC-32 rather than CRC-16.
+    pub crc32: bool,
+}
+
+impl Default for Config {
+    /// Enough for a USB serial port, or a UART at 115200 baud and up.
+    fn default() -> Self {
+        Self {
+            timeout_us: 100_000,
+            attempts: 5,
+            crc32: false,
+        }
+    }
+}
+
+/// Why a frame was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    Cobs(cobs::DecodeError),
+    /// Shorter than the header, with the session of a `START` frame, and
+    /// the CRC.
+    Short,
+    /// The CRC does not match, the frame was damaged.
+    Crc,
+    /// The frame came through but the message makes no sense, it was
+    /// acknowledged anyway.
+    Message(message::DecodeError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendError {
+    /// The previous message is still waiting for its ACK.
+    Busy,
+    /// The message is longer than [`MAX_MESSAGE`].
+    TooLong,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// A new message from the other side.
+    Message(Message<'a>),
+    /// The other side has the message sent last.
+    Acked,
+    /// No ACK for the message sent last after all the attempts, the link
+    /// is free for the next one.
+    Failed,
+    Dropped(FrameError),
+}
+
+/// The message waiting for its ACK.
+struct Outgoing {
+    frame: [u8; MAX_ENCODED],
+    len: usize,
+    sequence: u8,
+    sent_at: u64,
+    attempts: u8,
+}
+
+/// One end of the link, see the [module](self) docs.
+///
+/// The time is in microseconds from any start, such as the RP2040 timer.
+pub struct Link {
+    config: Config,
+    decoder: cobs::Decoder<MAX_FRAME>,
+    session: u32,
+    next_sequence: u8,
+    /// Whether the other side acknowledged a message of this session.
+    started: bool,
+    outgoing: Option<Outgoing>,
+    /// The session of the other side and the sequence number of the
+    /// message received last.
+    received: Option<(u32, u8)>,
+}
+
+impl Link {
+    /// Starts a session numbered `session`, which should differ from the
+    /// ones before it such as a random number.
+    pub const fn new(config: Config, session: u32) -> Self {
+        Self {
+            config,
+            decoder: cobs::Decoder::new(),
+            session,
+            next_sequence: 0,
+            started: false,
+            outgoing: None,
+            received: None,
+        }
+    }
+
+    /// Whether a message can be sent, the previous one was acknowledged or
+    /// given up.
+    pub fn is_ready(&self) -> bool {
+        self.outgoing.is_none()
+    }
+
+    /// Writes the frame of the message with `write` and keeps it to send
+    /// again until the ACK comes.
+    pub fn send(
+        &mut self,
+        message: &Message,
+        now_us: u64,
+        mut write: impl FnMut(&[u8]),
+    ) -> Result<(), SendError> {
+        if self.outgoing.is_some() {
+            return Err(SendError::Busy);
+        }
+        let mut buf = [0u8; MAX_MESSAGE];
+        let len = message.encode(&mut buf).ok_or(SendError::TooLong)?;
+
+        let sequence = self.next_sequence;
+        let flags = if self.started { 0 } else { START };
+        let mut outgoing = Outgoing {
+            frame: [0; MAX_ENCODED],
+            len: 0,
+            sequence,
+            sent_at: now_us,
+            attempts: 1,
+        };
+        outgoing.len = frame_into(
+            self.config.crc32,
+            sequence,
+            flags,
+            self.session,
+            &buf[..len],
+            &mut outgoing.frame,
+        );
+        write(&outgoing.frame[..outgoing.len]);
+
+        self.next_sequence = sequence.wrapping_add(1);
+        self.outgoing = Some(outgoing);
+        Ok(())
+    }
+
+    /// Sends the message again when its ACK is late, call often.
+    pub fn poll(&mut self, now_us: u64, mut write: impl FnMut(&[u8])) -> Option<Event<'_>> {
+        let outgoing = self.outgoing.as_mut()?;
+        if now_us.wrapping_sub(outgoing.sent_at) < self.config.timeout_us {
+            return None;
+        }
+        if outgoing.attempts >= self.config.attempts {
+            self.outgoing = None;
+            return Some(Event::Failed);
+        }
+        write(&outgoing.frame[..outgoing.len]);
+        outgoing.sent_at = now_us;
+        outgoing.attempts += 1;
+        None
+    }
+
+    /// Takes the next received byte. Acknowledges the messages with
+    /// `write`.
+    pub fn push(&mut self, byte: u8, mut write: impl FnMut(&[u8])) -> Option<Event<'_>> {
+        let frame = match self.decoder.push(byte)? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Event::Dropped(FrameError::Cobs(e))),
+        };
+        let Frame {
+            sequence,
+            flags,
+            session,
+            message,
+        } = match parse(frame) {
+            Ok(parsed) => parsed,
+            Err(e) => return Some(Event::Dropped(e)),
+        };
+
+        if flags & ACK != 0 {
+            let acked = self
+                .outgoing
+                .as_ref()
+                .is_some_and(|outgoing| outgoing.sequence == sequence);
+            if !acked {
+                // For a message sent before, or already given up
+                return None;
+            }
+            self.outgoing = None;
+            self.started = true;
+            return Some(Event::Acked);
+        }
+
+        let mut ack = [0u8; cobs::max_encoded_len(6) + 1];
+        let len = frame_into(self.config.crc32, sequence, ACK, 0, &[], &mut ack);
+        write(&ack[..len]);
+
+        // Without `START` the frame belongs to the session received last.
+        // The `START` of another session replaces it, so its first numbers
+        // are not taken for copies.
+        let session = session.or(self.received.map(|(session, _)| session));
+        let received = (session.unwrap_or(0), sequence);
+        if self.received == Some(received) {
+            // A copy, the ACK of the first one got lost
+            return None;
+        }
+        self.received = Some(received);
+        Some(match Message::decode(message) {
+            Ok(message) => Event::Message(message),
+            Err(e) => Event::Dropped(FrameError::Message(e)),
+        })
+    }
+}
+
+/// Builds and encodes a frame into `out`, returns its length on the wire.
+/// The session goes in only with `START`.
+fn frame_into(
+    crc32_check: bool,
+    sequence: u8,
+    flags: u8,
+    session: u32,
+    message: &[u8],
+    out: &mut [u8],
+) -> usize {
+    let mut frame = [0u8; MAX_FRAME];
+    let flags = if crc32_check { flags | CRC32 } else { flags };
+    frame[0] = sequence;
+    frame[1] = flags;
+    let mut len = 2;
+    if flags & START != 0 {
+        frame[len..len + 4].copy_from_slice(&session.to_le_bytes());
+        len += 4;
+    }
+    frame[len..len + message.len()].copy_from_slice(message);
+    len += message.len();
+    if crc32_check {
+        let crc = crc32(&frame[..len]);
+        frame[len..len + 4].copy_from_slice(&crc.to_le_bytes());
+        len += 4;
+    } else {
+        let crc = crc16(&frame[..len]);
+        frame[len..len + 2].copy_from_slice(&crc.to_le_bytes());
+        len += 2;
+    }
+    let encoded = cobs::encode(&frame[..len], out);
+    out[encoded] = 0;
+    encoded + 1
+}
+
+/// A decoded frame.
+struct Frame<'a> {
+    sequence: u8,
+    flags: u8,
+    /// Only in `START` frames.
+    session: Option<u32>,
+    message: &'a [u8],
+}
+
+fn parse(frame: &[u8]) -> Result<Frame<'_>, FrameError> {
+    let flags = *frame.get(1).ok_or(FrameError::Short)?;
+    let crc_len = if flags & CRC32 != 0 { 4 } else { 2 };
+    if frame.len() < 2 + crc_len {
+        return Err(FrameError::Short);
+    }
+    let (data, crc) = frame.split_at(frame.len() - crc_len);
+    let valid = if crc_len == 4 {
+        crc == crc32(data).to_le_bytes()
+    } else {
+        crc == crc16(data).to_le_bytes()
+    };
+    if !valid {
+        return Err(FrameError::Crc);
+    }
+    let (session, message) = if flags & START != 0 {
+        let session = data.get(2..6).ok_or(FrameError::Short)?;
+        (
+            Some(u32::from_le_bytes(session.try_into().unwrap())),
+            &data[6..],
+        )
+    } else {
+        (None, &data[2..])
+    };
+    Ok(Frame {
+        sequence: data[0],
+        flags,
+        session,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frames written, split at the terminating zeros.
+    fn frames(written: &[u8]) -> Vec<Vec<u8>> {
+        written
+            .split_inclusive(|&byte| byte == 0)
+            .map(<[u8]>::to_vec)
+            .collect()
+    }
+
+    /// Feeds the bytes to the link, returns the events as text and the
+    /// frames it wrote.
+    fn receive(link: &mut Link, bytes: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let mut written = Vec::new();
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if let Some(event) = link.push(byte, |frame| written.extend_from_slice(frame)) {
+                events.push(format!("{event:?}"));
+            }
+        }
+        (events, written)
+    }
+
+    #[test]
+    fn delivers_and_acknowledges() {
+        for crc32 in [false, true] {
+            let config = Config {
+                crc32,
+                ..Config::default()
+            };
+            let mut host = Link::new(config, 1);
+            let mut device = Link::new(config, 2);
+
+            let mut wire = Vec::new();
+            host.send(&Message::Text("hi"), 0, |frame| {
+                wire.extend_from_slice(frame)
+            })
+            .unwrap();
+            assert!(!host.is_ready());
+            assert_eq!(
+                host.send(&Message::Ping(1), 0, |_| {}),
+                Err(SendError::Busy)
+            );
+            assert_eq!(wire.iter().filter(|&&byte| byte == 0).count(), 1);
+
+            let (events, ack) = receive(&mut device, &wire);
+            assert_eq!(events, [r#"Message(Text("hi"))"#]);
+            let (events, reply) = receive(&mut host, &ack);
+            assert_eq!(events, ["Acked"]);
+            assert!(reply.is_empty());
+            assert!(host.is_ready());
+        }
+    }
+
+    #[test]
+    fn retransmits_until_acknowledged() {
+        let mut host = Link::new(Config::default(), 1);
+        let mut device = Link::new(Config::default(), 2);
+
+        let mut wire = Vec::new();
+        host.send(&Message::Counter(7), 0, |frame| {
+            wire.extend_from_slice(frame)
+        })
+        .unwrap();
+        // The first ACK gets lost
+        let (events, _) = receive(&mut device, &wire);
+        assert_eq!(events, ["Message(Counter(7))"]);
+
+        assert!(host.poll(99_999, |_| panic!("too early")).is_none());
+        let mut again = Vec::new();
+        assert!(host
+            .poll(100_000, |frame| again.extend_from_slice(frame))
+            .is_none());
+        assert_eq!(again, wire);
+
+        // The copy is acknowledged again, not delivered
+        let (events, ack) = receive(&mut device, &again);
+        assert!(events.is_empty());
+        let (events, _) = receive(&mut host, &ack);
+        assert_eq!(events, ["Acked"]);
+
+        // The next message has the next number
+        let mut next = Vec::new();
+        host.send(&Message::Counter(8), 200_000, |frame| {
+            next.extend_from_slice(frame)
+        })
+        .unwrap();
+        let (events, _) = receive(&mut device, &next);
+        assert_eq!(events, ["Message(Counter(8))"]);
+    }
+
+    #[test]
+    fn gives_up_after_the_attempts() {
+        let mut host = Link::new(Config::default(), 1);
+        let mut sent = 0;
+        host.send(&Message::Led(true), 0, |_| sent += 1).unwrap();
+        let mut events = Vec::new();
+        for now in (1..10).map(|i| i * 100_000) {
+            if let Some(event) = host.poll(now, |_| sent += 1) {
+                events.push(format!("{event:?}"));
+            }
+        }
+        assert_eq!(sent, 5);
+        assert_eq!(events, ["Failed"]);
+        assert!(host.is_ready());
+    }
+
+    #[test]
+    fn drops_damaged_frames() {
+        let mut host = Link::new(Config::default(), 1);
+        let mut device = Link::new(Config::default(), 2);
+        let mut wire = Vec::new();
+        host.send(&Message::Ping(0x55), 0, |frame| {
+            wire.extend_from_slice(frame)
+        })
+        .unwrap();
+
+        let mut damaged = wire.clone();
+        damaged[3] ^= 0x08;
+        let (events, ack) = receive(&mut device, &damaged);
+        assert_eq!(events, ["Dropped(Crc)"]);
+        assert!(ack.is_empty());
+
+        // Noise and a cut frame before the good one
+        let mut noisy = vec![0x13, 0x37, 0x00];
+        noisy.extend_from_slice(&wire[..3]);
+        noisy.push(0);
+        noisy.extend_from_slice(&wire);
+        let (events, ack) = receive(&mut device, &noisy);
+        assert_eq!(
+            events,
+            [
+                "Dropped(Cobs(Truncated))",
+                "Dropped(Cobs(Truncated))",
+                "Message(Ping(85))"
+            ]
+        );
+        assert_eq!(frames(&ack).len(), 1);
+    }
+
+    #[test]
+    fn takes_the_first_message_after_a_restart() {
+        let mut device = Link::new(Config::default(), 2);
+        let mut wire = Vec::new();
+        let mut host = Link::new(Config::default(), 1);
+        host.send(&Message::Ping(1), 0, |frame| wire.extend_from_slice(frame))
+            .unwrap();
+        let (events, ack) = receive(&mut device, &wire);
+        assert_eq!(events, ["Message(Ping(1))"]);
+        receive(&mut host, &ack);
+
+        // 256 messages later the numbers come round
+        for i in 1..=256u32 {
+            let mut wire = Vec::new();
+            host.send(&Message::Counter(i), 0, |frame| {
+                wire.extend_from_slice(frame)
+            })
+            .unwrap();
+            let (events, ack) = receive(&mut device, &wire);
+            assert_eq!(events, [format!("Message(Counter({i}))")]);
+            receive(&mut host, &ack);
+        }
+
+        // The host restarts and number 0 comes again, in another session
+        let mut host = Link::new(Config::default(), 3);
+        let mut wire = Vec::new();
+        host.send(&Message::Ping(2), 0, |frame| wire.extend_from_slice(frame))
+            .unwrap();
+        let (events, _) = receive(&mut device, &wire);
+        assert_eq!(events, ["Message(Ping(2))"]);
+    }
+
+    #[test]
+    fn takes_the_first_message_after_each_restart() {
+        let mut device = Link::new(Config::default(), 1);
+        // Every session is cut short after its first message
+        for session in 2..5 {
+            let mut host = Link::new(Config::default(), session);
+            let mut wire = Vec::new();
+            host.send(&Message::Ping(session), 0, |frame| {
+                wire.extend_from_slice(frame)
+            })
+            .unwrap();
+            let (events, ack) = receive(&mut device, &wire);
+            assert_eq!(events, [format!("Message(Ping({session}))")]);
+            // A copy of it is still a copy
+            let (events, _) = receive(&mut device, &wire);
+            assert!(events.is_empty());
+            let (events, _) = receive(&mut host, &ack);
+            assert_eq!(events, ["Acked"]);
+        }
+    }
+
+    #[test]
+    fn starts_until_acknowledged() {
+        let mut device = Link::new(Config::default(), 1);
+        let mut host = Link::new(Config::default(), 2);
+        for i in 0..2 {
+            let mut wire = Vec::new();
+            host.send(&Message::Counter(i), 0, |frame| {
+                wire.extend_from_slice(frame)
+            })
+            .unwrap();
+            let (_, ack) = receive(&mut device, &wire);
+            receive(&mut host, &ack);
+        }
+
+        // The host restarts and its number 0 never gets through
+        let mut host = Link::new(Config::default(), 3);
+        host.send(&Message::Counter(0), 0, |_| {}).unwrap();
+        for now in (1..6).map(|i| i * 100_000) {
+            host.poll(now, |_| {});
+        }
+        assert!(host.is_ready());
+
+        // Number 1 still carries `START`, it is no copy of the last one
+        let mut wire = Vec::new();
+        host.send(&Message::Counter(1), 600_000, |frame| {
+            wire.extend_from_slice(frame)
+        })
+        .unwrap();
+        let (events, ack) = receive(&mut device, &wire);
+        assert_eq!(events, ["Message(Counter(1))"]);
+        receive(&mut host, &ack);
+
+        // Acknowledged, the next one goes without
+        let mut next = Vec::new();
+        host.send(&Message::Counter(2), 700_000, |frame| {
+            next.extend_from_slice(frame)
+        })
+        .unwrap();
+        let (events, _) = receive(&mut device, &next);
+        assert_eq!(events, ["Message(Counter(2))"]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Consistent Overhead Byte Stuffing.
+//!
+//! The encoding takes the zeros out of a frame for a byte every 254, so a
+//! zero can mark the end of each frame: a receiver that starts in the
+//! middle of one, or loses bytes, is back in step at the next zero.
+
+/// The longest encoding of `len` bytes, without the terminating zero.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Encodes `data` into `out`, returns the length of the encoding. The
+/// terminating zero is up to the caller.
+///
+/// Panics if `out` is shorter than [`max_encoded_len`] of the data.
+pub fn encode(data: &[u8], out: &mut [u8]) -> usize {
+    // Each block is its length code and up to 254 bytes other than zero,
+    // the code of a shorter block stands for the zero after it
+    let mut code_at = 0;
+    let mut len = 1;
+    for &byte in data {
+        if byte != 0 {
+            out[len] = byte;
+            len += 1;
+        }
+        if byte == 0 || len - code_at == 0xff {
+            out[code_at] = (len - code_at) as u8;
+            code_at = len;
+            len += 1;
+        }
+    }
+    out[code_at] = (len - code_at) as u8;
+    len
+}
+
+/// Why a frame was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A zero in the middle of a block, the frame lost bytes.
+    Truncated,
+    /// Longer than the buffer.
+    TooLong,
+}
+
+/// Decodes the frames from the bytes as they come, into a buffer of `N`
+/// bytes.
+pub struct Decoder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    /// The code of the current block, and how many of its bytes are left.
+    code: u8,
+    left: u8,
+    too_long: bool,
+}
+
+impl<const N: usize> Default for Decoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Decoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            code: 0,
+            left: 0,
+            too_long: false,
+        }
+    }
+
+    /// Takes the next byte, returns the decoded frame at the terminating
+    /// zero. Zeros between frames are skipped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], DecodeError>> {
+        if byte == 0 {
+            let started = self.code != 0;
+            let result = match (self.too_long, self.left) {
+                (true, _) => Err(DecodeError::TooLong),
+                (false, 0) => Ok(()),
+                (false, _) => Err(DecodeError::Truncated),
+            };
+            let len = self.len;
+            (self.len, self.code, self.left, self.too_long) = (0, 0, 0, false);
+            return started.then_some(result.map(|()| &self.buf[..len]));
+        }
+
+        if self.left == 0 {
+            // The zero a shorter block stands for, as more data follows
+            if self.code != 0 && self.code != 0xff {
+                self.append(0);
+            }
+            self.code = byte;
+            self.left = byte - 1;
+        } else {
+            self.append(byte);
+            self.left -= 1;
+        }
+        None
+    }
+
+    fn append(&mut self, byte: u8) {
+        match self.buf.get_mut(self.len) {
+            Some(slot) => {
+                *slot = byte;
+                self.len += 1;
+            }
+            None => self.too_long = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; max_encoded_len(data.len())];
+        let len = encode(data, &mut out);
+        out.truncate(len);
+        out
+    }
+
+    fn decoded(encoded: &[u8]) -> Vec<Result<Vec<u8>, DecodeError>> {
+        let mut decoder = Decoder::<600>::new();
+        encoded
+            .iter()
+            .filter_map(|&byte| decoder.push(byte).map(|r| r.map(<[u8]>::to_vec)))
+            .collect()
+    }
+
+    #[test]
+    fn encodes_the_known_examples() {
+        assert_eq!(encoded(&[]), [0x01]);
+        assert_eq!(encoded(&[0x00]), [0x01, 0x01]);
+        assert_eq!(encoded(&[0x00, 0x00]), [0x01, 0x01, 0x01]);
+        assert_eq!(
+            encoded(&[0x11, 0x22, 0x00, 0x33]),
+            [0x03, 0x11, 0x22, 0x02, 0x33]
+        );
+        assert_eq!(
+            encoded(&[0x11, 0x00, 0x00, 0x00]),
+            [0x02, 0x11, 0x01, 0x01, 0x01]
+        );
+
+        let long: Vec<u8> = (1..=255).collect();
+        let mut expected = vec![0xff];
+        expected.extend(1..=254);
+        expected.extend([0x02, 0xff]);
+        assert_eq!(encoded(&long), expected);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let frames: [Vec<u8>; 5] = [
+            vec![],
+            vec![0],
+            vec![1, 0, 2, 0, 0],
+            (0..=255).collect(),
+            (0..600).map(|i| (i % 7) as u8).collect(),
+        ];
+        for frame in &frames {
+            let mut stream = encoded(frame);
+            stream.push(0);
+            assert_eq!(decoded(&stream), [Ok(frame.clone())], "{frame:?}");
+        }
+
+        // No zeros, around the block boundaries
+        for len in [253, 254, 255, 508, 509] {
+            let frame: Vec<u8> = (0..len).map(|i| (i % 255 + 1) as u8).collect();
+            let mut stream = encoded(&frame);
+            stream.push(0);
+            assert_eq!(decoded(&stream), [Ok(frame)], "{len}");
+        }
+    }
+
+    #[test]
+    fn resynchronises_at_zero() {
+        let mut stream = vec![0, 0];
+        stream.extend(encoded(b"lost"));
+        // Cut in the middle of a block
+        stream.truncate(3);
+        stream.push(0);
+        stream.extend(encoded(b"next"));
+        stream.push(0);
+        assert_eq!(
+            decoded(&stream),
+            [Err(DecodeError::Truncated), Ok(b"next".to_vec())]
+        );
+
+        let mut decoder = Decoder::<4>::new();
+        let mut stream = encoded(b"toolong");
+        stream.push(0);
+        let results: Vec<_> = stream
+            .iter()
+            .filter_map(|&byte| decoder.push(byte).map(|r| r.map(<[u8]>::to_vec)))
+            .collect();
+        assert_eq!(results, [Err(DecodeError::TooLong)]);
+    }
+}
@@ -0,0 +1,221 @@
+//! The messages and their encoding.
+//!
+//! A message is a tag byte and the fields in order: numbers as LEB128
+//! varints (signed ones zigzagged first), so small ones take a byte,
+//! booleans as a byte, strings as their length and the UTF-8 bytes.
+
+/// What the firmware and the host tell each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message<'a> {
+    /// Asks for a [`Message::Pong`] with the same number.
+    Ping(u32),
+    Pong(u32),
+    /// The count the firmware sends every second.
+    Counter(u32),
+    /// Turns the on-board LED on or off.
+    Led(bool),
+    /// The temperature of the chip in tenths of a degree Celsius.
+    Temperature(i32),
+    Text(&'a str),
+}
+
+/// Why a payload is not a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A message this side does not know of, maybe from a newer peer.
+    UnknownTag(u8),
+    /// Ends before the last field.
+    Truncated,
+    /// A field out of range, or bytes past the last field.
+    Malformed,
+}
+
+impl<'a> Message<'a> {
+    /// Encodes the message into `out`, returns the length or `None` if it
+    /// does not fit.
+    pub fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer { out, len: 0 };
+        match *self {
+            Message::Ping(number) => {
+                writer.byte(1)?;
+                writer.varint(number)?;
+            }
+            Message::Pong(number) => {
+                writer.byte(2)?;
+                writer.varint(number)?;
+            }
+            Message::Counter(count) => {
+                writer.byte(3)?;
+                writer.varint(count)?;
+            }
+            Message::Led(on) => {
+                writer.byte(4)?;
+                writer.byte(u8::from(on))?;
+            }
+            Message::Temperature(tenths) => {
+                writer.byte(5)?;
+                writer.varint(((tenths << 1) ^ (tenths >> 31)) as u32)?;
+            }
+            Message::Text(text) => {
+                writer.byte(6)?;
+                writer.varint(text.len() as u32)?;
+                writer.bytes(text.as_bytes())?;
+            }
+        }
+        Some(writer.len)
+    }
+
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        let message = match reader.byte()? {
+            1 => Message::Ping(reader.varint()?),
+            2 => Message::Pong(reader.varint()?),
+            3 => Message::Counter(reader.varint()?),
+            4 => Message::Led(match reader.byte()? {
+                0 => false,
+                1 => true,
+                _ => return Err(DecodeError::Malformed),
+            }),
+            5 => {
+                let zigzag = reader.varint()?;
+                Message::Temperature((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+            }
+            6 => {
+                let len = reader.varint()? as usize;
+                let text =
+                    core::str::from_utf8(reader.bytes(len)?).map_err(|_| DecodeError::Malformed)?;
+                Message::Text(text)
+            }
+            tag => return Err(DecodeError::UnknownTag(tag)),
+        };
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::Malformed);
+        }
+        Ok(message)
+    }
+}
+
+struct Writer<'o> {
+    out: &'o mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn byte(&mut self, byte: u8) -> Option<()> {
+        *self.out.get_mut(self.len)? = byte;
+        self.len += 1;
+        Some(())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        self.out
+            .get_mut(self.len..self.len + bytes.len())?
+            .copy_from_slice(bytes);
+        self.len += bytes.len();
+        Some(())
+    }
+
+    fn varint(&mut self, mut value: u32) -> Option<()> {
+        while value >= 0x80 {
+            self.byte(value as u8 | 0x80)?;
+            value >>= 7;
+        }
+        self.byte(value as u8)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::Truncated)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u32, DecodeError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            // The fifth byte has room for the top 4 bits only
+            if shift == 28 && byte > 0x0f {
+                return Err(DecodeError::Malformed);
+            }
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(message: &Message) -> Vec<u8> {
+        let mut out = [0u8; 64];
+        let len = message.encode(&mut out).unwrap();
+        out[..len].to_vec()
+    }
+
+    #[test]
+    fn encodes_compactly() {
+        assert_eq!(encoded(&Message::Ping(1)), [1, 1]);
+        assert_eq!(encoded(&Message::Counter(300)), [3, 0xac, 0x02]);
+        assert_eq!(
+            encoded(&Message::Pong(u32::MAX)),
+            [2, 0xff, 0xff, 0xff, 0xff, 0x0f]
+        );
+        assert_eq!(encoded(&Message::Led(true)), [4, 1]);
+        assert_eq!(encoded(&Message::Temperature(-1)), [5, 1]);
+        assert_eq!(encoded(&Message::Temperature(1)), [5, 2]);
+        assert_eq!(encoded(&Message::Text("hi")), [6, 2, b'h', b'i']);
+        assert_eq!(Message::Text("too long").encode(&mut [0; 4]), None);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let messages = [
+            Message::Ping(0),
+            Message::Pong(0x1234_5678),
+            Message::Counter(u32::MAX),
+            Message::Led(false),
+            Message::Temperature(i32::MIN),
+            Message::Temperature(i32::MAX),
+            Message::Temperature(-273),
+            Message::Text(""),
+            Message::Text("Grüße"),
+        ];
+        for message in messages {
+            assert_eq!(Message::decode(&encoded(&message)), Ok(message));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        assert_eq!(Message::decode(&[]), Err(DecodeError::Truncated));
+        assert_eq!(Message::decode(&[9]), Err(DecodeError::UnknownTag(9)));
+        assert_eq!(Message::decode(&[1, 0x80]), Err(DecodeError::Truncated));
+        assert_eq!(
+            Message::decode(&[1, 0xff, 0xff, 0xff, 0xff, 0x10]),
+            Err(DecodeError::Malformed)
+        );
+        assert_eq!(Message::decode(&[1, 1, 1]), Err(DecodeError::Malformed));
+        assert_eq!(Message::decode(&[4, 2]), Err(DecodeError::Malformed));
+        assert_eq!(Message::decode(&[6, 3, b'a']), Err(DecodeError::Truncated));
+        assert_eq!(Message::decode(&[6, 1, 0xff]), Err(DecodeError::Malformed));
+    }
+}
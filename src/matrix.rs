@@ -0,0 +1,300 @@
+//! Text on a WS2812 LED matrix.
+//!
+//! [`Matrix`] maps the `x`/`y` coordinates onto the LED strip (`x` grows to
+//! the right, `y` grows downwards), [`Marquee`] scrolls a string across it
+//! one pixel at a time.
+
+mod font;
+
+pub use font::FONT_5X5;
+
+use smart_leds::RGB8;
+
+/// Glyph size in pixels.
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Horizontal distance between the glyphs, one blank column included.
+pub const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+/// How the strip is laid out on the matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// The first LED is in the top left corner, every row runs to the right.
+    TopLeft,
+    /// The first LED is in the bottom right corner, every row runs to the
+    /// left. This is how the Waveshare RP2040-Matrix is wired.
+    BottomRight,
+    /// The first LED is in the top left corner, the rows alternate their
+    /// direction.
+    Serpentine,
+}
+
+/// A `width` by `height` view on the LED colours.
+pub struct Matrix<'a> {
+    leds: &'a mut [RGB8],
+    width: usize,
+    height: usize,
+    layout: Layout,
+}
+
+impl<'a> Matrix<'a> {
+    /// Panics if there are fewer than `width * height` LEDs.
+    pub fn new(leds: &'a mut [RGB8], width: usize, height: usize, layout: Layout) -> Self {
+        assert!(leds.len() >= width * height);
+
+        Self {
+            leds,
+            width,
+            height,
+            layout,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Index of the LED at `x`, `y` in the strip.
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        match self.layout {
+            Layout::TopLeft => y * self.width + x,
+            Layout::BottomRight => (self.height - 1 - y) * self.width + (self.width - 1 - x),
+            Layout::Serpentine if y.is_multiple_of(2) => y * self.width + x,
+            Layout::Serpentine => y * self.width + (self.width - 1 - x),
+        }
+    }
+
+    pub fn fill(&mut self, color: RGB8) {
+        self.leds[..self.width * self.height].fill(color);
+    }
+
+    /// Sets the pixel, the coordinates outside the matrix are ignored.
+    pub fn set(&mut self, x: i32, y: i32, color: RGB8) {
+        if let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) {
+            if x < self.width && y < self.height {
+                let index = self.index(x, y);
+                self.leds[index] = color;
+            }
+        }
+    }
+
+    /// Draws the text with the top left corner of the first glyph at `x`, `y`.
+    ///
+    /// The glyphs may be partially or fully outside the matrix. Without the
+    /// background colour only the lit pixels are drawn.
+    pub fn draw_text(&mut self, text: &[u8], x: i32, y: i32, fg: RGB8, bg: Option<RGB8>) {
+        for (i, &ascii_sym) in text.iter().enumerate() {
+            let cell_x = x + (i * CELL_WIDTH) as i32;
+            if cell_x >= self.width as i32 {
+                break;
+            }
+            if cell_x + (CELL_WIDTH as i32) <= 0 {
+                continue;
+            }
+            self.draw_glyph(ascii_sym, cell_x, y, fg, bg);
+        }
+    }
+
+    fn draw_glyph(&mut self, ascii_sym: u8, x: i32, y: i32, fg: RGB8, bg: Option<RGB8>) {
+        let glyph = &FONT_5X5[ascii_sym as usize];
+        for (row, &scan_line) in glyph.iter().enumerate() {
+            for col in 0..CELL_WIDTH {
+                // The spacing column is always blank
+                let lit = col < GLYPH_WIDTH && scan_line & (1 << (GLYPH_WIDTH - 1 - col)) != 0;
+                let color = if lit { Some(fg) } else { bg };
+                if let Some(color) = color {
+                    self.set(x + col as i32, y + row as i32, color);
+                }
+            }
+        }
+    }
+}
+
+/// Scrolls the text from the right to the left, wrapping around.
+pub struct Marquee<'t> {
+    text: &'t [u8],
+    position: usize,
+}
+
+impl<'t> Marquee<'t> {
+    pub fn new(text: &'t [u8]) -> Self {
+        Self { text, position: 0 }
+    }
+
+    /// Width of the text in pixels, the marquee repeats with this period.
+    pub fn width(&self) -> usize {
+        self.text.len() * CELL_WIDTH
+    }
+
+    /// Moves the text one pixel to the left.
+    pub fn step(&mut self) {
+        self.position += 1;
+        if self.position >= self.width() {
+            self.position = 0;
+        }
+    }
+
+    /// Clears the matrix and draws the visible part of the text, centred vertically.
+    pub fn render(&self, matrix: &mut Matrix<'_>, fg: RGB8, bg: RGB8) {
+        matrix.fill(bg);
+
+        let width = self.width() as i32;
+        if width == 0 {
+            return;
+        }
+
+        let y = (matrix.height() as i32 - GLYPH_HEIGHT as i32) / 2;
+        let mut x = -(self.position as i32);
+        while x < matrix.width() as i32 {
+            matrix.draw_text(self.text, x, y, fg, None);
+            x += width;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FG: RGB8 = RGB8::new(1, 2, 3);
+    const BG: RGB8 = RGB8::new(0, 0, 0);
+
+    /// The renderer the original matrix example used, one glyph on 5x5 LEDs.
+    fn render_ascii(
+        leds: &mut [RGB8; 25],
+        ascii_sym: u8,
+        fg_color: &RGB8,
+        bg_color: Option<&RGB8>,
+    ) {
+        let glyph = &FONT_5X5[ascii_sym as usize];
+        let mut led_idx = 0;
+        for &scan_line in glyph.iter().rev() {
+            for pixel_idx in 0..5 {
+                if scan_line & 1u8.wrapping_shl(pixel_idx) != 0 {
+                    leds[led_idx] = *fg_color;
+                } else if let Some(bg_color) = bg_color {
+                    leds[led_idx] = *bg_color;
+                }
+                led_idx += 1;
+            }
+        }
+    }
+
+    /// Draws lit pixels as `#`, one row per string.
+    fn picture(matrix: &Matrix<'_>) -> Vec<String> {
+        (0..matrix.height)
+            .map(|y| {
+                (0..matrix.width)
+                    .map(|x| {
+                        if matrix.leds[matrix.index(x, y)] == FG {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_original_renderer() {
+        for ascii_sym in 0..=255u8 {
+            let mut expected = [BG; 25];
+            render_ascii(&mut expected, ascii_sym, &FG, Some(&BG));
+
+            let mut leds = [BG; 25];
+            let mut matrix = Matrix::new(&mut leds, 5, 5, Layout::BottomRight);
+            matrix.draw_text(&[ascii_sym], 0, 0, FG, Some(BG));
+
+            assert_eq!(leds, expected, "{ascii_sym}");
+        }
+    }
+
+    #[test]
+    fn maps_layouts() {
+        let mut leds = [BG; 6];
+        let top_left = Matrix::new(&mut leds, 3, 2, Layout::TopLeft);
+        assert_eq!(top_left.index(0, 0), 0);
+        assert_eq!(top_left.index(2, 0), 2);
+        assert_eq!(top_left.index(0, 1), 3);
+
+        let bottom_right = Matrix::new(&mut leds, 3, 2, Layout::BottomRight);
+        assert_eq!(bottom_right.index(2, 1), 0);
+        assert_eq!(bottom_right.index(0, 1), 2);
+        assert_eq!(bottom_right.index(0, 0), 5);
+
+        let serpentine = Matrix::new(&mut leds, 3, 2, Layout::Serpentine);
+        assert_eq!(serpentine.index(2, 0), 2);
+        assert_eq!(serpentine.index(2, 1), 3);
+        assert_eq!(serpentine.index(0, 1), 5);
+    }
+
+    #[test]
+    fn clips_text() {
+        let mut leds = [BG; 12];
+        let mut matrix = Matrix::new(&mut leds, 4, 3, Layout::TopLeft);
+        matrix.draw_text(b"T", -1, -2, FG, None);
+
+        assert_eq!(picture(&matrix), [".#..", ".#..", ".#.."]);
+    }
+
+    #[test]
+    fn scrolls_one_pixel_at_a_time() {
+        let mut leds = [BG; 35];
+        let mut matrix = Matrix::new(&mut leds, 7, 5, Layout::Serpentine);
+        let mut marquee = Marquee::new(b"I-");
+
+        marquee.render(&mut matrix, FG, BG);
+        let first = picture(&matrix);
+        assert_eq!(first[0], ".###...");
+        assert_eq!(first[2], "..#....");
+
+        marquee.step();
+        marquee.render(&mut matrix, FG, BG);
+        let second = picture(&matrix);
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a[1..], b[..6]);
+        }
+        // The next glyph comes in on the right
+        assert_eq!(second[2], ".#....#");
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut leds = [BG; 35];
+        let mut matrix = Matrix::new(&mut leds, 7, 5, Layout::TopLeft);
+        let mut marquee = Marquee::new(b"I");
+
+        // The text is shorter than the matrix, its start repeats on the right
+        marquee.render(&mut matrix, FG, BG);
+        assert_eq!(picture(&matrix)[0], ".###...");
+
+        for _ in 0..4 {
+            marquee.step();
+        }
+        marquee.render(&mut matrix, FG, BG);
+        assert_eq!(picture(&matrix)[0], "...###.");
+
+        for _ in 4..marquee.width() {
+            marquee.step();
+        }
+        assert_eq!(marquee.position, 0);
+    }
+
+    #[test]
+    fn centres_vertically() {
+        let mut leds = [BG; 7 * 9];
+        let mut matrix = Matrix::new(&mut leds, 7, 9, Layout::TopLeft);
+        Marquee::new(b"_").render(&mut matrix, FG, BG);
+
+        let picture = picture(&matrix);
+        assert_eq!(picture[6], "#####.#");
+        assert!(picture[7].chars().all(|c| c == '.'));
+    }
+}
@@ -0,0 +1,267 @@
+//! 5x5 pixel font.
+
+/// Glyphs indexed by the byte value, each row is 5 bits wide with the
+/// most significant bit on the left, the first row is the top one.
+///
+/// Trading space for absence of the conditional statements: control
+/// characters and the upper half of the table render as hatch patterns.
+pub const FONT_5X5: [[u8; 5]; 256] = [
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b11111, 0b00000, 0b11111, 0b00000], // non-printable
+    [0b11111, 0b00000, 0b11111, 0b00000, 0b11111], // non-printable
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // non-printable
+    // ASCII 32-127
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // (space)
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00100], // !
+    [0b01010, 0b01010, 0b00000, 0b00000, 0b00000], // "
+    [0b01010, 0b11111, 0b01010, 0b11111, 0b01010], // #
+    [0b00100, 0b01111, 0b10100, 0b01011, 0b00100], // $
+    [0b11001, 0b11010, 0b00100, 0b01011, 0b10011], // %
+    [0b01100, 0b10010, 0b10101, 0b01000, 0b10100], // &
+    [0b00100, 0b00100, 0b00000, 0b00000, 0b00000], // '
+    [0b00010, 0b00100, 0b01000, 0b00100, 0b00010], // (
+    [0b01000, 0b00100, 0b00010, 0b00100, 0b01000], // )
+    [0b00100, 0b10101, 0b01110, 0b10101, 0b00100], // *
+    [0b00000, 0b00100, 0b01110, 0b00100, 0b00000], // +
+    [0b00000, 0b00000, 0b00000, 0b00100, 0b01000], // ,
+    [0b00000, 0b00000, 0b01110, 0b00000, 0b00000], // -
+    [0b00000, 0b00000, 0b00000, 0b00100, 0b00000], // .
+    [0b00001, 0b00010, 0b00100, 0b01000, 0b10000], // /
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // 0
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b01110], // 1
+    [0b01110, 0b10001, 0b00110, 0b01000, 0b11111], // 2
+    [0b11111, 0b00010, 0b01110, 0b00001, 0b11110], // 3
+    [0b10001, 0b10001, 0b11111, 0b00001, 0b00001], // 4
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b11110], // 5
+    [0b01110, 0b10000, 0b11110, 0b10001, 0b01110], // 6
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000], // 7
+    [0b01110, 0b10001, 0b01110, 0b10001, 0b01110], // 8
+    [0b01110, 0b10001, 0b01111, 0b00001, 0b01110], // 9
+    [0b00000, 0b00100, 0b00000, 0b00100, 0b00000], // :
+    [0b00000, 0b00100, 0b00000, 0b00100, 0b01000], // ;
+    [0b00010, 0b00100, 0b01000, 0b00100, 0b00010], // <
+    [0b00000, 0b01110, 0b00000, 0b01110, 0b00000], // =
+    [0b01000, 0b00100, 0b00010, 0b00100, 0b01000], // >
+    [0b01110, 0b10001, 0b00010, 0b00000, 0b00100], // ?
+    [0b01110, 0b10001, 0b10111, 0b10101, 0b01110], // @
+    [0b01110, 0b10001, 0b11111, 0b10001, 0b10001], // A
+    [0b11110, 0b10001, 0b11110, 0b10001, 0b11110], // B
+    [0b01110, 0b10001, 0b10000, 0b10001, 0b01110], // C
+    [0b11100, 0b10010, 0b10001, 0b10010, 0b11100], // D
+    [0b11111, 0b10000, 0b11100, 0b10000, 0b11111], // E
+    [0b11111, 0b10000, 0b11100, 0b10000, 0b10000], // F
+    [0b01110, 0b10001, 0b10000, 0b10100, 0b01110], // G
+    [0b10001, 0b10001, 0b11111, 0b10001, 0b10001], // H
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b01110], // I
+    [0b00111, 0b00010, 0b00010, 0b10010, 0b01100], // J
+    [0b10001, 0b10010, 0b11000, 0b10010, 0b10001], // K
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b11111], // L
+    [0b10001, 0b11011, 0b10101, 0b10001, 0b10001], // M
+    [0b10001, 0b11001, 0b10101, 0b10011, 0b10001], // N
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // O
+    [0b11110, 0b10001, 0b11110, 0b10000, 0b10000], // P
+    [0b01110, 0b10001, 0b10101, 0b10010, 0b01101], // Q
+    [0b11110, 0b10001, 0b11110, 0b10010, 0b10001], // R
+    [0b01110, 0b10000, 0b01110, 0b00001, 0b01110], // S
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100], // T
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // U
+    [0b10001, 0b10001, 0b01010, 0b01010, 0b00100], // V
+    [0b10001, 0b10001, 0b10101, 0b11011, 0b10001], // W
+    [0b10001, 0b01010, 0b00100, 0b01010, 0b10001], // X
+    [0b10001, 0b10001, 0b01110, 0b00100, 0b00100], // Y
+    [0b11111, 0b00010, 0b00100, 0b01000, 0b11111], // Z
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01110], // [
+    [0b10000, 0b01000, 0b00100, 0b00010, 0b00001], // \
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b01110], // ]
+    [0b00100, 0b01010, 0b10001, 0b00000, 0b00000], // ^
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // _
+    [0b00100, 0b00100, 0b00010, 0b00000, 0b00000], // `
+    [0b00000, 0b00110, 0b01000, 0b01110, 0b10001], // a
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b11100], // b
+    [0b00000, 0b01100, 0b10000, 0b10000, 0b01100], // c
+    [0b00010, 0b00010, 0b01110, 0b10010, 0b01110], // d
+    [0b00000, 0b01100, 0b10100, 0b11000, 0b01100], // e
+    [0b00100, 0b01010, 0b01100, 0b01000, 0b01000], // f
+    [0b00000, 0b01110, 0b10010, 0b01110, 0b00010], // g
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b10010], // h
+    [0b00100, 0b00000, 0b00100, 0b00100, 0b00100], // i
+    [0b00010, 0b00000, 0b00010, 0b00010, 0b10010], // j
+    [0b10000, 0b10010, 0b10100, 0b11000, 0b10100], // k
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100], // l
+    [0b00000, 0b11010, 0b10101, 0b10101, 0b10101], // m
+    [0b00000, 0b11100, 0b10010, 0b10010, 0b10010], // n
+    [0b00000, 0b01100, 0b10010, 0b10010, 0b01100], // o
+    [0b00000, 0b11100, 0b10010, 0b11100, 0b10000], // p
+    [0b00000, 0b01110, 0b10010, 0b01110, 0b00010], // q
+    [0b00000, 0b10100, 0b11000, 0b10000, 0b10000], // r
+    [0b00000, 0b01100, 0b00100, 0b00010, 0b01100], // s
+    [0b01000, 0b11100, 0b01000, 0b01000, 0b00100], // t
+    [0b00000, 0b10010, 0b10010, 0b10010, 0b01110], // u
+    [0b00000, 0b10001, 0b10001, 0b01010, 0b00100], // v
+    [0b00000, 0b10001, 0b10101, 0b10101, 0b01010], // w
+    [0b00000, 0b10001, 0b01010, 0b01010, 0b10001], // x
+    [0b00000, 0b10010, 0b10010, 0b01110, 0b00010], // y
+    [0b00000, 0b11110, 0b00100, 0b01000, 0b11110], // z
+    [0b00100, 0b01000, 0b01000, 0b01000, 0b00100], // {
+    [0b00100, 0b00100, 0b00000, 0b00100, 0b00100], // |
+    [0b01000, 0b00100, 0b00100, 0b00100, 0b01000], // }
+    [0b00000, 0b00000, 0b01010, 0b10100, 0b00000], // ~
+    // Extended, not impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b10101, 0b10101, 0b10101, 0b10101, 0b10101], // non impelemented
+    [0b01010, 0b01010, 0b01010, 0b01010, 0b01010], // non impelemented
+    [0b11111, 0b11111, 0b11111, 0b11111, 0b11111], // non impelemented
+];
@@ -0,0 +1,279 @@
+//! MIDI over USB.
+//!
+//! USB MIDI moves the messages in 4-byte event packets: the cable number
+//! and the code index (which says how long the message is) in the first
+//! byte, then up to three bytes of the MIDI message itself. [`Message`]
+//! decodes and encodes the channel voice messages, the rest (system
+//! exclusive, clock, etc.) is skipped.
+//!
+//! On the target [`MidiClass`] is a MIDI streaming interface with one
+//! cable in each direction.
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod class;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use class::MidiClass;
+
+/// An USB MIDI event packet.
+pub type Packet = [u8; 4];
+
+/// Control changes with a meaning of their own.
+pub const CONTROL_MODULATION: u8 = 1;
+pub const CONTROL_VOLUME: u8 = 7;
+pub const CONTROL_ALL_NOTES_OFF: u8 = 123;
+
+/// A channel voice message, the channels are `0..16`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyPressure {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        control: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    /// `value` is `0..0x4000`, `0x2000` is the centre.
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+}
+
+impl Message {
+    /// Decodes an event packet into the cable number and the message.
+    /// A note-on with zero velocity is a note-off, as the MIDI
+    /// specification says.
+    pub fn decode(packet: &Packet) -> Option<(u8, Self)> {
+        let [header, status, data1, data2] = *packet;
+        let cable = header >> 4;
+        let code_index = header & 0x0f;
+        // The code index of a channel message repeats its status nibble
+        if !(0x8..=0xe).contains(&code_index) || status >> 4 != code_index {
+            return None;
+        }
+        if data1 > 0x7f || data2 > 0x7f {
+            return None;
+        }
+
+        let channel = status & 0x0f;
+        let message = match code_index {
+            0x8 => Self::NoteOff {
+                channel,
+                note: data1,
+                velocity: data2,
+            },
+            0x9 if data2 == 0 => Self::NoteOff {
+                channel,
+                note: data1,
+                velocity: 0,
+            },
+            0x9 => Self::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            },
+            0xa => Self::PolyPressure {
+                channel,
+                note: data1,
+                pressure: data2,
+            },
+            0xb => Self::ControlChange {
+                channel,
+                control: data1,
+                value: data2,
+            },
+            0xc => Self::ProgramChange {
+                channel,
+                program: data1,
+            },
+            0xd => Self::ChannelPressure {
+                channel,
+                pressure: data1,
+            },
+            _ => Self::PitchBend {
+                channel,
+                value: u16::from(data1) | u16::from(data2) << 7,
+            },
+        };
+        Some((cable, message))
+    }
+
+    /// Encodes the message for the cable, the values are cut to 7 bits.
+    pub fn encode(&self, cable: u8) -> Packet {
+        let (kind, channel, data1, data2) = match *self {
+            Self::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => (0x8, channel, note, velocity),
+            Self::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => (0x9, channel, note, velocity),
+            Self::PolyPressure {
+                channel,
+                note,
+                pressure,
+            } => (0xa, channel, note, pressure),
+            Self::ControlChange {
+                channel,
+                control,
+                value,
+            } => (0xb, channel, control, value),
+            Self::ProgramChange { channel, program } => (0xc, channel, program, 0),
+            Self::ChannelPressure { channel, pressure } => (0xd, channel, pressure, 0),
+            Self::PitchBend { channel, value } => (0xe, channel, value as u8, (value >> 7) as u8),
+        };
+        [
+            (cable << 4) | kind,
+            (kind << 4) | (channel & 0x0f),
+            data1 & 0x7f,
+            data2 & 0x7f,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_channel_messages() {
+        assert_eq!(
+            Message::decode(&[0x09, 0x93, 60, 100]),
+            Some((
+                0,
+                Message::NoteOn {
+                    channel: 3,
+                    note: 60,
+                    velocity: 100
+                }
+            ))
+        );
+        assert_eq!(
+            Message::decode(&[0x19, 0x90, 60, 0]),
+            Some((
+                1,
+                Message::NoteOff {
+                    channel: 0,
+                    note: 60,
+                    velocity: 0
+                }
+            ))
+        );
+        assert_eq!(
+            Message::decode(&[0x0b, 0xbf, CONTROL_VOLUME, 127]),
+            Some((
+                0,
+                Message::ControlChange {
+                    channel: 15,
+                    control: CONTROL_VOLUME,
+                    value: 127
+                }
+            ))
+        );
+        assert_eq!(
+            Message::decode(&[0x0e, 0xe0, 0x00, 0x40]),
+            Some((
+                0,
+                Message::PitchBend {
+                    channel: 0,
+                    value: 0x2000
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn skips_other_packets() {
+        // System exclusive start, a mismatched code index, a bad data byte
+        assert_eq!(Message::decode(&[0x04, 0xf0, 0x7e, 0x7f]), None);
+        assert_eq!(Message::decode(&[0x08, 0x90, 60, 100]), None);
+        assert_eq!(Message::decode(&[0x09, 0x90, 0x80, 100]), None);
+        // Padding the host sends after the last packet
+        assert_eq!(Message::decode(&[0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn encodes_what_it_decodes() {
+        let messages = [
+            Message::NoteOff {
+                channel: 1,
+                note: 64,
+                velocity: 10,
+            },
+            Message::NoteOn {
+                channel: 2,
+                note: 0,
+                velocity: 127,
+            },
+            Message::PolyPressure {
+                channel: 3,
+                note: 5,
+                pressure: 6,
+            },
+            Message::ControlChange {
+                channel: 4,
+                control: CONTROL_MODULATION,
+                value: 64,
+            },
+            Message::ProgramChange {
+                channel: 5,
+                program: 42,
+            },
+            Message::ChannelPressure {
+                channel: 6,
+                pressure: 99,
+            },
+            Message::PitchBend {
+                channel: 7,
+                value: 0x3fff,
+            },
+        ];
+        for message in messages {
+            let packet = message.encode(2);
+            assert_eq!(Message::decode(&packet), Some((2, message)));
+        }
+
+        assert_eq!(
+            Message::NoteOn {
+                channel: 0,
+                note: 60,
+                velocity: 100
+            }
+            .encode(0),
+            [0x09, 0x90, 60, 100]
+        );
+        assert_eq!(
+            Message::ProgramChange {
+                channel: 0,
+                program: 1
+            }
+            .encode(0),
+            [0x0c, 0xc0, 1, 0]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! The MIDI streaming interface as a USB class.
+
+use usb_device::class_prelude::*;
+
+use super::Packet;
+
+const AUDIO_CLASS: u8 = 0x01;
+const AUDIO_CONTROL_SUB_CLASS: u8 = 0x01;
+const MIDI_STREAMING_SUB_CLASS: u8 = 0x03;
+
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+
+const HEADER_SUBTYPE: u8 = 0x01;
+const MIDI_IN_JACK_SUBTYPE: u8 = 0x02;
+const MIDI_OUT_JACK_SUBTYPE: u8 = 0x03;
+const MS_GENERAL_SUBTYPE: u8 = 0x01;
+
+const JACK_EMBEDDED: u8 = 0x01;
+const JACK_EXTERNAL: u8 = 0x02;
+
+/// The jacks of the only cable: the host writes to the embedded IN jack
+/// and reads from the embedded OUT jack.
+const EMBEDDED_IN_JACK: u8 = 1;
+const EXTERNAL_IN_JACK: u8 = 2;
+const EMBEDDED_OUT_JACK: u8 = 3;
+const EXTERNAL_OUT_JACK: u8 = 4;
+
+/// The class-specific descriptors of the streaming interface: the header,
+/// two IN jacks, two OUT jacks and the two endpoint descriptors.
+const MIDI_STREAMING_TOTAL_LENGTH: u16 = 7 + 2 * 6 + 2 * 9 + 2 * (9 + 5);
+
+const PACKET_SIZE: u16 = 64;
+
+/// An audio control interface with the MIDI streaming interface it
+/// owns, one cable each way.
+pub struct MidiClass<'a, B: UsbBus> {
+    audio_control: InterfaceNumber,
+    midi_streaming: InterfaceNumber,
+    read_ep: EndpointOut<'a, B>,
+    write_ep: EndpointIn<'a, B>,
+}
+
+impl<'a, B: UsbBus> MidiClass<'a, B> {
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self {
+            audio_control: alloc.interface(),
+            midi_streaming: alloc.interface(),
+            read_ep: alloc.bulk(PACKET_SIZE),
+            write_ep: alloc.bulk(PACKET_SIZE),
+        }
+    }
+
+    /// Reads the packets the host sent, up to 16 of them. The host may pad
+    /// the transfer with zeros, which decode to nothing.
+    pub fn read(&mut self, buf: &mut [u8; PACKET_SIZE as usize]) -> usb_device::Result<usize> {
+        self.read_ep.read(buf)
+    }
+
+    /// Sends a packet, fails with `WouldBlock` while the previous one has
+    /// not been picked up.
+    pub fn write(&mut self, packet: &Packet) -> usb_device::Result<()> {
+        self.write_ep.write(packet).map(|_| ())
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for MidiClass<'_, B> {
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        writer.interface(self.audio_control, AUDIO_CLASS, AUDIO_CONTROL_SUB_CLASS, 0)?;
+        // Audio 1.0 header owning the streaming interface
+        writer.write(
+            CS_INTERFACE,
+            &[
+                HEADER_SUBTYPE,
+                0x00,
+                0x01,
+                0x09,
+                0x00,
+                0x01,
+                u8::from(self.midi_streaming),
+            ],
+        )?;
+
+        writer.interface(
+            self.midi_streaming,
+            AUDIO_CLASS,
+            MIDI_STREAMING_SUB_CLASS,
+            0,
+        )?;
+        let [total_lo, total_hi] = MIDI_STREAMING_TOTAL_LENGTH.to_le_bytes();
+        writer.write(
+            CS_INTERFACE,
+            &[HEADER_SUBTYPE, 0x00, 0x01, total_lo, total_hi],
+        )?;
+        for (jack_type, id) in [
+            (JACK_EMBEDDED, EMBEDDED_IN_JACK),
+            (JACK_EXTERNAL, EXTERNAL_IN_JACK),
+        ] {
+            writer.write(CS_INTERFACE, &[MIDI_IN_JACK_SUBTYPE, jack_type, id, 0])?;
+        }
+        // Each OUT jack has one input pin, fed by the opposite IN jack
+        for (jack_type, id, source) in [
+            (JACK_EMBEDDED, EMBEDDED_OUT_JACK, EXTERNAL_IN_JACK),
+            (JACK_EXTERNAL, EXTERNAL_OUT_JACK, EMBEDDED_IN_JACK),
+        ] {
+            writer.write(
+                CS_INTERFACE,
+                &[MIDI_OUT_JACK_SUBTYPE, jack_type, id, 1, source, 1, 0],
+            )?;
+        }
+
+        writer.endpoint_ex(&self.read_ep, audio_endpoint)?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL_SUBTYPE, 1, EMBEDDED_IN_JACK])?;
+        writer.endpoint_ex(&self.write_ep, audio_endpoint)?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL_SUBTYPE, 1, EMBEDDED_OUT_JACK])
+    }
+}
+
+/// The audio endpoint descriptors carry two more bytes, the refresh rate
+/// and the synch endpoint, both unused.
+fn audio_endpoint(extra: &mut [u8]) -> usb_device::Result<usize> {
+    if extra.len() < 2 {
+        return Err(UsbError::BufferOverflow);
+    }
+    extra[..2].fill(0);
+    Ok(2)
+}
@@ -0,0 +1,479 @@
+//! Modbus RTU, the server (slave) side.
+//!
+//! A request and its response travel in the same kind of frame:
+//!
+//! | address u8 | function u8 | data | CRC-16, low byte first |
+//!
+//! The numbers in the data are big-endian. There are no delimiters, a frame
+//! ends with at least 3.5 characters of silence on the line: [`Framer`]
+//! cuts the frames by the arrival times of the bytes, [`respond`] checks
+//! one and builds the response.
+//!
+//! The data comes from a [`Device`]:
+//!
+//! | Function                 | Code | Reads or writes         |
+//! |--------------------------|------|-------------------------|
+//! | Read Coils               | 1    | [`Device::coil`]        |
+//! | Read Discrete Inputs     | 2    | [`Device::discrete_input`] |
+//! | Read Input Registers     | 4    | [`Device::input_register`] |
+//! | Write Single Coil        | 5    | [`Device::set_coil`]    |
+//! | Write Multiple Coils     | 15   | [`Device::set_coil`]    |
+//!
+//! Other functions get the `IllegalFunction` exception.
+
+use crate::crc::crc16;
+use crate::uart;
+
+/// The address all servers take requests on, without responding.
+pub const BROADCAST: u8 = 0;
+/// The longest frame.
+pub const MAX_ADU: usize = 256;
+/// The longest function code and data.
+pub const MAX_PDU: usize = MAX_ADU - 3;
+
+/// The limits of the standard, the responses have to fit a frame.
+const MAX_READ_BITS: u16 = 2000;
+const MAX_READ_REGISTERS: u16 = 125;
+const MAX_WRITE_BITS: u16 = 1968;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Function {
+    ReadCoils = 1,
+    ReadDiscreteInputs = 2,
+    ReadInputRegisters = 4,
+    WriteSingleCoil = 5,
+    WriteMultipleCoils = 15,
+}
+
+impl Function {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => Function::ReadCoils,
+            2 => Function::ReadDiscreteInputs,
+            4 => Function::ReadInputRegisters,
+            5 => Function::WriteSingleCoil,
+            15 => Function::WriteMultipleCoils,
+            _ => return None,
+        })
+    }
+}
+
+/// What the response says instead of the data when a request fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Exception {
+    IllegalFunction = 1,
+    /// Some of the addresses are past the last coil, input or register.
+    IllegalDataAddress = 2,
+    /// A quantity out of the limits, or malformed data.
+    IllegalDataValue = 3,
+    ServerDeviceFailure = 4,
+}
+
+/// The coils, the inputs and the registers of the server, numbered from 0.
+///
+/// [`respond`] checks the addresses against the counts before it reads or
+/// writes anything, so the methods only get the numbers below them.
+pub trait Device {
+    fn coils(&self) -> u16;
+    fn coil(&mut self, index: u16) -> bool;
+    fn set_coil(&mut self, index: u16, on: bool);
+
+    fn discrete_inputs(&self) -> u16;
+    fn discrete_input(&mut self, index: u16) -> bool;
+
+    fn input_registers(&self) -> u16;
+    fn input_register(&mut self, index: u16) -> Result<u16, Exception>;
+}
+
+/// Checks the request frame and carries it out, returns the length of the
+/// response frame in `out`, if there is one.
+///
+/// Frames for other servers, damaged ones and broadcasts get no response,
+/// as the standard says.
+pub fn respond(
+    address: u8,
+    device: &mut impl Device,
+    request: &[u8],
+    out: &mut [u8; MAX_ADU],
+) -> Option<usize> {
+    if request.len() < 4 {
+        return None;
+    }
+    let (frame, crc) = request.split_at(request.len() - 2);
+    if crc != crc16(frame).to_le_bytes() {
+        return None;
+    }
+    if frame[0] != address && frame[0] != BROADCAST {
+        return None;
+    }
+
+    let mut pdu = [0u8; MAX_PDU];
+    let len = handle(device, &frame[1..], &mut pdu);
+    if frame[0] == BROADCAST {
+        return None;
+    }
+    out[0] = address;
+    out[1..1 + len].copy_from_slice(&pdu[..len]);
+    let crc = crc16(&out[..1 + len]);
+    out[1 + len..3 + len].copy_from_slice(&crc.to_le_bytes());
+    Some(3 + len)
+}
+
+/// Carries out the request PDU, the function code and the data, returns
+/// the length of the response PDU in `out`.
+pub fn handle(device: &mut impl Device, request: &[u8], out: &mut [u8; MAX_PDU]) -> usize {
+    let code = request.first().copied().unwrap_or(0);
+    match execute(device, request, out) {
+        Ok(len) => len,
+        Err(exception) => {
+            out[0] = code | 0x80;
+            out[1] = exception as u8;
+            2
+        }
+    }
+}
+
+fn execute(
+    device: &mut impl Device,
+    request: &[u8],
+    out: &mut [u8; MAX_PDU],
+) -> Result<usize, Exception> {
+    let (&code, data) = request.split_first().ok_or(Exception::IllegalFunction)?;
+    let function = Function::from_u8(code).ok_or(Exception::IllegalFunction)?;
+    let word = |at: usize| -> Result<u16, Exception> {
+        data.get(at..at + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+    let start = word(0)?;
+    out[0] = code;
+
+    match function {
+        Function::ReadCoils | Function::ReadDiscreteInputs => {
+            let quantity = word(2)?;
+            if data.len() != 4 || !(1..=MAX_READ_BITS).contains(&quantity) {
+                return Err(Exception::IllegalDataValue);
+            }
+            let count = match function {
+                Function::ReadCoils => device.coils(),
+                _ => device.discrete_inputs(),
+            };
+            check_range(start, quantity, count)?;
+
+            let bytes = usize::from(quantity).div_ceil(8);
+            out[1] = bytes as u8;
+            out[2..2 + bytes].fill(0);
+            for i in 0..quantity {
+                let on = match function {
+                    Function::ReadCoils => device.coil(start + i),
+                    _ => device.discrete_input(start + i),
+                };
+                out[2 + usize::from(i / 8)] |= u8::from(on) << (i % 8);
+            }
+            Ok(2 + bytes)
+        }
+        Function::ReadInputRegisters => {
+            let quantity = word(2)?;
+            if data.len() != 4 || !(1..=MAX_READ_REGISTERS).contains(&quantity) {
+                return Err(Exception::IllegalDataValue);
+            }
+            check_range(start, quantity, device.input_registers())?;
+
+            out[1] = (2 * quantity) as u8;
+            for i in 0..quantity {
+                let at = 2 + 2 * usize::from(i);
+                let value = device.input_register(start + i)?;
+                out[at..at + 2].copy_from_slice(&value.to_be_bytes());
+            }
+            Ok(2 + 2 * usize::from(quantity))
+        }
+        Function::WriteSingleCoil => {
+            let on = match word(2)? {
+                0xff00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue),
+            };
+            if data.len() != 4 {
+                return Err(Exception::IllegalDataValue);
+            }
+            check_range(start, 1, device.coils())?;
+
+            device.set_coil(start, on);
+            // The response repeats the request
+            out[1..5].copy_from_slice(data);
+            Ok(5)
+        }
+        Function::WriteMultipleCoils => {
+            let quantity = word(2)?;
+            let bytes = usize::from(quantity).div_ceil(8);
+            let valid = (1..=MAX_WRITE_BITS).contains(&quantity)
+                && data
+                    .get(4)
+                    .is_some_and(|&count| usize::from(count) == bytes)
+                && data.len() == 5 + bytes;
+            if !valid {
+                return Err(Exception::IllegalDataValue);
+            }
+            check_range(start, quantity, device.coils())?;
+
+            for i in 0..quantity {
+                let on = data[5 + usize::from(i / 8)] & (1 << (i % 8)) != 0;
+                device.set_coil(start + i, on);
+            }
+            out[1..5].copy_from_slice(&data[..4]);
+            Ok(5)
+        }
+    }
+}
+
+fn check_range(start: u16, quantity: u16, count: u16) -> Result<(), Exception> {
+    if u32::from(start) + u32::from(quantity) > u32::from(count) {
+        return Err(Exception::IllegalDataAddress);
+    }
+    Ok(())
+}
+
+/// The silence that ends a frame, 3.5 characters, or 1.75 ms above
+/// 19200 baud as the standard suggests.
+pub fn frame_gap_us(config: &uart::Config) -> u64 {
+    if config.baud > 19_200 {
+        return 1750;
+    }
+    (u64::from(config.character_bits()) * 3_500_000).div_ceil(u64::from(config.baud))
+}
+
+/// Cuts the received bytes into frames at the silences between them.
+pub struct Framer {
+    buf: [u8; MAX_ADU],
+    len: usize,
+    too_long: bool,
+    gap_us: u64,
+    received_at: u64,
+}
+
+impl Framer {
+    /// `gap_us` is the silence that ends a frame, see [`frame_gap_us`].
+    pub const fn new(gap_us: u64) -> Self {
+        Self {
+            buf: [0; MAX_ADU],
+            len: 0,
+            too_long: false,
+            gap_us,
+            received_at: 0,
+        }
+    }
+
+    pub fn set_gap_us(&mut self, gap_us: u64) {
+        self.gap_us = gap_us;
+    }
+
+    /// Takes a byte and the time it arrived in microseconds, e.g. from the
+    /// RP2040 timer. A byte after the gap starts the next frame, so call
+    /// [`Framer::poll`] before.
+    pub fn push(&mut self, byte: u8, now_us: u64) {
+        if self.ended(now_us) {
+            self.len = 0;
+            self.too_long = false;
+        }
+        match self.buf.get_mut(self.len) {
+            Some(slot) => {
+                *slot = byte;
+                self.len += 1;
+            }
+            None => self.too_long = true,
+        }
+        self.received_at = now_us;
+    }
+
+    /// Returns the frame once the line has been silent for the gap. Frames
+    /// longer than [`MAX_ADU`] are dropped.
+    pub fn poll(&mut self, now_us: u64) -> Option<&[u8]> {
+        if !self.ended(now_us) {
+            return None;
+        }
+        let len = core::mem::take(&mut self.len);
+        if core::mem::take(&mut self.too_long) {
+            return None;
+        }
+        Some(&self.buf[..len])
+    }
+
+    fn ended(&self, now_us: u64) -> bool {
+        self.len != 0 && now_us.wrapping_sub(self.received_at) >= self.gap_us
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Coils, inputs and registers in arrays, the inputs read the coils
+    /// inverted.
+    struct Rig {
+        coils: [bool; 12],
+        registers: [u16; 3],
+    }
+
+    impl Device for Rig {
+        fn coils(&self) -> u16 {
+            self.coils.len() as u16
+        }
+
+        fn coil(&mut self, index: u16) -> bool {
+            self.coils[usize::from(index)]
+        }
+
+        fn set_coil(&mut self, index: u16, on: bool) {
+            self.coils[usize::from(index)] = on;
+        }
+
+        fn discrete_inputs(&self) -> u16 {
+            self.coils.len() as u16
+        }
+
+        fn discrete_input(&mut self, index: u16) -> bool {
+            !self.coils[usize::from(index)]
+        }
+
+        fn input_registers(&self) -> u16 {
+            self.registers.len() as u16
+        }
+
+        fn input_register(&mut self, index: u16) -> Result<u16, Exception> {
+            match self.registers[usize::from(index)] {
+                0xdead => Err(Exception::ServerDeviceFailure),
+                value => Ok(value),
+            }
+        }
+    }
+
+    fn rig() -> Rig {
+        let mut coils = [false; 12];
+        coils[0] = true;
+        coils[3] = true;
+        coils[9] = true;
+        Rig {
+            coils,
+            registers: [0x0123, 0xfff6, 0xdead],
+        }
+    }
+
+    fn handled(rig: &mut Rig, request: &[u8]) -> Vec<u8> {
+        let mut out = [0u8; MAX_PDU];
+        let len = handle(rig, request, &mut out);
+        out[..len].to_vec()
+    }
+
+    #[test]
+    fn reads_bits_and_registers() {
+        let mut rig = rig();
+        assert_eq!(
+            handled(&mut rig, &[1, 0, 0, 0, 12]),
+            [1, 2, 0b0000_1001, 0b0000_0010]
+        );
+        assert_eq!(handled(&mut rig, &[1, 0, 3, 0, 1]), [1, 1, 1]);
+        assert_eq!(handled(&mut rig, &[2, 0, 0, 0, 4]), [2, 1, 0b0110]);
+        assert_eq!(
+            handled(&mut rig, &[4, 0, 0, 0, 2]),
+            [4, 4, 0x01, 0x23, 0xff, 0xf6]
+        );
+    }
+
+    #[test]
+    fn writes_coils() {
+        let mut rig = rig();
+        assert_eq!(handled(&mut rig, &[5, 0, 1, 0xff, 0]), [5, 0, 1, 0xff, 0]);
+        assert!(rig.coils[1]);
+        assert_eq!(handled(&mut rig, &[5, 0, 0, 0, 0]), [5, 0, 0, 0, 0]);
+        assert!(!rig.coils[0]);
+
+        assert_eq!(
+            handled(&mut rig, &[15, 0, 2, 0, 10, 2, 0b1111_1111, 0b01]),
+            [15, 0, 2, 0, 10]
+        );
+        assert_eq!(
+            rig.coils,
+            [false, true, true, true, true, true, true, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn answers_with_exceptions() {
+        let mut rig = rig();
+        // Holding registers
+        assert_eq!(handled(&mut rig, &[3, 0, 0, 0, 1]), [0x83, 1]);
+        assert_eq!(handled(&mut rig, &[]), [0x80, 1]);
+        assert_eq!(handled(&mut rig, &[1, 0, 8, 0, 5]), [0x81, 2]);
+        assert_eq!(handled(&mut rig, &[2, 0xff, 0xff, 0, 2]), [0x82, 2]);
+        assert_eq!(handled(&mut rig, &[4, 0, 0, 0, 0]), [0x84, 3]);
+        assert_eq!(handled(&mut rig, &[4, 0, 0, 0, 126]), [0x84, 3]);
+        assert_eq!(handled(&mut rig, &[4, 0, 2, 0, 1]), [0x84, 4]);
+        assert_eq!(handled(&mut rig, &[4, 0, 0, 0]), [0x84, 3]);
+        assert_eq!(handled(&mut rig, &[5, 0, 0, 0x12, 0x34]), [0x85, 3]);
+        assert_eq!(handled(&mut rig, &[5, 0, 12, 0xff, 0]), [0x85, 2]);
+        // The byte count does not match the quantity
+        assert_eq!(handled(&mut rig, &[15, 0, 0, 0, 9, 1, 0xff]), [0x8f, 3]);
+        assert_eq!(handled(&mut rig, &[15, 0, 8, 0, 8, 1, 0xff]), [0x8f, 2]);
+        // Nothing written by the failed requests
+        assert_eq!(rig.coils, self::rig().coils);
+    }
+
+    #[test]
+    fn responds_to_its_address() {
+        let mut rig = rig();
+        let mut out = [0u8; MAX_ADU];
+        let mut request = vec![0x11, 0x04, 0x00, 0x01, 0x00, 0x01];
+        request.extend(crc16(&request).to_le_bytes());
+        assert_eq!(respond(0x11, &mut rig, &request, &mut out), Some(7));
+        let crc = crc16(&out[..5]).to_le_bytes();
+        assert_eq!(out[..7], [0x11, 0x04, 0x02, 0xff, 0xf6, crc[0], crc[1]]);
+
+        assert_eq!(respond(0x12, &mut rig, &request, &mut out), None);
+        let mut damaged = request.clone();
+        damaged[3] ^= 1;
+        assert_eq!(respond(0x11, &mut rig, &damaged, &mut out), None);
+        assert_eq!(respond(0x11, &mut rig, &request[..3], &mut out), None);
+
+        let mut broadcast = vec![BROADCAST, 5, 0, 11, 0xff, 0];
+        broadcast.extend(crc16(&broadcast).to_le_bytes());
+        assert_eq!(respond(0x11, &mut rig, &broadcast, &mut out), None);
+        assert!(rig.coils[11]);
+    }
+
+    #[test]
+    fn times_the_frame_gap() {
+        let mut config = uart::Config::new(9600);
+        config.parity = uart::Parity::Even;
+        // 11 bits a character
+        assert_eq!(frame_gap_us(&config), 4011);
+        config.baud = 19_200;
+        assert_eq!(frame_gap_us(&config), 2006);
+        config.baud = 115_200;
+        assert_eq!(frame_gap_us(&config), 1750);
+    }
+
+    #[test]
+    fn cuts_frames_at_the_gaps() {
+        let mut framer = Framer::new(1750);
+        for (i, byte) in [1, 2, 3].into_iter().enumerate() {
+            framer.push(byte, 100 * i as u64);
+        }
+        assert_eq!(framer.poll(1949), None);
+        assert_eq!(framer.poll(1950), Some(&[1, 2, 3][..]));
+        assert_eq!(framer.poll(5000), None);
+
+        // Not polled in time, the next byte starts another frame
+        framer.push(4, 10_000);
+        framer.push(5, 20_000);
+        assert_eq!(framer.poll(21_750), Some(&[5][..]));
+
+        for i in 0..=MAX_ADU as u64 {
+            framer.push(0, 30_000 + i);
+        }
+        assert_eq!(framer.poll(40_000), None);
+        framer.push(6, 50_000);
+        assert_eq!(framer.poll(60_000), Some(&[6][..]));
+    }
+}
@@ -0,0 +1,549 @@
+//! A USB drive: mass storage with the bulk-only transport.
+//!
+//! The host wraps SCSI commands in 31-byte command blocks sent to the bulk
+//! OUT endpoint, moves the data, if any, and collects a 13-byte status on
+//! the bulk IN endpoint. [`BulkOnly`] runs that exchange over a
+//! [`BlockDevice`], one packet at a time, without knowing about USB; on the
+//! target [`MscClass`] feeds it from the endpoints. [`fat`] puts a FAT12
+//! volume on the blocks, so the drive can be used without formatting it.
+//!
+//! ```ignore
+//! let disk = FlashDisk::new(unsafe { RomFlash::new(flash::disk_region()) });
+//! let mut drive = MscClass::new(&usb_bus, BulkOnly::new(disk, "Pico", "Drive", "1.0"));
+//! loop {
+//!     usb_dev.poll(&mut [&mut drive]);
+//! }
+//! ```
+//!
+//! Failed commands end their data phase early instead of stalling the
+//! endpoints, the status tells the host how much was left out.
+
+mod disk;
+pub mod fat;
+pub mod scsi;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod class;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use class::MscClass;
+pub use disk::BlockDevice;
+pub use disk::Error;
+pub use disk::FlashDisk;
+pub use disk::BLOCK_SIZE;
+
+use scsi::Command;
+use scsi::Sense;
+
+/// Interface class, subclass and protocol: mass storage, SCSI transparent
+/// command set, bulk-only transport.
+pub const INTERFACE_CLASS: u8 = 0x08;
+pub const INTERFACE_SUB_CLASS: u8 = 0x06;
+pub const INTERFACE_PROTOCOL: u8 = 0x50;
+
+/// Bulk endpoint packets at full speed.
+pub const PACKET_SIZE: usize = 64;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CBW_LEN: usize = 31;
+
+/// The command block wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandBlock {
+    pub tag: u32,
+    /// Bytes the host expects to move in the data phase.
+    pub data_length: u32,
+    /// The data goes to the host.
+    pub data_in: bool,
+    pub lun: u8,
+    command: [u8; 16],
+    command_len: u8,
+}
+
+impl CommandBlock {
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if packet.len() != CBW_LEN {
+            return None;
+        }
+        let le32 = |i: usize| u32::from_le_bytes(packet[i..i + 4].try_into().unwrap());
+        let command_len = packet[14] & 0x1f;
+        if le32(0) != CBW_SIGNATURE || !(1..=16).contains(&command_len) {
+            return None;
+        }
+
+        Some(Self {
+            tag: le32(4),
+            data_length: le32(8),
+            data_in: packet[12] & 0x80 != 0,
+            lun: packet[13] & 0x0f,
+            command: packet[15..].try_into().unwrap(),
+            command_len,
+        })
+    }
+
+    /// The SCSI command descriptor block.
+    pub fn command(&self) -> &[u8] {
+        &self.command[..usize::from(self.command_len)]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Waiting for a command block.
+    Command,
+    /// Sending the response in `buf`, then the blocks left to read.
+    DataIn,
+    /// Receiving the blocks to write, or data nobody wants.
+    DataOut,
+    /// Sending the status.
+    Status,
+}
+
+/// The device side of the bulk-only transport with a single LUN.
+pub struct BulkOnly<D: BlockDevice> {
+    disk: D,
+    inquiry: [u8; 36],
+    state: State,
+    tag: u32,
+    expected: u32,
+    transferred: u32,
+    passed: bool,
+    sense: Sense,
+    ejected: bool,
+    /// Response or block being moved, `len` bytes of it are valid
+    buf: [u8; BLOCK_SIZE],
+    len: usize,
+    pos: usize,
+    /// Blocks left to read or write, starting at `lba`
+    lba: u32,
+    blocks: u32,
+    csw: [u8; 13],
+}
+
+impl<D: BlockDevice> BulkOnly<D> {
+    /// `vendor`, `product` and `revision` are what the host shows, up to
+    /// 8, 16 and 4 characters.
+    pub fn new(disk: D, vendor: &str, product: &str, revision: &str) -> Self {
+        Self {
+            disk,
+            inquiry: scsi::inquiry_data(vendor, product, revision),
+            state: State::Command,
+            tag: 0,
+            expected: 0,
+            transferred: 0,
+            passed: true,
+            sense: Sense::NO_SENSE,
+            ejected: false,
+            buf: [0; BLOCK_SIZE],
+            len: 0,
+            pos: 0,
+            lba: 0,
+            blocks: 0,
+            csw: [0; 13],
+        }
+    }
+
+    pub fn disk(&mut self) -> &mut D {
+        &mut self.disk
+    }
+
+    /// Did the host eject the medium?
+    pub fn is_ejected(&self) -> bool {
+        self.ejected
+    }
+
+    /// Makes the medium present again after an eject.
+    pub fn load(&mut self) {
+        self.ejected = false;
+    }
+
+    /// Drops the command in progress, after a bus or a mass storage reset.
+    pub fn reset(&mut self) {
+        self.state = State::Command;
+        self.len = 0;
+        self.pos = 0;
+        self.blocks = 0;
+    }
+
+    /// Can a packet from the bulk OUT endpoint be taken now?
+    pub fn wants_out(&self) -> bool {
+        matches!(self.state, State::Command | State::DataOut)
+    }
+
+    /// Takes a packet from the bulk OUT endpoint.
+    pub fn packet_out(&mut self, packet: &[u8]) {
+        match self.state {
+            State::Command => {
+                // Not a command block, wait for one
+                if let Some(cbw) = CommandBlock::parse(packet) {
+                    self.execute(&cbw);
+                }
+            }
+            State::DataOut => {
+                let room = (self.expected - self.transferred) as usize;
+                let data = &packet[..packet.len().min(room)];
+                self.transferred += data.len() as u32;
+                self.receive(data);
+
+                if self.transferred == self.expected || packet.len() < PACKET_SIZE {
+                    // Whatever was written reaches the flash before the status
+                    if self.disk.flush().is_err() {
+                        self.fail(Sense::WRITE_ERROR);
+                    }
+                    self.state = State::Status;
+                }
+            }
+            State::DataIn | State::Status => {}
+        }
+    }
+
+    /// The next packet for the bulk IN endpoint, it stays the same until
+    /// [`sent_in`](BulkOnly::sent_in) is called. An empty packet ends a
+    /// data phase short of what the host expected.
+    pub fn packet_in(&mut self) -> Option<&[u8]> {
+        if self.state == State::DataIn {
+            if self.pos == self.len && self.blocks > 0 && self.transferred < self.expected {
+                self.read_next_block();
+            }
+            if self.transferred == self.expected {
+                self.state = State::Status;
+            } else {
+                let len = self.in_len();
+                return Some(&self.buf[self.pos..self.pos + len]);
+            }
+        }
+
+        if self.state != State::Status {
+            return None;
+        }
+        let residue = self.expected - self.transferred;
+        self.csw[..4].copy_from_slice(&CSW_SIGNATURE.to_le_bytes());
+        self.csw[4..8].copy_from_slice(&self.tag.to_le_bytes());
+        self.csw[8..12].copy_from_slice(&residue.to_le_bytes());
+        self.csw[12] = if self.passed { 0 } else { 1 };
+        Some(&self.csw)
+    }
+
+    /// The packet from [`packet_in`](BulkOnly::packet_in) went out.
+    pub fn sent_in(&mut self) {
+        match self.state {
+            State::DataIn => {
+                let len = self.in_len();
+                self.pos += len;
+                self.transferred += len as u32;
+                // A short packet ends the data phase
+                if len < PACKET_SIZE || self.transferred == self.expected {
+                    self.state = State::Status;
+                }
+            }
+            State::Status => self.state = State::Command,
+            State::Command | State::DataOut => {}
+        }
+    }
+
+    fn in_len(&self) -> usize {
+        PACKET_SIZE
+            .min(self.len - self.pos)
+            .min((self.expected - self.transferred) as usize)
+    }
+
+    fn fail(&mut self, sense: Sense) {
+        self.passed = false;
+        self.sense = sense;
+        self.blocks = 0;
+    }
+
+    fn execute(&mut self, cbw: &CommandBlock) {
+        self.tag = cbw.tag;
+        self.expected = cbw.data_length;
+        self.transferred = 0;
+        self.passed = true;
+        self.len = 0;
+        self.pos = 0;
+        self.blocks = 0;
+
+        match Command::parse(cbw.command()) {
+            Some(command) => self.execute_command(command, cbw.data_in),
+            None => self.fail(Sense::INVALID_FIELD_IN_CDB),
+        }
+
+        self.state = if self.expected == 0 {
+            State::Status
+        } else if cbw.data_in {
+            State::DataIn
+        } else {
+            State::DataOut
+        };
+    }
+
+    fn execute_command(&mut self, command: Command, data_in: bool) {
+        if !matches!(command, Command::RequestSense { .. }) {
+            self.sense = Sense::NO_SENSE;
+        }
+        let needs_medium = matches!(
+            command,
+            Command::TestUnitReady
+                | Command::ReadCapacity10
+                | Command::Read10 { .. }
+                | Command::Write10 { .. }
+                | Command::Verify10
+        );
+        if needs_medium && self.ejected {
+            self.fail(Sense::MEDIUM_NOT_PRESENT);
+            return;
+        }
+
+        let block_count = self.disk.block_count();
+        match command {
+            Command::TestUnitReady | Command::PreventAllowMediumRemoval | Command::Verify10 => {}
+            Command::RequestSense { allocation_length } => {
+                let data = self.sense.data();
+                self.sense = Sense::NO_SENSE;
+                self.respond(&data, allocation_length, data_in);
+            }
+            Command::Inquiry {
+                vital_product_data: true,
+                ..
+            } => self.fail(Sense::INVALID_FIELD_IN_CDB),
+            Command::Inquiry {
+                allocation_length, ..
+            } => {
+                let data = self.inquiry;
+                self.respond(&data, allocation_length, data_in);
+            }
+            Command::ModeSense6 { allocation_length } => {
+                self.respond(&scsi::mode_sense6_data(false), allocation_length, data_in)
+            }
+            Command::ModeSense10 { allocation_length } => {
+                self.respond(&scsi::mode_sense10_data(false), allocation_length, data_in)
+            }
+            Command::StartStopUnit {
+                load_eject: true,
+                start,
+            } => {
+                if self.disk.flush().is_err() {
+                    self.fail(Sense::WRITE_ERROR);
+                }
+                self.ejected = !start;
+            }
+            Command::StartStopUnit { .. } => {}
+            Command::ReadFormatCapacities { allocation_length } => self.respond(
+                &scsi::format_capacities_data(block_count),
+                allocation_length,
+                data_in,
+            ),
+            Command::ReadCapacity10 => self.respond(&scsi::capacity_data(block_count), 8, data_in),
+            Command::Read10 { lba, blocks } | Command::Write10 { lba, blocks } => {
+                let is_read = matches!(command, Command::Read10 { .. });
+                if u64::from(lba) + u64::from(blocks) > u64::from(block_count) {
+                    self.fail(Sense::LBA_OUT_OF_RANGE);
+                } else if blocks > 0 && data_in != is_read {
+                    self.fail(Sense::INVALID_FIELD_IN_CDB);
+                } else {
+                    self.lba = lba;
+                    self.blocks = blocks.into();
+                }
+            }
+            Command::SynchronizeCache10 => {
+                if self.disk.flush().is_err() {
+                    self.fail(Sense::WRITE_ERROR);
+                }
+            }
+            Command::Unsupported { .. } => self.fail(Sense::INVALID_COMMAND),
+        }
+    }
+
+    /// Queues a response of the data-in commands.
+    fn respond(&mut self, data: &[u8], allocation_length: u16, data_in: bool) {
+        if data_in {
+            self.len = data.len().min(allocation_length.into());
+            self.buf[..self.len].copy_from_slice(&data[..self.len]);
+        }
+    }
+
+    fn read_next_block(&mut self) {
+        match self.disk.read_block(self.lba, &mut self.buf) {
+            Ok(()) => {
+                self.len = BLOCK_SIZE;
+                self.pos = 0;
+                self.lba += 1;
+                self.blocks -= 1;
+            }
+            Err(_) => self.fail(Sense::UNRECOVERED_READ_ERROR),
+        }
+    }
+
+    /// Collects the data of a write, whatever is past the blocks of the
+    /// command is dropped.
+    fn receive(&mut self, mut data: &[u8]) {
+        while !data.is_empty() && self.blocks > 0 {
+            let count = data.len().min(BLOCK_SIZE - self.len);
+            self.buf[self.len..self.len + count].copy_from_slice(&data[..count]);
+            self.len += count;
+            data = &data[count..];
+
+            if self.len == BLOCK_SIZE {
+                self.len = 0;
+                match self.disk.write_block(self.lba, &self.buf) {
+                    Ok(()) => {
+                        self.lba += 1;
+                        self.blocks -= 1;
+                    }
+                    Err(_) => self.fail(Sense::WRITE_ERROR),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+
+    type Drive = BulkOnly<FlashDisk<SimFlash>>;
+
+    fn drive() -> Drive {
+        BulkOnly::new(FlashDisk::new(SimFlash::new(4)), "pico", "bites", "0.1")
+    }
+
+    fn command(drive: &mut Drive, tag: u32, cb: &[u8], data_length: u32, data_in: bool) {
+        let mut cbw = [0u8; CBW_LEN];
+        cbw[..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        cbw[4..8].copy_from_slice(&tag.to_le_bytes());
+        cbw[8..12].copy_from_slice(&data_length.to_le_bytes());
+        cbw[12] = if data_in { 0x80 } else { 0 };
+        cbw[14] = cb.len() as u8;
+        cbw[15..15 + cb.len()].copy_from_slice(cb);
+
+        assert!(drive.wants_out());
+        drive.packet_out(&cbw);
+    }
+
+    /// The IN packets up to and including the status.
+    fn packets_in(drive: &mut Drive) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = drive.packet_in() {
+            packets.push(packet.to_vec());
+            drive.sent_in();
+        }
+        packets
+    }
+
+    fn status(packet: &[u8], tag: u32) -> (u32, u8) {
+        assert_eq!(packet.len(), 13);
+        assert_eq!(&packet[..4], b"USBS");
+        assert_eq!(packet[4..8], tag.to_le_bytes());
+        (
+            u32::from_le_bytes(packet[8..12].try_into().unwrap()),
+            packet[12],
+        )
+    }
+
+    #[test]
+    fn answers_inquiry() {
+        let mut drive = drive();
+        command(&mut drive, 7, &[0x12, 0, 0, 0, 36, 0], 36, true);
+
+        let packets = packets_in(&mut drive);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(&packets[0][16..21], b"bites");
+        assert_eq!(status(&packets[1], 7), (0, 0));
+        assert!(drive.wants_out());
+    }
+
+    #[test]
+    fn writes_and_reads_blocks() {
+        let mut drive = drive();
+        let data: Vec<u8> = (0..1024).map(|i| (i % 251) as u8).collect();
+
+        command(
+            &mut drive,
+            1,
+            &[0x2a, 0, 0, 0, 0, 3, 0, 0, 2, 0],
+            1024,
+            false,
+        );
+        for packet in data.chunks(PACKET_SIZE) {
+            assert!(drive.wants_out());
+            drive.packet_out(packet);
+        }
+        assert!(!drive.wants_out());
+        assert_eq!(status(&packets_in(&mut drive)[0], 1), (0, 0));
+
+        command(
+            &mut drive,
+            2,
+            &[0x28, 0, 0, 0, 0, 3, 0, 0, 2, 0],
+            1024,
+            true,
+        );
+        let packets = packets_in(&mut drive);
+        assert_eq!(packets.len(), 17);
+        assert_eq!(packets[..16].concat(), data);
+        assert_eq!(status(&packets[16], 2), (0, 0));
+
+        // Written through to the flash
+        let mut block = [0; BLOCK_SIZE];
+        drive.disk().read_block(4, &mut block).unwrap();
+        assert_eq!(block[..], data[512..]);
+    }
+
+    #[test]
+    fn fails_reads_past_the_end() {
+        let mut drive = drive();
+        command(
+            &mut drive,
+            3,
+            &[0x28, 0, 0, 0, 0, 127, 0, 0, 2, 0],
+            1024,
+            true,
+        );
+
+        // An empty packet ends the data phase, nothing was sent
+        let packets = packets_in(&mut drive);
+        assert_eq!(packets.len(), 2);
+        assert!(packets[0].is_empty());
+        assert_eq!(status(&packets[1], 3), (1024, 1));
+
+        command(&mut drive, 4, &[0x03, 0, 0, 0, 18, 0], 18, true);
+        let packets = packets_in(&mut drive);
+        assert_eq!(packets[0][2], 0x05);
+        assert_eq!(packets[0][12], 0x21);
+        assert_eq!(status(&packets[1], 4), (0, 0));
+    }
+
+    #[test]
+    fn reports_capacity_and_ejects() {
+        let mut drive = drive();
+        command(&mut drive, 5, &[0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0], 8, true);
+        let packets = packets_in(&mut drive);
+        assert_eq!(packets[0], [0, 0, 0, 31, 0, 0, 2, 0]);
+
+        command(&mut drive, 6, &[0x1b, 0, 0, 0, 0x02, 0], 0, false);
+        assert_eq!(status(&packets_in(&mut drive)[0], 6), (0, 0));
+        assert!(drive.is_ejected());
+
+        command(&mut drive, 7, &[0x00, 0, 0, 0, 0, 0], 0, false);
+        assert_eq!(status(&packets_in(&mut drive)[0], 7), (0, 1));
+        command(&mut drive, 8, &[0x03, 0, 0, 0, 18, 0], 18, true);
+        assert_eq!(packets_in(&mut drive)[0][12], 0x3a);
+    }
+
+    #[test]
+    fn ignores_garbage_commands() {
+        let mut drive = drive();
+        drive.packet_out(b"not a command block");
+        assert!(drive.packet_in().is_none());
+
+        command(
+            &mut drive,
+            9,
+            &[0xa0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            0,
+            false,
+        );
+        assert_eq!(status(&packets_in(&mut drive)[0], 9), (0, 1));
+    }
+}
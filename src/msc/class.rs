@@ -0,0 +1,115 @@
+//! The bulk-only transport as a USB class.
+
+use usb_device::class_prelude::*;
+use usb_device::control::Recipient;
+use usb_device::control::RequestType;
+
+use super::BlockDevice;
+use super::BulkOnly;
+use super::INTERFACE_CLASS;
+use super::INTERFACE_PROTOCOL;
+use super::INTERFACE_SUB_CLASS;
+use super::PACKET_SIZE;
+
+const REQUEST_GET_MAX_LUN: u8 = 0xfe;
+const REQUEST_MASS_STORAGE_RESET: u8 = 0xff;
+
+/// A mass storage interface with a pair of bulk endpoints.
+pub struct MscClass<'a, B: UsbBus, D: BlockDevice> {
+    interface: InterfaceNumber,
+    read_ep: EndpointOut<'a, B>,
+    write_ep: EndpointIn<'a, B>,
+    drive: BulkOnly<D>,
+}
+
+impl<'a, B: UsbBus, D: BlockDevice> MscClass<'a, B, D> {
+    pub fn new(alloc: &'a UsbBusAllocator<B>, drive: BulkOnly<D>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            read_ep: alloc.bulk(PACKET_SIZE as u16),
+            write_ep: alloc.bulk(PACKET_SIZE as u16),
+            drive,
+        }
+    }
+
+    pub fn drive(&mut self) -> &mut BulkOnly<D> {
+        &mut self.drive
+    }
+}
+
+impl<B: UsbBus, D: BlockDevice> UsbClass<B> for MscClass<'_, B, D> {
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        writer.interface(
+            self.interface,
+            INTERFACE_CLASS,
+            INTERFACE_SUB_CLASS,
+            INTERFACE_PROTOCOL,
+        )?;
+        writer.endpoint(&self.read_ep)?;
+        writer.endpoint(&self.write_ep)
+    }
+
+    fn reset(&mut self) {
+        // A replugged drive has its medium back
+        self.drive.reset();
+        self.drive.load();
+    }
+
+    fn poll(&mut self) {
+        // Move packets until both endpoints have to wait for the host
+        loop {
+            let mut progress = false;
+
+            if self.drive.wants_out() {
+                let mut packet = [0u8; PACKET_SIZE];
+                if let Ok(count) = self.read_ep.read(&mut packet) {
+                    self.drive.packet_out(&packet[..count]);
+                    progress = true;
+                }
+            }
+
+            if let Some(packet) = self.drive.packet_in() {
+                if self.write_ep.write(packet).is_ok() {
+                    self.drive.sent_in();
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                return;
+            }
+        }
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        let req = xfer.request();
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        if req.request == REQUEST_GET_MAX_LUN {
+            xfer.accept_with(&[0]).ok();
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let req = xfer.request();
+        if req.request_type != RequestType::Class
+            || req.recipient != Recipient::Interface
+            || req.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        if req.request == REQUEST_MASS_STORAGE_RESET {
+            self.drive.reset();
+            xfer.accept().ok();
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Blocks of the drive and their flash backing.
+
+use crate::flash;
+use crate::flash::Flash;
+
+/// Size of a logical block, as seen by the host.
+pub const BLOCK_SIZE: usize = 512;
+
+const BLOCKS_PER_SECTOR: usize = flash::SECTOR_SIZE / BLOCK_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The block is past the end of the device.
+    OutOfRange,
+    /// The storage failed.
+    Device,
+}
+
+/// Storage addressed in blocks of [`BLOCK_SIZE`] bytes.
+pub trait BlockDevice {
+    fn block_count(&self) -> u32;
+
+    fn read_block(&mut self, lba: u32, block: &mut [u8; BLOCK_SIZE]) -> Result<(), Error>;
+
+    /// Writes the block, possibly only to a cache until [`flush`].
+    ///
+    /// [`flush`]: BlockDevice::flush
+    fn write_block(&mut self, lba: u32, block: &[u8; BLOCK_SIZE]) -> Result<(), Error>;
+
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+impl From<flash::Error> for Error {
+    fn from(_: flash::Error) -> Self {
+        Error::Device
+    }
+}
+
+/// Blocks stored in a flash region.
+///
+/// A flash sector holds 8 blocks and has to be erased as a whole, so
+/// writes go to a copy of the sector in RAM that is written back when
+/// another sector is written or on [`flush`](BlockDevice::flush). Writes
+/// that change nothing do not cause an erase.
+pub struct FlashDisk<F: Flash> {
+    flash: F,
+    sector: [u8; flash::SECTOR_SIZE],
+    cached: Option<usize>,
+    dirty: bool,
+}
+
+impl<F: Flash> FlashDisk<F> {
+    pub fn new(flash: F) -> Self {
+        Self {
+            flash,
+            sector: [0; flash::SECTOR_SIZE],
+            cached: None,
+            dirty: false,
+        }
+    }
+
+    /// The flash, the cached sector is not written back.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    fn locate(&self, lba: u32) -> Result<(usize, usize), Error> {
+        if lba >= self.block_count() {
+            return Err(Error::OutOfRange);
+        }
+        let lba = lba as usize;
+        Ok((
+            lba / BLOCKS_PER_SECTOR * flash::SECTOR_SIZE,
+            lba % BLOCKS_PER_SECTOR * BLOCK_SIZE,
+        ))
+    }
+}
+
+impl<F: Flash> BlockDevice for FlashDisk<F> {
+    fn block_count(&self) -> u32 {
+        (self.flash.capacity() / BLOCK_SIZE) as u32
+    }
+
+    fn read_block(&mut self, lba: u32, block: &mut [u8; BLOCK_SIZE]) -> Result<(), Error> {
+        let (sector, offset) = self.locate(lba)?;
+        if self.cached == Some(sector) {
+            block.copy_from_slice(&self.sector[offset..offset + BLOCK_SIZE]);
+        } else {
+            self.flash.read(sector + offset, block)?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, lba: u32, block: &[u8; BLOCK_SIZE]) -> Result<(), Error> {
+        let (sector, offset) = self.locate(lba)?;
+        if self.cached != Some(sector) {
+            self.flush()?;
+            self.flash.read(sector, &mut self.sector)?;
+            self.cached = Some(sector);
+        }
+
+        let cached = &mut self.sector[offset..offset + BLOCK_SIZE];
+        if cached != block {
+            cached.copy_from_slice(block);
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let Some(sector) = self.cached else {
+            return Ok(());
+        };
+        if self.dirty {
+            self.flash.erase_sector(sector)?;
+            self.flash.program(sector, &self.sector)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+
+    #[test]
+    fn caches_a_sector() {
+        let mut disk = FlashDisk::new(SimFlash::new(2));
+        assert_eq!(disk.block_count(), 16);
+
+        for lba in 0..8 {
+            disk.write_block(lba, &[lba as u8; BLOCK_SIZE]).unwrap();
+        }
+        let mut block = [0; BLOCK_SIZE];
+        disk.read_block(3, &mut block).unwrap();
+        assert_eq!(block, [3; BLOCK_SIZE]);
+
+        // Moving on to the next sector writes the first one back, once
+        disk.write_block(8, &[8; BLOCK_SIZE]).unwrap();
+        disk.flush().unwrap();
+
+        let flash = disk.into_inner();
+        assert_eq!(flash.erases, [1, 1]);
+        assert_eq!(flash.data[3 * BLOCK_SIZE], 3);
+        assert_eq!(flash.data[8 * BLOCK_SIZE], 8);
+        assert_eq!(flash.data[9 * BLOCK_SIZE], 0xff);
+    }
+
+    #[test]
+    fn skips_unchanged_writes() {
+        let mut disk = FlashDisk::new(SimFlash::new(1));
+        disk.write_block(0, &[0xff; BLOCK_SIZE]).unwrap();
+        disk.flush().unwrap();
+        assert_eq!(disk.into_inner().total_erases(), 0);
+    }
+
+    #[test]
+    fn rejects_blocks_past_the_end() {
+        let mut disk = FlashDisk::new(SimFlash::new(1));
+        let mut block = [0; BLOCK_SIZE];
+        assert_eq!(disk.read_block(8, &mut block), Err(Error::OutOfRange));
+        assert_eq!(disk.write_block(8, &block), Err(Error::OutOfRange));
+    }
+}
@@ -0,0 +1,506 @@
+//! A FAT12 volume on the drive.
+//!
+//! [`format`] writes an empty volume with a few files on it, [`read_file`]
+//! finds a file in the root directory, e.g. a configuration the user has
+//! dropped there. Only short (8.3) names in the root directory are looked
+//! at, the long names of the host are ignored.
+
+use super::BlockDevice;
+use super::BLOCK_SIZE;
+
+/// FAT12 tops out at 4084 clusters.
+const MAX_CLUSTERS: u32 = 4084;
+
+const RESERVED_BLOCKS: u32 = 1;
+const FAT_COUNT: u32 = 2;
+const ROOT_ENTRIES: u32 = 64;
+const ENTRY_SIZE: usize = 32;
+const MEDIA_FIXED: u8 = 0xf8;
+
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+
+const END_OF_CHAIN: u16 = 0xfff;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Disk(super::Error),
+    /// The blocks do not hold a FAT12 volume this module can read.
+    NotFat12,
+    /// The device is too small or too large for FAT12.
+    UnsupportedSize,
+    /// Not an 8.3 name.
+    InvalidName,
+    /// The files do not fit in the volume or in the root directory.
+    Full,
+}
+
+impl From<super::Error> for Error {
+    fn from(e: super::Error) -> Self {
+        Error::Disk(e)
+    }
+}
+
+/// A file to put on a new volume.
+pub struct File<'a> {
+    pub name: &'a str,
+    pub contents: &'a [u8],
+}
+
+/// The layout of a volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    pub total_blocks: u32,
+    pub blocks_per_cluster: u32,
+    pub blocks_per_fat: u32,
+    pub reserved_blocks: u32,
+    pub fat_count: u32,
+    pub root_entries: u32,
+}
+
+impl Geometry {
+    /// The layout [`format`] uses for a device of `total_blocks`.
+    pub fn for_blocks(total_blocks: u32) -> Result<Self, Error> {
+        let mut blocks_per_cluster = 1;
+        while blocks_per_cluster <= 64 {
+            // Sized for every block being a cluster, which is a bit more
+            let entries = total_blocks / blocks_per_cluster + 2;
+            let geometry = Self {
+                total_blocks,
+                blocks_per_cluster,
+                blocks_per_fat: (entries * 3 / 2).div_ceil(BLOCK_SIZE as u32),
+                reserved_blocks: RESERVED_BLOCKS,
+                fat_count: FAT_COUNT,
+                root_entries: ROOT_ENTRIES,
+            };
+            if total_blocks <= geometry.data_start() {
+                return Err(Error::UnsupportedSize);
+            }
+            if geometry.clusters() <= MAX_CLUSTERS {
+                return Ok(geometry);
+            }
+            blocks_per_cluster *= 2;
+        }
+        Err(Error::UnsupportedSize)
+    }
+
+    /// Reads the layout from the boot block of the volume.
+    pub fn read<D: BlockDevice>(disk: &mut D) -> Result<Self, Error> {
+        let mut block = [0; BLOCK_SIZE];
+        disk.read_block(0, &mut block)?;
+
+        let le16 = |i: usize| u32::from(u16::from_le_bytes([block[i], block[i + 1]]));
+        let le32 = |i: usize| u32::from_le_bytes(block[i..i + 4].try_into().unwrap());
+        if block[510..] != [0x55, 0xaa] || le16(11) != BLOCK_SIZE as u32 {
+            return Err(Error::NotFat12);
+        }
+        let total_blocks = match le16(19) {
+            0 => le32(32),
+            total => total,
+        };
+        let geometry = Self {
+            total_blocks,
+            blocks_per_cluster: block[13].into(),
+            blocks_per_fat: le16(22),
+            reserved_blocks: le16(14),
+            fat_count: block[16].into(),
+            root_entries: le16(17),
+        };
+
+        // The cluster count alone tells FAT12 from FAT16
+        if !geometry.blocks_per_cluster.is_power_of_two()
+            || geometry.blocks_per_fat == 0
+            || geometry.fat_count == 0
+            || geometry.total_blocks > disk.block_count()
+            || geometry.total_blocks <= geometry.data_start()
+            || geometry.clusters() > MAX_CLUSTERS
+        {
+            return Err(Error::NotFat12);
+        }
+        Ok(geometry)
+    }
+
+    pub fn root_start(&self) -> u32 {
+        self.reserved_blocks + self.fat_count * self.blocks_per_fat
+    }
+
+    pub fn data_start(&self) -> u32 {
+        self.root_start() + (self.root_entries * ENTRY_SIZE as u32).div_ceil(BLOCK_SIZE as u32)
+    }
+
+    pub fn clusters(&self) -> u32 {
+        (self.total_blocks - self.data_start()) / self.blocks_per_cluster
+    }
+
+    fn cluster_size(&self) -> usize {
+        self.blocks_per_cluster as usize * BLOCK_SIZE
+    }
+
+    fn cluster_start(&self, cluster: u16) -> u32 {
+        self.data_start() + (u32::from(cluster) - 2) * self.blocks_per_cluster
+    }
+
+    fn boot_block(&self, label: &[u8; 11]) -> [u8; BLOCK_SIZE] {
+        let mut block = [0; BLOCK_SIZE];
+        block[..3].copy_from_slice(&[0xeb, 0x3c, 0x90]);
+        block[3..11].copy_from_slice(b"MSWIN4.1");
+        block[11..13].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        block[13] = self.blocks_per_cluster as u8;
+        block[14..16].copy_from_slice(&(self.reserved_blocks as u16).to_le_bytes());
+        block[16] = self.fat_count as u8;
+        block[17..19].copy_from_slice(&(self.root_entries as u16).to_le_bytes());
+        match u16::try_from(self.total_blocks) {
+            Ok(total) => block[19..21].copy_from_slice(&total.to_le_bytes()),
+            Err(_) => block[32..36].copy_from_slice(&self.total_blocks.to_le_bytes()),
+        }
+        block[21] = MEDIA_FIXED;
+        block[22..24].copy_from_slice(&(self.blocks_per_fat as u16).to_le_bytes());
+        // One sector per track and one head, nobody looks
+        block[24] = 1;
+        block[26] = 1;
+        block[36] = 0x80;
+        block[38] = 0x29;
+        block[39..43].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        block[43..54].copy_from_slice(label);
+        block[54..62].copy_from_slice(b"FAT12   ");
+        // Not bootable, the boot code spins
+        block[62..64].copy_from_slice(&[0xeb, 0xfe]);
+        block[510..].copy_from_slice(&[0x55, 0xaa]);
+        block
+    }
+}
+
+/// Turns `README.TXT` into the padded `README  TXT` of the directory.
+pub fn short_name(name: &str) -> Result<[u8; 11], Error> {
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return Err(Error::InvalidName);
+    }
+
+    let mut short = [b' '; 11];
+    let (base_slots, extension_slots) = short.split_at_mut(8);
+    for (slot, c) in base_slots
+        .iter_mut()
+        .zip(base.bytes())
+        .chain(extension_slots.iter_mut().zip(extension.bytes()))
+    {
+        if !(c.is_ascii_alphanumeric() || b"!#$%&'()-@^_`{}~".contains(&c)) {
+            return Err(Error::InvalidName);
+        }
+        *slot = c.to_ascii_uppercase();
+    }
+    Ok(short)
+}
+
+/// Writes an empty volume named `label` holding `files`, with the
+/// layout of [`Geometry::for_blocks`].
+pub fn format<D: BlockDevice>(disk: &mut D, label: &str, files: &[File]) -> Result<(), Error> {
+    let geometry = Geometry::for_blocks(disk.block_count())?;
+    if files.len() + 1 > geometry.root_entries as usize {
+        return Err(Error::Full);
+    }
+    let mut volume_label = [b' '; 11];
+    for (slot, c) in volume_label.iter_mut().zip(label.bytes()) {
+        *slot = c.to_ascii_uppercase();
+    }
+
+    disk.write_block(0, &geometry.boot_block(&volume_label))?;
+    let zero = [0; BLOCK_SIZE];
+    for lba in RESERVED_BLOCKS..geometry.data_start() {
+        disk.write_block(lba, &zero)?;
+    }
+    set_fat_entry(disk, &geometry, 0, 0xf00 | u16::from(MEDIA_FIXED))?;
+    set_fat_entry(disk, &geometry, 1, END_OF_CHAIN)?;
+
+    let mut root = [0; BLOCK_SIZE];
+    root[..11].copy_from_slice(&volume_label);
+    root[11] = ATTR_VOLUME_ID;
+
+    let mut next_cluster = 2u16;
+    let mut block = [0; BLOCK_SIZE];
+    for (index, file) in files.iter().enumerate() {
+        let clusters = file.contents.len().div_ceil(geometry.cluster_size());
+        if u32::from(next_cluster) - 2 + clusters as u32 > geometry.clusters() {
+            return Err(Error::Full);
+        }
+        let start = if clusters > 0 { next_cluster } else { 0 };
+
+        for (i, chunk) in file.contents.chunks(BLOCK_SIZE).enumerate() {
+            block.fill(0);
+            block[..chunk.len()].copy_from_slice(chunk);
+            disk.write_block(geometry.cluster_start(next_cluster) + i as u32, &block)?;
+        }
+        for i in 0..clusters as u16 {
+            let next = if i + 1 == clusters as u16 {
+                END_OF_CHAIN
+            } else {
+                next_cluster + i + 1
+            };
+            set_fat_entry(disk, &geometry, next_cluster + i, next)?;
+        }
+        next_cluster += clusters as u16;
+
+        // The volume label takes the first entry
+        let entry = (index + 1) * ENTRY_SIZE;
+        let lba = geometry.root_start() + (entry / BLOCK_SIZE) as u32;
+        if entry.is_multiple_of(BLOCK_SIZE) && entry > 0 {
+            root.fill(0);
+        }
+        let entry = &mut root[entry % BLOCK_SIZE..][..ENTRY_SIZE];
+        entry[..11].copy_from_slice(&short_name(file.name)?);
+        entry[11] = ATTR_ARCHIVE;
+        entry[26..28].copy_from_slice(&start.to_le_bytes());
+        entry[28..32].copy_from_slice(&(file.contents.len() as u32).to_le_bytes());
+        disk.write_block(lba, &root)?;
+    }
+    if files.is_empty() {
+        disk.write_block(geometry.root_start(), &root)?;
+    }
+
+    disk.flush()?;
+    Ok(())
+}
+
+/// Copies the start of the file `name` from the root directory into
+/// `buf`, returns the size of the file or `None` if there is no such file.
+pub fn read_file<D: BlockDevice>(
+    disk: &mut D,
+    name: &str,
+    buf: &mut [u8],
+) -> Result<Option<usize>, Error> {
+    let geometry = Geometry::read(disk)?;
+    let name = short_name(name)?;
+
+    let mut block = [0; BLOCK_SIZE];
+    let root_blocks = geometry.data_start() - geometry.root_start();
+    for lba in geometry.root_start()..geometry.root_start() + root_blocks {
+        disk.read_block(lba, &mut block)?;
+        for entry in block.chunks(ENTRY_SIZE) {
+            match entry[0] {
+                // The end of the directory
+                0x00 => return Ok(None),
+                // A deleted file
+                0xe5 => continue,
+                _ => {}
+            }
+            if entry[11] & (ATTR_VOLUME_ID | ATTR_DIRECTORY) != 0 || entry[..11] != name {
+                continue;
+            }
+
+            let cluster = u16::from_le_bytes([entry[26], entry[27]]);
+            let size = u32::from_le_bytes(entry[28..32].try_into().unwrap()) as usize;
+            let len = size.min(buf.len());
+            read_chain(disk, &geometry, cluster, &mut buf[..len])?;
+            return Ok(Some(size));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the clusters of a chain into `buf` until it is full.
+fn read_chain<D: BlockDevice>(
+    disk: &mut D,
+    geometry: &Geometry,
+    mut cluster: u16,
+    mut buf: &mut [u8],
+) -> Result<(), Error> {
+    let mut block = [0; BLOCK_SIZE];
+    // A broken chain could loop, no file has more clusters than the volume
+    for _ in 0..geometry.clusters() {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if cluster < 2 || u32::from(cluster) - 2 >= geometry.clusters() {
+            return Err(Error::NotFat12);
+        }
+
+        for i in 0..geometry.blocks_per_cluster {
+            if buf.is_empty() {
+                break;
+            }
+            disk.read_block(geometry.cluster_start(cluster) + i, &mut block)?;
+            let count = buf.len().min(BLOCK_SIZE);
+            buf[..count].copy_from_slice(&block[..count]);
+            buf = &mut buf[count..];
+        }
+        cluster = fat_entry(disk, geometry, cluster)?;
+    }
+    if buf.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::NotFat12)
+    }
+}
+
+/// Where the 12 bits of the entry start: the block and the byte in it.
+fn fat_entry_location(geometry: &Geometry, cluster: u16, byte: usize) -> (u32, usize) {
+    let offset = usize::from(cluster) * 3 / 2 + byte;
+    (
+        geometry.reserved_blocks + (offset / BLOCK_SIZE) as u32,
+        offset % BLOCK_SIZE,
+    )
+}
+
+fn fat_entry<D: BlockDevice>(
+    disk: &mut D,
+    geometry: &Geometry,
+    cluster: u16,
+) -> Result<u16, Error> {
+    let mut block = [0; BLOCK_SIZE];
+    let mut bytes = [0; 2];
+    // The two bytes can be in different blocks
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let (lba, offset) = fat_entry_location(geometry, cluster, i);
+        disk.read_block(lba, &mut block)?;
+        *byte = block[offset];
+    }
+
+    let pair = u16::from_le_bytes(bytes);
+    Ok(if cluster.is_multiple_of(2) {
+        pair & 0xfff
+    } else {
+        pair >> 4
+    })
+}
+
+/// Sets the entry in every copy of the FAT.
+fn set_fat_entry<D: BlockDevice>(
+    disk: &mut D,
+    geometry: &Geometry,
+    cluster: u16,
+    value: u16,
+) -> Result<(), Error> {
+    let (mask, value) = if cluster.is_multiple_of(2) {
+        (0xf000u16, value & 0xfff)
+    } else {
+        (0x000fu16, value << 4)
+    };
+
+    let mut block = [0; BLOCK_SIZE];
+    for copy in 0..geometry.fat_count {
+        for (i, (mask, value)) in mask
+            .to_le_bytes()
+            .into_iter()
+            .zip(value.to_le_bytes())
+            .enumerate()
+        {
+            let (lba, offset) = fat_entry_location(geometry, cluster, i);
+            let lba = lba + copy * geometry.blocks_per_fat;
+            disk.read_block(lba, &mut block)?;
+            block[offset] = (block[offset] & mask) | value;
+            disk.write_block(lba, &block)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+    use crate::msc::FlashDisk;
+
+    /// 240 KiB, as much as the firmware gives the drive.
+    fn disk() -> FlashDisk<SimFlash> {
+        FlashDisk::new(SimFlash::new(60))
+    }
+
+    #[test]
+    fn lays_out_small_volumes() {
+        let geometry = Geometry::for_blocks(512).unwrap();
+        assert_eq!(geometry.blocks_per_cluster, 1);
+        assert_eq!(geometry.blocks_per_fat, 2);
+        assert_eq!(geometry.root_start(), 5);
+        assert_eq!(geometry.data_start(), 9);
+        assert_eq!(geometry.clusters(), 503);
+
+        // 16 MiB needs 8 KiB clusters to stay FAT12
+        let geometry = Geometry::for_blocks(32768).unwrap();
+        assert_eq!(geometry.blocks_per_cluster, 16);
+        assert!(geometry.clusters() <= MAX_CLUSTERS);
+
+        assert_eq!(Geometry::for_blocks(7), Err(Error::UnsupportedSize));
+    }
+
+    #[test]
+    fn makes_short_names() {
+        assert_eq!(short_name("readme.txt"), Ok(*b"README  TXT"));
+        assert_eq!(short_name("CONFIG"), Ok(*b"CONFIG     "));
+        assert_eq!(short_name("toolongname.txt"), Err(Error::InvalidName));
+        assert_eq!(short_name("a.text"), Err(Error::InvalidName));
+        assert_eq!(short_name("a b.txt"), Err(Error::InvalidName));
+        assert_eq!(short_name(".txt"), Err(Error::InvalidName));
+    }
+
+    #[test]
+    fn formats_and_reads_back() {
+        let mut disk = disk();
+        assert_eq!(Geometry::read(&mut disk), Err(Error::NotFat12));
+
+        let long: Vec<u8> = (0..1300).map(|i| (i % 256) as u8).collect();
+        let files = [
+            File {
+                name: "README.TXT",
+                contents: b"Drop config.txt here\r\n",
+            },
+            File {
+                name: "empty",
+                contents: b"",
+            },
+            File {
+                name: "data.bin",
+                contents: &long,
+            },
+        ];
+        format(&mut disk, "pico", &files).unwrap();
+        assert_eq!(Geometry::read(&mut disk), Geometry::for_blocks(480));
+
+        let mut buf = [0; 2048];
+        assert_eq!(read_file(&mut disk, "readme.txt", &mut buf), Ok(Some(22)));
+        assert_eq!(&buf[..22], b"Drop config.txt here\r\n");
+        assert_eq!(read_file(&mut disk, "EMPTY", &mut buf), Ok(Some(0)));
+        assert_eq!(read_file(&mut disk, "data.bin", &mut buf), Ok(Some(1300)));
+        assert_eq!(buf[..1300], long[..]);
+        assert_eq!(read_file(&mut disk, "config.txt", &mut buf), Ok(None));
+
+        // A short buffer gets the start of the file
+        let mut buf = [0; 4];
+        assert_eq!(read_file(&mut disk, "data.bin", &mut buf), Ok(Some(1300)));
+        assert_eq!(buf, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn chains_clusters_in_the_fat() {
+        let mut disk = disk();
+        let contents = [0x55; 3 * BLOCK_SIZE];
+        let files = [File {
+            name: "A",
+            contents: &contents,
+        }];
+        format(&mut disk, "pico", &files).unwrap();
+
+        let geometry = Geometry::read(&mut disk).unwrap();
+        assert_eq!(fat_entry(&mut disk, &geometry, 0), Ok(0xff8));
+        assert_eq!(fat_entry(&mut disk, &geometry, 1), Ok(0xfff));
+        assert_eq!(fat_entry(&mut disk, &geometry, 2), Ok(3));
+        assert_eq!(fat_entry(&mut disk, &geometry, 3), Ok(4));
+        assert_eq!(fat_entry(&mut disk, &geometry, 4), Ok(0xfff));
+        assert_eq!(fat_entry(&mut disk, &geometry, 5), Ok(0));
+
+        // The second FAT is the same
+        let mut first = [0; BLOCK_SIZE];
+        let mut second = [0; BLOCK_SIZE];
+        disk.read_block(1, &mut first).unwrap();
+        disk.read_block(3, &mut second).unwrap();
+        assert_eq!(first, second);
+
+        // An entry that straddles two blocks of the FAT
+        set_fat_entry(&mut disk, &geometry, 341, 0xabc).unwrap();
+        set_fat_entry(&mut disk, &geometry, 340, 0x123).unwrap();
+        set_fat_entry(&mut disk, &geometry, 342, 0x456).unwrap();
+        assert_eq!(fat_entry(&mut disk, &geometry, 340), Ok(0x123));
+        assert_eq!(fat_entry(&mut disk, &geometry, 341), Ok(0xabc));
+        assert_eq!(fat_entry(&mut disk, &geometry, 342), Ok(0x456));
+    }
+}
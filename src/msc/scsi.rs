@@ -0,0 +1,227 @@
+//! The subset of SCSI block commands hosts use with a USB drive.
+
+use super::BLOCK_SIZE;
+
+/// A command descriptor block, decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    TestUnitReady,
+    RequestSense {
+        allocation_length: u16,
+    },
+    Inquiry {
+        vital_product_data: bool,
+        allocation_length: u16,
+    },
+    ModeSense6 {
+        allocation_length: u16,
+    },
+    ModeSense10 {
+        allocation_length: u16,
+    },
+    StartStopUnit {
+        load_eject: bool,
+        start: bool,
+    },
+    PreventAllowMediumRemoval,
+    ReadFormatCapacities {
+        allocation_length: u16,
+    },
+    ReadCapacity10,
+    Read10 {
+        lba: u32,
+        blocks: u16,
+    },
+    Write10 {
+        lba: u32,
+        blocks: u16,
+    },
+    Verify10,
+    SynchronizeCache10,
+    Unsupported {
+        opcode: u8,
+    },
+}
+
+impl Command {
+    /// Decodes the block, `None` if it is shorter than its command needs.
+    pub fn parse(cb: &[u8]) -> Option<Self> {
+        let opcode = *cb.first()?;
+        let len = match opcode {
+            0x00..=0x1f => 6,
+            0x20..=0x5f => 10,
+            _ => 1,
+        };
+        if cb.len() < len {
+            return None;
+        }
+
+        let be16 = |i: usize| u16::from_be_bytes([cb[i], cb[i + 1]]);
+        let be32 = |i: usize| u32::from_be_bytes([cb[i], cb[i + 1], cb[i + 2], cb[i + 3]]);
+
+        Some(match opcode {
+            0x00 => Self::TestUnitReady,
+            0x03 => Self::RequestSense {
+                allocation_length: cb[4].into(),
+            },
+            0x12 => Self::Inquiry {
+                vital_product_data: cb[1] & 0x01 != 0,
+                allocation_length: be16(3),
+            },
+            0x1a => Self::ModeSense6 {
+                allocation_length: cb[4].into(),
+            },
+            0x1b => Self::StartStopUnit {
+                load_eject: cb[4] & 0x02 != 0,
+                start: cb[4] & 0x01 != 0,
+            },
+            0x1e => Self::PreventAllowMediumRemoval,
+            0x23 => Self::ReadFormatCapacities {
+                allocation_length: be16(7),
+            },
+            0x25 => Self::ReadCapacity10,
+            0x28 => Self::Read10 {
+                lba: be32(2),
+                blocks: be16(7),
+            },
+            0x2a => Self::Write10 {
+                lba: be32(2),
+                blocks: be16(7),
+            },
+            0x2f => Self::Verify10,
+            0x35 => Self::SynchronizeCache10,
+            0x5a => Self::ModeSense10 {
+                allocation_length: be16(7),
+            },
+            opcode => Self::Unsupported { opcode },
+        })
+    }
+}
+
+/// Sense key and additional sense code of the last failed command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sense {
+    pub key: u8,
+    pub code: u8,
+    pub qualifier: u8,
+}
+
+impl Sense {
+    pub const NO_SENSE: Self = Self::new(0x00, 0x00, 0x00);
+    pub const MEDIUM_NOT_PRESENT: Self = Self::new(0x02, 0x3a, 0x00);
+    pub const UNRECOVERED_READ_ERROR: Self = Self::new(0x03, 0x11, 0x00);
+    pub const WRITE_ERROR: Self = Self::new(0x03, 0x0c, 0x00);
+    pub const INVALID_COMMAND: Self = Self::new(0x05, 0x20, 0x00);
+    pub const LBA_OUT_OF_RANGE: Self = Self::new(0x05, 0x21, 0x00);
+    pub const INVALID_FIELD_IN_CDB: Self = Self::new(0x05, 0x24, 0x00);
+
+    const fn new(key: u8, code: u8, qualifier: u8) -> Self {
+        Self {
+            key,
+            code,
+            qualifier,
+        }
+    }
+
+    /// Fixed format sense data.
+    pub fn data(&self) -> [u8; 18] {
+        let mut data = [0; 18];
+        data[0] = 0x70;
+        data[2] = self.key;
+        data[7] = 10;
+        data[12] = self.code;
+        data[13] = self.qualifier;
+        data
+    }
+}
+
+/// Standard inquiry data of a removable direct-access device.
+pub fn inquiry_data(vendor: &str, product: &str, revision: &str) -> [u8; 36] {
+    let mut data = [b' '; 36];
+    data[..8].copy_from_slice(&[0x00, 0x80, 0x04, 0x02, 31, 0, 0, 0]);
+    for (field, text) in [(8..16, vendor), (16..32, product), (32..36, revision)] {
+        for (slot, &c) in data[field].iter_mut().zip(text.as_bytes()) {
+            *slot = c;
+        }
+    }
+    data
+}
+
+/// READ CAPACITY (10) data: the last block and the block size.
+pub fn capacity_data(block_count: u32) -> [u8; 8] {
+    let mut data = [0; 8];
+    data[..4].copy_from_slice(&block_count.saturating_sub(1).to_be_bytes());
+    data[4..].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+    data
+}
+
+/// READ FORMAT CAPACITIES data: one descriptor of a formatted medium.
+pub fn format_capacities_data(block_count: u32) -> [u8; 12] {
+    let mut data = [0; 12];
+    data[3] = 8;
+    data[4..8].copy_from_slice(&block_count.to_be_bytes());
+    data[8] = 0x02;
+    data[9..].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes()[1..]);
+    data
+}
+
+/// MODE SENSE (6) header without mode pages or block descriptors.
+pub fn mode_sense6_data(write_protected: bool) -> [u8; 4] {
+    [3, 0, if write_protected { 0x80 } else { 0 }, 0]
+}
+
+/// MODE SENSE (10) header without mode pages or block descriptors.
+pub fn mode_sense10_data(write_protected: bool) -> [u8; 8] {
+    [0, 6, 0, if write_protected { 0x80 } else { 0 }, 0, 0, 0, 0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            Command::parse(&[0x28, 0, 0, 0, 0x01, 0x02, 0, 0, 0x08, 0]),
+            Some(Command::Read10 {
+                lba: 0x102,
+                blocks: 8
+            })
+        );
+        assert_eq!(
+            Command::parse(&[0x12, 0, 0, 0, 36, 0]),
+            Some(Command::Inquiry {
+                vital_product_data: false,
+                allocation_length: 36
+            })
+        );
+        assert_eq!(
+            Command::parse(&[0x1b, 0, 0, 0, 0x02, 0]),
+            Some(Command::StartStopUnit {
+                load_eject: true,
+                start: false
+            })
+        );
+        assert_eq!(
+            Command::parse(&[0xa0, 0, 0]),
+            Some(Command::Unsupported { opcode: 0xa0 })
+        );
+        assert_eq!(Command::parse(&[0x2a, 0, 0]), None);
+        assert_eq!(Command::parse(&[]), None);
+    }
+
+    #[test]
+    fn formats_the_data() {
+        let inquiry = inquiry_data("pico", "bites disk", "0.1");
+        assert_eq!(&inquiry[8..16], b"pico    ");
+        assert_eq!(&inquiry[16..32], b"bites disk      ");
+        assert_eq!(&inquiry[32..], b"0.1 ");
+
+        assert_eq!(capacity_data(512), [0, 0, 0x01, 0xff, 0, 0, 0x02, 0]);
+        assert_eq!(
+            format_capacities_data(512),
+            [0, 0, 0, 8, 0, 0, 0x02, 0, 0x02, 0, 0x02, 0]
+        );
+        assert_eq!(Sense::LBA_OUT_OF_RANGE.data()[12], 0x21);
+    }
+}
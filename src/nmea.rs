@@ -0,0 +1,879 @@
+//! NMEA 0183 sentences from GPS receivers.
+//!
+//! [`parse`] checks a sentence and takes the fields of GGA, RMC, GSV and
+//! VTG out of it, from any talker (`GP`, `GN`, `GL`...). [`Fix`] gathers
+//! them into the position and the time. Nothing is allocated, the lines
+//! come from a [`LineBuffer`](crate::line::LineBuffer) of [`MAX_SENTENCE`]
+//! bytes.
+//!
+//! The numbers are integers in the units their names tell: the angles in
+//! 1e-7 degrees, as the receivers keep them, the altitude in millimetres,
+//! the speed in millimetres per second. The fields a receiver leaves empty,
+//! e.g. before the first fix, are `None`.
+//!
+//! ```ignore
+//! let mut fix = nmea::Fix::default();
+//! if let Some(line) = port.read_line(&mut line) {
+//!     match nmea::parse(line) {
+//!         Ok(sentence) => fix.update(&sentence),
+//!         Err(nmea::Error::Unsupported) => {}
+//!         Err(e) => log::warn!("{}", e),
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+
+/// The longest sentence the standard allows, `$` to the checksum.
+pub const MAX_SENTENCE: usize = 82;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// No `$` at the start or no `*` and two hex digits at the end.
+    Framing,
+    /// The checksum does not match, the sentence was damaged.
+    Checksum,
+    /// A sentence of another kind, or a proprietary one.
+    Unsupported,
+    /// A field is missing or out of range.
+    Field,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::Framing => "not a sentence",
+            Error::Checksum => "checksum mismatch",
+            Error::Unsupported => "unsupported sentence",
+            Error::Field => "malformed field",
+        })
+    }
+}
+
+/// UTC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    /// Up to 60 for a leap second.
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+impl fmt::Display for Time {
+    /// As in `12:35:19`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    /// From 1 for January.
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Whether the date is in the calendar, from the year 1 on; the
+    /// default date is not.
+    pub fn is_valid(&self) -> bool {
+        self.year > 0
+            && (1..=12).contains(&self.month)
+            && (1..=Self::days_in_month(self.year, self.month)).contains(&self.day)
+    }
+
+    /// From 0 for Sunday, `None` for an invalid date.
+    pub fn day_of_week(&self) -> Option<u8> {
+        const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        if !self.is_valid() {
+            return None;
+        }
+        let year = u32::from(self.year) - u32::from(self.month < 3);
+        let days = year + year / 4 - year / 100
+            + year / 400
+            + OFFSETS[usize::from(self.month - 1)]
+            + u32::from(self.day);
+        Some((days % 7) as u8)
+    }
+
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    /// As in `1994-03-23`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The second after `date` and `time`, the one the next PPS pulse starts.
+pub fn next_second(date: Date, time: Time) -> (Date, Time) {
+    let mut time = Time {
+        second: time.second + 1,
+        millisecond: 0,
+        ..time
+    };
+    let mut date = date;
+    if time.second >= 60 {
+        time.second = 0;
+        time.minute += 1;
+    }
+    if time.minute == 60 {
+        time.minute = 0;
+        time.hour += 1;
+    }
+    if time.hour == 24 {
+        time.hour = 0;
+        date.day += 1;
+    }
+    if date.day > Date::days_in_month(date.year, date.month) {
+        date.day = 1;
+        date.month += 1;
+    }
+    if date.month == 13 {
+        date.month = 1;
+        date.year += 1;
+    }
+    (date, time)
+}
+
+/// North and east are positive, in 1e-7 degrees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub latitude: i32,
+    pub longitude: i32,
+}
+
+impl fmt::Display for Position {
+    /// As in `48.117300 N 11.516667 E`, to about 10 cm.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let angle = |f: &mut fmt::Formatter<'_>, angle: i32, hemispheres: [char; 2]| {
+            let hemisphere = hemispheres[usize::from(angle < 0)];
+            let micro = (angle.unsigned_abs() + 5) / 10;
+            write!(
+                f,
+                "{}.{:06} {hemisphere}",
+                micro / 1_000_000,
+                micro % 1_000_000
+            )
+        };
+        angle(f, self.latitude, ['N', 'S'])?;
+        f.write_str(" ")?;
+        angle(f, self.longitude, ['E', 'W'])
+    }
+}
+
+/// The kind of fix in GGA.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Quality {
+    #[default]
+    NoFix = 0,
+    Gps = 1,
+    Differential = 2,
+    Pps = 3,
+    RealTimeKinematic = 4,
+    FloatRealTimeKinematic = 5,
+    /// Dead reckoning.
+    Estimated = 6,
+    Manual = 7,
+    Simulation = 8,
+}
+
+impl Quality {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Quality::NoFix,
+            1 => Quality::Gps,
+            2 => Quality::Differential,
+            3 => Quality::Pps,
+            4 => Quality::RealTimeKinematic,
+            5 => Quality::FloatRealTimeKinematic,
+            6 => Quality::Estimated,
+            7 => Quality::Manual,
+            8 => Quality::Simulation,
+            _ => return None,
+        })
+    }
+}
+
+/// Global positioning system fix data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gga {
+    pub time: Option<Time>,
+    pub position: Option<Position>,
+    pub quality: Quality,
+    /// Satellites used for the fix.
+    pub satellites: u8,
+    /// Horizontal dilution of precision, in hundredths.
+    pub hdop_centi: Option<u16>,
+    /// Above the mean sea level.
+    pub altitude_mm: Option<i32>,
+}
+
+/// Recommended minimum data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rmc {
+    pub time: Option<Time>,
+    /// Whether the receiver has a fix, the position is only there then.
+    pub valid: bool,
+    pub position: Option<Position>,
+    pub speed_mm_s: Option<u32>,
+    /// From the true north, in thousandths of a degree.
+    pub course_mdeg: Option<u32>,
+    pub date: Option<Date>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Satellite {
+    pub prn: u8,
+    /// In degrees.
+    pub elevation: Option<u8>,
+    pub azimuth: Option<u16>,
+    /// Signal to noise ratio in dB-Hz, `None` while not tracked.
+    pub snr: Option<u8>,
+}
+
+/// Satellites in view, up to four of them a sentence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gsv {
+    /// The sentences in the series, and which one this is from 1.
+    pub sentences: u8,
+    pub sentence: u8,
+    pub in_view: u8,
+    pub satellites: [Option<Satellite>; 4],
+}
+
+/// Course and speed over the ground.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vtg {
+    pub course_mdeg: Option<u32>,
+    pub speed_mm_s: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sentence {
+    Gga(Gga),
+    Rmc(Rmc),
+    Gsv(Gsv),
+    Vtg(Vtg),
+}
+
+/// Checks a sentence, without the line terminator, and parses it.
+pub fn parse(line: &[u8]) -> Result<Sentence, Error> {
+    let body = line.strip_prefix(b"$").ok_or(Error::Framing)?;
+    let star = body
+        .iter()
+        .rposition(|&byte| byte == b'*')
+        .ok_or(Error::Framing)?;
+    let (body, checksum) = (&body[..star], &body[star + 1..]);
+    let checksum = match checksum {
+        &[high, low] => hex_digit(high)? << 4 | hex_digit(low)?,
+        _ => return Err(Error::Framing),
+    };
+    if body.iter().fold(0, |sum, &byte| sum ^ byte) != checksum {
+        return Err(Error::Checksum);
+    }
+
+    let mut fields = Fields { rest: Some(body) };
+    let address = fields.next()?;
+    if address.len() != 5 || address[0] == b'P' {
+        return Err(Error::Unsupported);
+    }
+    match &address[2..] {
+        b"GGA" => gga(&mut fields).map(Sentence::Gga),
+        b"RMC" => rmc(&mut fields).map(Sentence::Rmc),
+        b"GSV" => gsv(&mut fields).map(Sentence::Gsv),
+        b"VTG" => vtg(&mut fields).map(Sentence::Vtg),
+        _ => Err(Error::Unsupported),
+    }
+}
+
+fn gga(fields: &mut Fields) -> Result<Gga, Error> {
+    let time = time(fields.next()?)?;
+    let position = position(fields)?;
+    let quality = number(fields.next()?)?
+        .map_or(Some(Quality::NoFix), Quality::from_u8)
+        .ok_or(Error::Field)?;
+    let satellites = number(fields.next()?)?.unwrap_or(0);
+    let hdop_centi = decimal(fields.next()?, 2)?
+        .map(|hdop| u16::try_from(hdop).map_err(|_| Error::Field))
+        .transpose()?;
+    let altitude_mm = decimal(fields.next()?, 3)?
+        .map(|altitude| i32::try_from(altitude).map_err(|_| Error::Field))
+        .transpose()?;
+    Ok(Gga {
+        time,
+        position,
+        quality,
+        satellites,
+        hdop_centi,
+        altitude_mm,
+    })
+}
+
+fn rmc(fields: &mut Fields) -> Result<Rmc, Error> {
+    let time = time(fields.next()?)?;
+    let valid = match fields.next()? {
+        b"A" => true,
+        b"V" => false,
+        _ => return Err(Error::Field),
+    };
+    let position = position(fields)?;
+    let speed_mm_s = knots(fields.next()?)?;
+    let course_mdeg = course(fields.next()?)?;
+    let date = date(fields.next()?)?;
+    Ok(Rmc {
+        time,
+        valid,
+        position: position.filter(|_| valid),
+        speed_mm_s,
+        course_mdeg,
+        date,
+    })
+}
+
+fn gsv(fields: &mut Fields) -> Result<Gsv, Error> {
+    let sentences = number(fields.next()?)?.ok_or(Error::Field)?;
+    let sentence = number(fields.next()?)?.ok_or(Error::Field)?;
+    let in_view = number(fields.next()?)?.unwrap_or(0);
+    let mut satellites = [None; 4];
+    for satellite in &mut satellites {
+        // Fewer in the last sentence, maybe followed by the signal ID
+        let Ok(prn) = fields.next() else {
+            break;
+        };
+        let (Ok(elevation), Ok(azimuth), Ok(snr)) = (fields.next(), fields.next(), fields.next())
+        else {
+            break;
+        };
+        *satellite = Some(Satellite {
+            prn: number(prn)?.ok_or(Error::Field)?,
+            elevation: number(elevation)?,
+            azimuth: number(azimuth)?,
+            snr: number(snr)?,
+        });
+    }
+    Ok(Gsv {
+        sentences,
+        sentence,
+        in_view,
+        satellites,
+    })
+}
+
+fn vtg(fields: &mut Fields) -> Result<Vtg, Error> {
+    let course_mdeg = course(fields.next()?)?;
+    // The magnetic course, then the speed in knots and in km/h, each with
+    // a unit field
+    let _ = (fields.next()?, fields.next()?, fields.next()?);
+    let knots_mm_s = knots(fields.next()?)?;
+    let _ = fields.next()?;
+    let km_h_mm_s = speed(fields.next()?, 1000, 3600)?;
+    Ok(Vtg {
+        course_mdeg,
+        speed_mm_s: km_h_mm_s.or(knots_mm_s),
+    })
+}
+
+/// The comma-separated fields of a sentence.
+struct Fields<'a> {
+    rest: Option<&'a [u8]>,
+}
+
+impl<'a> Fields<'a> {
+    fn next(&mut self) -> Result<&'a [u8], Error> {
+        let rest = self.rest.ok_or(Error::Field)?;
+        match rest.iter().position(|&byte| byte == b',') {
+            Some(comma) => {
+                self.rest = Some(&rest[comma + 1..]);
+                Ok(&rest[..comma])
+            }
+            None => {
+                self.rest = None;
+                Ok(rest)
+            }
+        }
+    }
+}
+
+fn hex_digit(byte: u8) -> Result<u8, Error> {
+    match byte {
+        b'0'..=b'9' => Ok(byte - b'0'),
+        b'A'..=b'F' => Ok(byte - b'A' + 10),
+        b'a'..=b'f' => Ok(byte - b'a' + 10),
+        _ => Err(Error::Framing),
+    }
+}
+
+/// A decimal number times 10 to the power of `digits`, the digits past
+/// that are dropped.
+fn decimal(field: &[u8], digits: usize) -> Result<Option<i64>, Error> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    let (negative, field) = match field.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        _ => (false, field),
+    };
+    let (integer, fraction) = match field.iter().position(|&byte| byte == b'.') {
+        Some(dot) => (&field[..dot], &field[dot + 1..]),
+        None => (field, &[][..]),
+    };
+    if integer.is_empty() && fraction.is_empty() || !fraction.iter().all(u8::is_ascii_digit) {
+        return Err(Error::Field);
+    }
+
+    let padded = fraction.iter().copied().chain(core::iter::repeat(b'0'));
+    let mut value = 0i64;
+    for byte in integer.iter().copied().chain(padded.take(digits)) {
+        if !byte.is_ascii_digit() {
+            return Err(Error::Field);
+        }
+        value = value
+            .checked_mul(10)
+            .and_then(|value| value.checked_add(i64::from(byte - b'0')))
+            .ok_or(Error::Field)?;
+    }
+    Ok(Some(if negative { -value } else { value }))
+}
+
+fn number<T: TryFrom<i64>>(field: &[u8]) -> Result<Option<T>, Error> {
+    decimal(field, 0)?
+        .map(|value| T::try_from(value).map_err(|_| Error::Field))
+        .transpose()
+}
+
+/// `hhmmss` with any fraction of a second.
+fn time(field: &[u8]) -> Result<Option<Time>, Error> {
+    let Some(value) = decimal(field, 3)? else {
+        return Ok(None);
+    };
+    let digits = field
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    let time = Time {
+        hour: (value / 10_000_000) as u8,
+        minute: (value / 100_000 % 100) as u8,
+        second: (value / 1000 % 100) as u8,
+        millisecond: (value % 1000) as u16,
+    };
+    if digits != 6 || value < 0 || time.hour > 23 || time.minute > 59 || time.second > 60 {
+        return Err(Error::Field);
+    }
+    Ok(Some(time))
+}
+
+/// `ddmmyy`, in this century.
+fn date(field: &[u8]) -> Result<Option<Date>, Error> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    if field.len() != 6 {
+        return Err(Error::Field);
+    }
+    let value: u32 = number(field)?.ok_or(Error::Field)?;
+    let date = Date {
+        year: 2000 + (value % 100) as u16,
+        month: (value / 100 % 100) as u8,
+        day: (value / 10_000) as u8,
+    };
+    if !date.is_valid() {
+        return Err(Error::Field);
+    }
+    Ok(Some(date))
+}
+
+/// The latitude and the longitude fields, each with its hemisphere.
+fn position(fields: &mut Fields) -> Result<Option<Position>, Error> {
+    let latitude = angle(fields.next()?, fields.next()?, 90, *b"NS")?;
+    let longitude = angle(fields.next()?, fields.next()?, 180, *b"EW")?;
+    Ok(latitude
+        .zip(longitude)
+        .map(|(latitude, longitude)| Position {
+            latitude,
+            longitude,
+        }))
+}
+
+/// `dddmm.mmmm` and the hemisphere.
+fn angle(
+    field: &[u8],
+    hemisphere: &[u8],
+    max_degrees: i64,
+    hemispheres: [u8; 2],
+) -> Result<Option<i32>, Error> {
+    let Some(value) = decimal(field, 5)? else {
+        return Ok(None);
+    };
+    let degrees = value / 10_000_000;
+    let minutes = value % 10_000_000;
+    if value < 0 || degrees > max_degrees || minutes >= 6_000_000 {
+        return Err(Error::Field);
+    }
+    let angle = degrees * 10_000_000 + (minutes * 100 + 30) / 60;
+    if angle > max_degrees * 10_000_000 {
+        return Err(Error::Field);
+    }
+    let angle = angle as i32;
+    match hemisphere {
+        [byte] if *byte == hemispheres[0] => Ok(Some(angle)),
+        [byte] if *byte == hemispheres[1] => Ok(Some(-angle)),
+        _ => Err(Error::Field),
+    }
+}
+
+fn knots(field: &[u8]) -> Result<Option<u32>, Error> {
+    speed(field, 1852, 3600)
+}
+
+/// The speed in mm/s from the field in units of `numerator / denominator`
+/// metres a second.
+fn speed(field: &[u8], numerator: i64, denominator: i64) -> Result<Option<u32>, Error> {
+    decimal(field, 3)?
+        .map(|speed| {
+            speed
+                .checked_mul(numerator)
+                .and_then(|speed| u32::try_from(speed / denominator).ok())
+                .ok_or(Error::Field)
+        })
+        .transpose()
+}
+
+fn course(field: &[u8]) -> Result<Option<u32>, Error> {
+    match decimal(field, 3)? {
+        None => Ok(None),
+        Some(course @ 0..=360_000) => Ok(Some(course as u32)),
+        Some(_) => Err(Error::Field),
+    }
+}
+
+/// What the receiver last said about the position and the time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fix {
+    pub time: Option<Time>,
+    pub date: Option<Date>,
+    /// Only while the receiver has a fix.
+    pub position: Option<Position>,
+    pub altitude_mm: Option<i32>,
+    pub quality: Quality,
+    pub satellites_used: u8,
+    pub satellites_in_view: u8,
+    pub hdop_centi: Option<u16>,
+    pub speed_mm_s: Option<u32>,
+    pub course_mdeg: Option<u32>,
+}
+
+impl Fix {
+    pub fn update(&mut self, sentence: &Sentence) {
+        match *sentence {
+            Sentence::Gga(gga) => {
+                self.time = gga.time.or(self.time);
+                self.quality = gga.quality;
+                self.position = gga.position.filter(|_| gga.quality != Quality::NoFix);
+                self.altitude_mm = gga.altitude_mm;
+                self.satellites_used = gga.satellites;
+                self.hdop_centi = gga.hdop_centi;
+            }
+            Sentence::Rmc(rmc) => {
+                self.time = rmc.time.or(self.time);
+                self.date = rmc.date.or(self.date);
+                self.position = rmc.position;
+                self.speed_mm_s = rmc.speed_mm_s;
+                self.course_mdeg = rmc.course_mdeg;
+            }
+            Sentence::Gsv(gsv) => self.satellites_in_view = gsv.in_view,
+            Sentence::Vtg(vtg) => {
+                self.speed_mm_s = vtg.speed_mm_s;
+                self.course_mdeg = vtg.course_mdeg;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GGA: &[u8] = b"$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+    const RMC: &[u8] = b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+    const GSV: &[u8] = b"$GPGSV,2,1,08,01,40,083,46,02,17,308,41,12,07,344,39,14,22,228,45*75";
+    const VTG: &[u8] = b"$GPVTG,054.7,T,034.4,M,005.5,N,010.2,K*48";
+
+    /// The sentence with the body and the checksum it needs.
+    fn sentence(body: &[u8]) -> Vec<u8> {
+        let checksum = body.iter().fold(0, |sum, &byte| sum ^ byte);
+        let mut line = b"$".to_vec();
+        line.extend_from_slice(body);
+        line.extend_from_slice(format!("*{checksum:02X}").as_bytes());
+        line
+    }
+
+    #[test]
+    fn parses_gga() {
+        let Ok(Sentence::Gga(gga)) = parse(GGA) else {
+            panic!();
+        };
+        assert_eq!(
+            gga,
+            Gga {
+                time: Some(Time {
+                    hour: 12,
+                    minute: 35,
+                    second: 19,
+                    millisecond: 0
+                }),
+                position: Some(Position {
+                    latitude: 481_173_000,
+                    longitude: 115_166_667,
+                }),
+                quality: Quality::Gps,
+                satellites: 8,
+                hdop_centi: Some(90),
+                altitude_mm: Some(545_400),
+            }
+        );
+
+        // Before the first fix
+        let line = sentence(b"GNGGA,002153.000,,,,,0,00,,,M,,M,,");
+        let Ok(Sentence::Gga(gga)) = parse(&line) else {
+            panic!();
+        };
+        assert_eq!(gga.time.unwrap().millisecond, 0);
+        assert_eq!(gga.position, None);
+        assert_eq!(gga.quality, Quality::NoFix);
+        assert_eq!(gga.altitude_mm, None);
+    }
+
+    #[test]
+    fn parses_rmc() {
+        let Ok(Sentence::Rmc(rmc)) = parse(RMC) else {
+            panic!();
+        };
+        assert!(rmc.valid);
+        assert_eq!(rmc.speed_mm_s, Some(11_523));
+        assert_eq!(rmc.course_mdeg, Some(84_400));
+        assert_eq!(
+            rmc.date,
+            Some(Date {
+                year: 2094,
+                month: 3,
+                day: 23
+            })
+        );
+
+        // South and west, with a mode field, but no fix
+        let line = sentence(b"GNRMC,235959.50,V,3351.1234,S,15112.5678,W,,,311299,,,N");
+        let Ok(Sentence::Rmc(rmc)) = parse(&line) else {
+            panic!();
+        };
+        assert!(!rmc.valid);
+        assert_eq!(rmc.position, None);
+        assert_eq!(rmc.time.unwrap().millisecond, 500);
+
+        let line = sentence(b"GNRMC,235959.50,A,3351.1234,S,15112.5678,W,,,311299,,,A");
+        let Ok(Sentence::Rmc(rmc)) = parse(&line) else {
+            panic!();
+        };
+        assert_eq!(
+            rmc.position,
+            Some(Position {
+                latitude: -338_520_567,
+                longitude: -1_512_094_633,
+            })
+        );
+        assert_eq!(
+            rmc.position.unwrap().to_string(),
+            "33.852057 S 151.209463 W"
+        );
+    }
+
+    #[test]
+    fn parses_gsv_and_vtg() {
+        let Ok(Sentence::Gsv(gsv)) = parse(GSV) else {
+            panic!();
+        };
+        assert_eq!((gsv.sentences, gsv.sentence, gsv.in_view), (2, 1, 8));
+        assert_eq!(
+            gsv.satellites[3],
+            Some(Satellite {
+                prn: 14,
+                elevation: Some(22),
+                azimuth: Some(228),
+                snr: Some(45),
+            })
+        );
+
+        // The last of the series, one untracked satellite and a signal ID
+        let line = sentence(b"GPGSV,3,3,09,32,05,012,,1");
+        let Ok(Sentence::Gsv(gsv)) = parse(&line) else {
+            panic!();
+        };
+        assert_eq!(gsv.satellites[0].unwrap().snr, None);
+        assert_eq!(gsv.satellites[1], None);
+
+        assert_eq!(
+            parse(VTG),
+            Ok(Sentence::Vtg(Vtg {
+                course_mdeg: Some(54_700),
+                speed_mm_s: Some(2833),
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_damaged_sentences() {
+        assert_eq!(parse(b"GPGGA,123519*47"), Err(Error::Framing));
+        assert_eq!(parse(&GGA[..GGA.len() - 3]), Err(Error::Framing));
+        assert_eq!(parse(&GGA[..GGA.len() - 1]), Err(Error::Framing));
+
+        let mut damaged = GGA.to_vec();
+        damaged[10] = b'9';
+        assert_eq!(parse(&damaged), Err(Error::Checksum));
+        let mut lower = RMC.to_vec();
+        let len = lower.len();
+        lower[len - 1] = b'a';
+        assert!(parse(&lower).is_ok());
+
+        assert_eq!(parse(&sentence(b"GPZDA,1")), Err(Error::Unsupported));
+        assert_eq!(parse(&sentence(b"PUBX,00")), Err(Error::Unsupported));
+        assert_eq!(parse(&sentence(b"GPGGA,123519")), Err(Error::Field));
+        assert_eq!(
+            parse(&sentence(
+                b"GPRMC,123519,A,9107.038,N,01131.000,E,,,230394,,"
+            )),
+            Err(Error::Field)
+        );
+        assert_eq!(
+            parse(&sentence(
+                b"GPRMC,123519,A,4807.038,N,01131.000,E,,,300294,,"
+            )),
+            Err(Error::Field)
+        );
+        assert_eq!(
+            parse(&sentence(
+                b"GPRMC,126019,A,4807.038,N,01131.000,E,,,230394,,"
+            )),
+            Err(Error::Field)
+        );
+        assert_eq!(
+            parse(&sentence(
+                b"GPGGA,123519,4807.038,N,01131.000,E,1,99999999999999999999,,,M,,M,,"
+            )),
+            Err(Error::Field)
+        );
+    }
+
+    #[test]
+    fn survives_garbage() {
+        // Mutated sentences with the checksum fixed, so the fields get
+        // parsed
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let alphabet = b"0123456789.,-*$NSEWAVMK";
+        for _ in 0..20_000 {
+            let original = [GGA, RMC, GSV, VTG][random() as usize % 4];
+            let mut body = original[1..original.len() - 3].to_vec();
+            for _ in 0..1 + random() % 4 {
+                let at = random() as usize % body.len();
+                match random() % 3 {
+                    0 => body[at] = alphabet[random() as usize % alphabet.len()],
+                    1 => body.truncate(at.max(1)),
+                    _ => body.insert(at, alphabet[random() as usize % alphabet.len()]),
+                }
+            }
+            let line = sentence(&body);
+            if let Ok(sentence) = parse(&line) {
+                Fix::default().update(&sentence);
+            }
+            let _ = parse(&body);
+        }
+    }
+
+    #[test]
+    fn gathers_the_fix() {
+        let mut fix = Fix::default();
+        for line in [GGA, RMC, GSV, VTG] {
+            fix.update(&parse(line).unwrap());
+        }
+        assert_eq!(fix.quality, Quality::Gps);
+        assert_eq!(fix.satellites_used, 8);
+        assert_eq!(fix.satellites_in_view, 8);
+        assert_eq!(fix.speed_mm_s, Some(2833));
+        assert_eq!(fix.date.unwrap().to_string(), "2094-03-23");
+        assert_eq!(fix.time.unwrap().to_string(), "12:35:19");
+        assert_eq!(fix.position.unwrap().to_string(), "48.117300 N 11.516667 E");
+
+        let lost = sentence(b"GPRMC,123520,V,,,,,,,230394,,");
+        fix.update(&parse(&lost).unwrap());
+        assert_eq!(fix.position, None);
+        assert_eq!(fix.time.unwrap().second, 20);
+    }
+
+    #[test]
+    fn counts_the_seconds_and_days() {
+        let date = |year, month, day| Date { year, month, day };
+        let time = |hour, minute, second| Time {
+            hour,
+            minute,
+            second,
+            millisecond: 0,
+        };
+        assert_eq!(
+            next_second(date(2024, 2, 28), time(23, 59, 59)),
+            (date(2024, 2, 29), time(0, 0, 0))
+        );
+        assert_eq!(
+            next_second(date(2100, 2, 28), time(23, 59, 59)),
+            (date(2100, 3, 1), time(0, 0, 0))
+        );
+        assert_eq!(
+            next_second(date(2016, 12, 31), time(23, 59, 60)),
+            (date(2017, 1, 1), time(0, 0, 0))
+        );
+        assert_eq!(
+            next_second(date(2024, 4, 30), time(12, 34, 56)),
+            (date(2024, 4, 30), time(12, 34, 57))
+        );
+
+        assert_eq!(date(2000, 1, 1).day_of_week(), Some(6));
+        assert_eq!(date(2024, 2, 29).day_of_week(), Some(4));
+        assert_eq!(date(2094, 3, 23).day_of_week(), Some(2));
+        assert_eq!(date(1, 1, 1).day_of_week(), Some(1));
+    }
+
+    #[test]
+    fn has_no_day_of_the_week_for_invalid_dates() {
+        assert!(!Date::default().is_valid());
+        assert_eq!(Date::default().day_of_week(), None);
+        let date = |year, month, day| Date { year, month, day };
+        for invalid in [
+            date(0, 1, 1),
+            date(2024, 0, 1),
+            date(2024, 13, 1),
+            date(2024, 1, 0),
+            date(2023, 2, 29),
+        ] {
+            assert_eq!(invalid.day_of_week(), None, "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Firmware updates over USB into the other flash slot.
+//!
+//! `memory.x` splits the flash into two slots of the same size. An image
+//! runs from the slot it is linked for: the default build is for slot A,
+//! the one the boot ROM starts, `PICO_BITES_SLOT=b` links for slot B.
+//!
+//! The firmware running from slot A receives the image for slot B with an
+//! [`Updater`], checks its SHA-256 and records the slot as on trial in a
+//! [`StateStore`]; slot A itself is only written with a UF2, see
+//! [`protocol`]. On the next boot the image in slot A (see
+//! `boot`) starts the trial slot with the watchdog running; the new image
+//! has [`TRIAL_TIMEOUT_US`] to confirm itself, or the watchdog resets the
+//! chip and the previous slot runs again. Confirming makes the running
+//! slot the active one.
+//!
+//! ```ignore
+//! #[hal::entry]
+//! fn main() -> ! {
+//!     // Before anything else, this may jump to slot B
+//!     ota::boot();
+//!     ...
+//!     // Once the firmware is known to work
+//!     ota::confirm();
+//! }
+//! ```
+//!
+//! Every image for slot A must call `ota::boot()`, otherwise slot B never
+//! runs and a bad image in slot A has no way back.
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod boot;
+pub mod protocol;
+mod updater;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::boot;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::confirm;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::reboot;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::running_slot;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::slot_info;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use boot::update_flash;
+pub use updater::Updater;
+
+use core::fmt;
+use core::ops::Range;
+
+use crate::flash;
+use crate::flash::journal;
+use crate::flash::Flash;
+use crate::flash::Journal;
+
+/// How long a new image has to confirm itself, close to the longest
+/// watchdog period of the RP2040.
+pub const TRIAL_TIMEOUT_US: u32 = 8_000_000;
+
+/// Where the vector table is in a slot, past the copy of boot2.
+pub const VECTOR_TABLE_OFFSET: usize = 0x100;
+
+/// The RAM, with the scratch X and Y banks.
+const RAM: Range<u32> = 0x2000_0000..0x2004_2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    pub fn other(self) -> Self {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Slot::A),
+            1 => Some(Slot::B),
+            _ => None,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Slot::A => "A",
+            Slot::B => "B",
+        })
+    }
+}
+
+/// Tells whether the start of a vector table belongs to an image linked
+/// for the slot at `base` (its address in the XIP space), `len` bytes long.
+pub fn check_vector_table(stack: u32, reset: u32, base: u32, len: usize) -> bool {
+    let code = base + VECTOR_TABLE_OFFSET as u32..base + len as u32;
+    // A Thumb address, the lowest bit is set
+    (RAM.start..=RAM.end).contains(&stack) && reset & 1 == 1 && code.contains(&reset)
+}
+
+/// Which slot runs, and which one is on trial.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State {
+    pub active: Slot,
+    pub trial: Option<Slot>,
+}
+
+impl Default for State {
+    /// What a flash without any record means: the image the boot ROM
+    /// starts.
+    fn default() -> Self {
+        Self {
+            active: Slot::A,
+            trial: None,
+        }
+    }
+}
+
+impl State {
+    /// The slot to boot, and whether it starts a trial. A trial that was
+    /// started before and did not confirm in time falls back to the active
+    /// slot.
+    pub fn boot_slot(&self, trial_started: bool) -> (Slot, bool) {
+        match self.trial {
+            Some(trial) if !trial_started => (trial, true),
+            _ => (self.active, false),
+        }
+    }
+
+    /// The state once the running slot confirms, `None` if it does not
+    /// change.
+    pub fn confirmed(&self, running: Slot) -> Option<Self> {
+        let confirmed = Self {
+            active: running,
+            trial: None,
+        };
+        (*self != confirmed).then_some(confirmed)
+    }
+}
+
+const NO_TRIAL: u8 = 0xff;
+
+/// Keeps the [`State`] in a [`Journal`], so the newest one survives a power
+/// failure at any point.
+pub struct StateStore<F: Flash> {
+    journal: Journal<F>,
+}
+
+impl<F: Flash> StateStore<F> {
+    /// The region must be two sectors or more.
+    pub fn new(flash: F) -> Self {
+        Self {
+            journal: Journal::new(flash),
+        }
+    }
+
+    /// The newest state, the default one if there is none.
+    pub fn read(&mut self) -> Result<State, flash::Error> {
+        let mut record = [0u8; journal::MAX_RECORD];
+        let state = match self.journal.read(&mut record)? {
+            Some(len) => decode_state(&record[..len]),
+            None => None,
+        };
+        Ok(state.unwrap_or_default())
+    }
+
+    pub fn write(&mut self, state: State) -> Result<(), flash::Error> {
+        let record = [
+            state.active.to_u8(),
+            state.trial.map_or(NO_TRIAL, Slot::to_u8),
+        ];
+        self.journal.write(&record)
+    }
+}
+
+fn decode_state(record: &[u8]) -> Option<State> {
+    let &[active, trial] = record else {
+        return None;
+    };
+    let trial = match trial {
+        NO_TRIAL => None,
+        slot => Some(Slot::from_u8(slot)?),
+    };
+    Some(State {
+        active: Slot::from_u8(active)?,
+        trial,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+
+    const TRIAL_B: State = State {
+        active: Slot::A,
+        trial: Some(Slot::B),
+    };
+
+    #[test]
+    fn boots_the_trial_once() {
+        assert_eq!(TRIAL_B.boot_slot(false), (Slot::B, true));
+        // The trial did not confirm before the watchdog reset
+        assert_eq!(TRIAL_B.boot_slot(true), (Slot::A, false));
+        assert_eq!(State::default().boot_slot(false), (Slot::A, false));
+
+        // The new image confirms, or the old one takes over again
+        let confirmed = TRIAL_B.confirmed(Slot::B).unwrap();
+        assert_eq!(confirmed.boot_slot(false), (Slot::B, false));
+        assert_eq!(TRIAL_B.confirmed(Slot::A), Some(State::default()));
+        assert_eq!(confirmed.confirmed(Slot::B), None);
+    }
+
+    #[test]
+    fn checks_vector_tables() {
+        let base = 0x1000_0000 + 0x80000;
+        let len = 0x80000;
+        assert!(check_vector_table(0x2004_0000, base + 0x1c1, base, len));
+        // Linked for the other slot, an ARM address, a bad stack
+        assert!(!check_vector_table(0x2004_0000, 0x1000_01c1, base, len));
+        assert!(!check_vector_table(0x2004_0000, base + 0x1c0, base, len));
+        assert!(!check_vector_table(0xffff_ffff, base + 0x1c1, base, len));
+    }
+
+    #[test]
+    fn keeps_the_newest_state() {
+        let mut store = StateStore::new(SimFlash::new(2));
+        assert_eq!(store.read(), Ok(State::default()));
+
+        let states = [
+            TRIAL_B,
+            State::default(),
+            TRIAL_B.confirmed(Slot::B).unwrap(),
+        ];
+        for state in states {
+            store.write(state).unwrap();
+            assert_eq!(store.read(), Ok(state));
+        }
+
+        // A new store finds it again
+        let mut store = StateStore::new(store.journal.into_inner());
+        assert_eq!(store.read(), Ok(states[2]));
+    }
+}
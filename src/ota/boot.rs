@@ -0,0 +1,173 @@
+//! Choosing the slot at boot, the trial watchdog and the reboot.
+
+use core::ops::Range;
+
+use rp2040_hal as hal;
+
+use hal::pac;
+
+use crate::crash;
+use crate::flash::RomFlash;
+use crate::flash::XIP_BASE;
+
+use super::check_vector_table;
+use super::protocol::Info;
+use super::Slot;
+use super::StateStore;
+use super::TRIAL_TIMEOUT_US;
+use super::VECTOR_TABLE_OFFSET;
+
+extern "C" {
+    static __slot_a_start: u8;
+    static __slot_b_start: u8;
+    static __slot_b_end: u8;
+    static __ota_state_start: u8;
+    static __ota_state_end: u8;
+}
+
+/// In the watchdog scratch register while a trial runs. The scratch
+/// registers survive the watchdog reset, the power-on clears them.
+const TRIAL_MAGIC: u32 = 0x7472_6961;
+
+/// The longest the watchdog counter can count.
+const MAX_WATCHDOG_LOAD: u32 = 0xff_ffff;
+
+/// Offsets of the slot from the start of the flash.
+fn slot_region(slot: Slot) -> Range<usize> {
+    // Only the addresses of the linker symbols are used
+    let (a, b, end) = (
+        core::ptr::addr_of!(__slot_a_start) as usize,
+        core::ptr::addr_of!(__slot_b_start) as usize,
+        core::ptr::addr_of!(__slot_b_end) as usize,
+    );
+    match slot {
+        Slot::A => a - XIP_BASE..b - XIP_BASE,
+        Slot::B => b - XIP_BASE..end - XIP_BASE,
+    }
+}
+
+fn state_store() -> StateStore<RomFlash> {
+    let (start, end) = (
+        core::ptr::addr_of!(__ota_state_start) as usize,
+        core::ptr::addr_of!(__ota_state_end) as usize,
+    );
+    // The region is reserved by memory.x and only used from here
+    StateStore::new(unsafe { RomFlash::new(start - XIP_BASE..end - XIP_BASE) })
+}
+
+/// The slot the code runs from.
+pub fn running_slot() -> Slot {
+    // The address of any function tells
+    let pc = running_slot as fn() -> Slot as usize - XIP_BASE;
+    if slot_region(Slot::B).contains(&pc) {
+        Slot::B
+    } else {
+        Slot::A
+    }
+}
+
+/// The slots as the update protocol describes them.
+pub fn slot_info() -> Info {
+    let (a, b) = (slot_region(Slot::A), slot_region(Slot::B));
+    Info {
+        running: running_slot(),
+        slot_a: (XIP_BASE + a.start) as u32,
+        slot_b: (XIP_BASE + b.start) as u32,
+        slot_len: a.len() as u32,
+    }
+}
+
+/// The flash of the slot the firmware does not run from, and the state
+/// store, for an [`Updater`](super::Updater), which never writes slot A.
+///
+/// # Safety
+///
+/// Only one updater may exist.
+pub unsafe fn update_flash() -> (RomFlash, StateStore<RomFlash>) {
+    let target = slot_region(running_slot().other());
+    (RomFlash::new(target), state_store())
+}
+
+/// Runs the slot the state asks for, call it first thing in `main`, before
+/// the clocks and the peripherals are set up.
+///
+/// Only the image in slot A decides, an image in slot B was started by it.
+/// A new image starts its trial here: the watchdog runs from now on, and
+/// the next boot falls back to the active slot unless [`confirm`] is called
+/// first.
+pub fn boot() {
+    let running = running_slot();
+    if running != Slot::A {
+        return;
+    }
+
+    // Nothing else runs yet
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    let trial_started = watchdog.scratch0().read().bits() == TRIAL_MAGIC;
+    let mut store = state_store();
+    let state = store.read().unwrap_or_default();
+    let (slot, start_trial) = state.boot_slot(trial_started);
+    if trial_started {
+        // Back from a failed trial, forgotten so that no later reset starts
+        // it again, and `confirm` leaves the watchdog of the old image be
+        watchdog.scratch0().write(|w| unsafe { w.bits(0) });
+        if let Some(state) = state.confirmed(slot) {
+            // Tried again at the next boot
+            let _ = store.write(state);
+        }
+    }
+    if start_trial {
+        watchdog
+            .scratch0()
+            .write(|w| unsafe { w.bits(TRIAL_MAGIC) });
+        crash::select_watchdog_reset();
+        // The counter goes down by two every tick (erratum RP2040-E1), the
+        // tick is a microsecond once the clocks run from the crystal
+        let load = TRIAL_TIMEOUT_US.saturating_mul(2).min(MAX_WATCHDOG_LOAD);
+        watchdog.load().write(|w| unsafe { w.bits(load) });
+        watchdog.ctrl().modify(|_, w| w.enable().set_bit());
+    }
+    if slot == running {
+        return;
+    }
+
+    let region = slot_region(slot);
+    let vector_table = (XIP_BASE + region.start + VECTOR_TABLE_OFFSET) as *const u32;
+    // Within the slot, which is mapped read-only
+    let (stack, reset) = unsafe { (vector_table.read(), vector_table.add(1).read()) };
+    if !check_vector_table(stack, reset, (XIP_BASE + region.start) as u32, region.len()) {
+        return;
+    }
+    // The image starts as after the reset, with its own vector table
+    unsafe {
+        (*cortex_m::peripheral::SCB::PTR)
+            .vtor
+            .write(vector_table as u32);
+        cortex_m::asm::bootload(vector_table)
+    }
+}
+
+/// Makes the running slot the active one and ends the trial, if any.
+/// Returns whether the state changed: a new image was confirmed, or the
+/// old one took over after a failed trial.
+pub fn confirm() -> bool {
+    let mut store = state_store();
+    let changed = match store.read().map(|state| state.confirmed(running_slot())) {
+        Ok(Some(state)) => store.write(state).is_ok(),
+        _ => false,
+    };
+
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    if watchdog.scratch0().read().bits() == TRIAL_MAGIC {
+        watchdog.scratch0().write(|w| unsafe { w.bits(0) });
+        watchdog.ctrl().modify(|_, w| w.enable().clear_bit());
+    }
+    changed
+}
+
+/// Resets the chip, a trial of the new image starts on the way up.
+pub fn reboot() -> ! {
+    // Not a failed trial, whatever ran before
+    unsafe { (*pac::WATCHDOG::ptr()).scratch0().write(|w| w.bits(0)) };
+    crash::reset()
+}
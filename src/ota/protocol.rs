@@ -0,0 +1,285 @@
+//! The update protocol, shared with the uploader in `tools/pico-update`.
+//!
+//! The host sends requests and the firmware answers each one, both in the
+//! same frame:
+//!
+//! | `SYNC` | body length, u16 | body | CRC-32 of the body, u32 |
+//!
+//! All numbers are little-endian. A request body is a [`Command`] byte and
+//! its arguments, a response body a [`Status`] byte and the results:
+//!
+//! | Command  | Arguments                 | Results         |
+//! |----------|---------------------------|-----------------|
+//! | `Info`   |                           | [`Info`]        |
+//! | `Begin`  | image size u32, SHA-256   |                 |
+//! | `Data`   | offset u32, up to 256 bytes | bytes received u32 |
+//! | `Finish` |                           |                 |
+//! | `Reboot` |                           |                 |
+//! | `Fallback` |                         |                 |
+//!
+//! The data comes in order in whole pages, only the last one may be
+//! shorter. Sending the last chunk again is fine, so the host can retry
+//! when a response gets lost.
+//!
+//! The updates go to slot B only. Slot A starts with boot2, which the boot
+//! ROM runs from the start of the flash, and its image picks the slot: cut
+//! short or bad, nothing would be left to fall back to. A board running
+//! slot B answers `Begin` with [`Status::BootSlot`], and `Fallback` makes
+//! slot A the active one and reboots into it, to take the update from
+//! there.
+
+use crate::crc::crc32;
+
+use super::Slot;
+
+pub const SYNC: u8 = 0xa5;
+
+/// The most image bytes in a `Data` request, one flash page.
+pub const MAX_DATA: usize = crate::flash::PAGE_SIZE;
+pub const MAX_BODY: usize = 1 + 4 + MAX_DATA;
+pub const MAX_FRAME: usize = 3 + MAX_BODY + 4;
+
+pub const DIGEST_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Command {
+    Info = 1,
+    Begin = 2,
+    Data = 3,
+    Finish = 4,
+    Reboot = 5,
+    /// Makes slot A the active one, then reboots like `Reboot`.
+    Fallback = 6,
+}
+
+impl Command {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => Command::Info,
+            2 => Command::Begin,
+            3 => Command::Data,
+            4 => Command::Finish,
+            5 => Command::Reboot,
+            6 => Command::Fallback,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Status {
+    Ok = 0,
+    /// The request failed the CRC.
+    BadFrame = 1,
+    /// An unknown command or malformed arguments.
+    BadRequest = 2,
+    /// `Data` or `Finish` without a `Begin`, or before all the data.
+    BadState = 3,
+    /// The data is not where the previous chunk ended.
+    BadOffset = 4,
+    /// The image does not fit the slot.
+    TooLarge = 5,
+    /// The SHA-256 of the received image differs.
+    DigestMismatch = 6,
+    /// The image is not linked for the slot.
+    WrongSlot = 7,
+    /// Erasing or programming failed.
+    Flash = 8,
+    /// The update would go to slot A, `Fallback` first.
+    BootSlot = 9,
+}
+
+impl Status {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Status::Ok,
+            1 => Status::BadFrame,
+            2 => Status::BadRequest,
+            3 => Status::BadState,
+            4 => Status::BadOffset,
+            5 => Status::TooLarge,
+            6 => Status::DigestMismatch,
+            7 => Status::WrongSlot,
+            8 => Status::Flash,
+            9 => Status::BootSlot,
+            _ => return None,
+        })
+    }
+}
+
+/// What the `Info` command returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Info {
+    /// The slot the firmware runs from, the update goes to the other one.
+    pub running: Slot,
+    /// Addresses of the slots in the XIP space.
+    pub slot_a: u32,
+    pub slot_b: u32,
+    /// Size of a slot in bytes, the largest image.
+    pub slot_len: u32,
+}
+
+impl Info {
+    pub const LEN: usize = 13;
+
+    pub fn target(&self) -> Slot {
+        self.running.other()
+    }
+
+    /// Address of the slot in the XIP space.
+    pub fn base(&self, slot: Slot) -> u32 {
+        match slot {
+            Slot::A => self.slot_a,
+            Slot::B => self.slot_b,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.running.to_u8();
+        bytes[1..5].copy_from_slice(&self.slot_a.to_le_bytes());
+        bytes[5..9].copy_from_slice(&self.slot_b.to_le_bytes());
+        bytes[9..13].copy_from_slice(&self.slot_len.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        Some(Self {
+            running: Slot::from_u8(bytes[0])?,
+            slot_a: word(1),
+            slot_b: word(5),
+            slot_len: word(9),
+        })
+    }
+}
+
+/// Frames the body into `out`, returns the length of the frame.
+///
+/// Panics if the body is longer than [`MAX_BODY`] or `out` is too short.
+pub fn encode(body: &[u8], out: &mut [u8]) -> usize {
+    assert!(body.len() <= MAX_BODY);
+    let len = 3 + body.len() + 4;
+    out[0] = SYNC;
+    out[1..3].copy_from_slice(&(body.len() as u16).to_le_bytes());
+    out[3..3 + body.len()].copy_from_slice(body);
+    out[3 + body.len()..len].copy_from_slice(&crc32(body).to_le_bytes());
+    len
+}
+
+/// Why a frame was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// The length is over [`MAX_BODY`].
+    TooLong,
+    /// The CRC does not match, the frame was damaged.
+    Crc,
+}
+
+/// Collects the frames from the bytes as they come.
+pub struct Decoder {
+    buf: [u8; MAX_FRAME],
+    len: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            buf: [0; MAX_FRAME],
+            len: 0,
+        }
+    }
+
+    /// Takes the next byte, returns the body once the frame is complete.
+    /// Anything before the `SYNC` byte is skipped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], FrameError>> {
+        if self.len == 0 && byte != SYNC {
+            return None;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < 3 {
+            return None;
+        }
+
+        let body_len = usize::from(u16::from_le_bytes([self.buf[1], self.buf[2]]));
+        if body_len > MAX_BODY {
+            self.len = 0;
+            return Some(Err(FrameError::TooLong));
+        }
+        let frame_len = 3 + body_len + 4;
+        if self.len < frame_len {
+            return None;
+        }
+
+        self.len = 0;
+        let body = &self.buf[3..3 + body_len];
+        let crc = u32::from_le_bytes(self.buf[3 + body_len..frame_len].try_into().unwrap());
+        if crc == crc32(body) {
+            Some(Ok(body))
+        } else {
+            Some(Err(FrameError::Crc))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let mut frame = [0u8; MAX_FRAME];
+        let len = encode(&[Command::Data as u8, 1, 2, 3], &mut frame);
+        assert_eq!(len, 3 + 4 + 4);
+        assert_eq!(&frame[..3], &[SYNC, 4, 0]);
+
+        let mut decoder = Decoder::new();
+        // Line noise before the frame
+        assert_eq!(decoder.push(0x00), None);
+        for &byte in &frame[..len - 1] {
+            assert_eq!(decoder.push(byte), None);
+        }
+        assert_eq!(
+            decoder.push(frame[len - 1]),
+            Some(Ok(&[Command::Data as u8, 1, 2, 3][..]))
+        );
+
+        let len = encode(&[Command::Finish as u8], &mut frame);
+        frame[3] ^= 0x10;
+        let results: Vec<_> = frame[..len]
+            .iter()
+            .filter_map(|&byte| decoder.push(byte).map(|r| r.map(<[u8]>::to_vec)))
+            .collect();
+        assert_eq!(results, [Err(FrameError::Crc)]);
+
+        assert_eq!(decoder.push(SYNC), None);
+        assert_eq!(decoder.push(0xff), None);
+        assert_eq!(decoder.push(0xff), Some(Err(FrameError::TooLong)));
+    }
+
+    #[test]
+    fn describes_the_slots() {
+        let info = Info {
+            running: Slot::B,
+            slot_a: 0x1000_0000,
+            slot_b: 0x100d_f000,
+            slot_len: 0xd_f000,
+        };
+        assert_eq!(Info::from_bytes(&info.to_bytes()), Some(info));
+        assert_eq!(info.target(), Slot::A);
+        assert_eq!(info.base(info.target()), 0x1000_0000);
+        assert_eq!(Info::from_bytes(&[2; Info::LEN]), None);
+    }
+}
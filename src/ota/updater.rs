@@ -0,0 +1,392 @@
+//! Receiving an image into the other slot.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::flash::Flash;
+use crate::flash::PAGE_SIZE;
+use crate::flash::SECTOR_SIZE;
+
+use super::check_vector_table;
+use super::protocol::Command;
+use super::protocol::Info;
+use super::protocol::Status;
+use super::protocol::DIGEST_LEN;
+use super::protocol::MAX_BODY;
+use super::protocol::MAX_DATA;
+use super::Slot;
+use super::State;
+use super::StateStore;
+use super::VECTOR_TABLE_OFFSET;
+
+/// The image being received.
+struct Transfer {
+    size: usize,
+    digest: [u8; DIGEST_LEN],
+    received: usize,
+}
+
+/// Answers the requests of the update protocol, see [`super::protocol`].
+///
+/// `slot` is the flash of the slot the firmware does not run from, `store`
+/// the one the state goes to. A verified image becomes the trial slot,
+/// the chip runs it after the `Reboot` request. Slot A is never written,
+/// see [`super::protocol`].
+pub struct Updater<F: Flash, S: Flash> {
+    slot: F,
+    store: StateStore<S>,
+    info: Info,
+    transfer: Option<Transfer>,
+    reboot: bool,
+}
+
+impl<F: Flash, S: Flash> Updater<F, S> {
+    pub fn new(slot: F, store: StateStore<S>, info: Info) -> Self {
+        Self {
+            slot,
+            store,
+            info,
+            transfer: None,
+            reboot: false,
+        }
+    }
+
+    /// The state store, for reading the state between the requests.
+    pub fn store(&mut self) -> &mut StateStore<S> {
+        &mut self.store
+    }
+
+    /// Whether the host asked for the reboot, reboot once the response is
+    /// sent.
+    pub fn reboot_requested(&self) -> bool {
+        self.reboot
+    }
+
+    /// Handles the request body, writes the response body into `response`
+    /// and returns its length.
+    pub fn handle(&mut self, request: &[u8], response: &mut [u8; MAX_BODY]) -> usize {
+        let (status, len) = match request.split_first() {
+            Some((&command, args)) => match Command::from_u8(command) {
+                Some(command) => self.dispatch(command, args, &mut response[1..]),
+                None => (Status::BadRequest, 0),
+            },
+            None => (Status::BadRequest, 0),
+        };
+        response[0] = status as u8;
+        1 + len
+    }
+
+    fn dispatch(&mut self, command: Command, args: &[u8], results: &mut [u8]) -> (Status, usize) {
+        match command {
+            Command::Info if args.is_empty() => {
+                results[..Info::LEN].copy_from_slice(&self.info.to_bytes());
+                (Status::Ok, Info::LEN)
+            }
+            Command::Begin if args.len() == 4 + DIGEST_LEN => (self.begin(args), 0),
+            Command::Data if args.len() > 4 && args.len() <= 4 + MAX_DATA => {
+                let offset = u32::from_le_bytes(args[..4].try_into().unwrap()) as usize;
+                let status = self.data(offset, &args[4..]);
+                let received = self.transfer.as_ref().map_or(0, |t| t.received);
+                results[..4].copy_from_slice(&(received as u32).to_le_bytes());
+                (status, 4)
+            }
+            Command::Finish if args.is_empty() => (self.finish(), 0),
+            Command::Reboot if args.is_empty() => {
+                self.reboot = true;
+                (Status::Ok, 0)
+            }
+            Command::Fallback if args.is_empty() => {
+                self.transfer = None;
+                match self.store.write(State::default()) {
+                    Ok(()) => {
+                        self.reboot = true;
+                        (Status::Ok, 0)
+                    }
+                    Err(_) => (Status::Flash, 0),
+                }
+            }
+            _ => (Status::BadRequest, 0),
+        }
+    }
+
+    fn begin(&mut self, args: &[u8]) -> Status {
+        self.transfer = None;
+        if self.info.target() == Slot::A {
+            return Status::BootSlot;
+        }
+        let size = u32::from_le_bytes(args[..4].try_into().unwrap()) as usize;
+        if size > self.slot.capacity() {
+            return Status::TooLarge;
+        }
+        // Too short to hold the vector table
+        if size < VECTOR_TABLE_OFFSET + 8 {
+            return Status::WrongSlot;
+        }
+        self.transfer = Some(Transfer {
+            size,
+            digest: args[4..].try_into().unwrap(),
+            received: 0,
+        });
+        Status::Ok
+    }
+
+    fn data(&mut self, offset: usize, data: &[u8]) -> Status {
+        let Some(transfer) = &mut self.transfer else {
+            return Status::BadState;
+        };
+        // The response to the previous chunk got lost
+        if offset + data.len() == transfer.received && offset.is_multiple_of(PAGE_SIZE) {
+            return Status::Ok;
+        }
+        let is_last = offset + data.len() == transfer.size;
+        if offset != transfer.received || (data.len() != PAGE_SIZE && !is_last) {
+            return Status::BadOffset;
+        }
+        if offset + data.len() > transfer.size {
+            return Status::TooLarge;
+        }
+
+        let mut page = [0xff; PAGE_SIZE];
+        page[..data.len()].copy_from_slice(data);
+        let written = if offset.is_multiple_of(SECTOR_SIZE) {
+            self.slot.erase_sector(offset)
+        } else {
+            Ok(())
+        }
+        .and_then(|()| self.slot.program(offset, &page));
+        if written.is_err() {
+            self.transfer = None;
+            return Status::Flash;
+        }
+        transfer.received += data.len();
+        Status::Ok
+    }
+
+    fn finish(&mut self) -> Status {
+        let Some(transfer) = self.transfer.take() else {
+            return Status::BadState;
+        };
+        if transfer.received != transfer.size {
+            self.transfer = Some(transfer);
+            return Status::BadState;
+        }
+
+        // Hash what the flash holds, not what was received
+        let mut hasher = Sha256::new();
+        let mut buf = [0u8; PAGE_SIZE];
+        for offset in (0..transfer.size).step_by(PAGE_SIZE) {
+            let chunk = &mut buf[..PAGE_SIZE.min(transfer.size - offset)];
+            if self.slot.read(offset, chunk).is_err() {
+                return Status::Flash;
+            }
+            hasher.update(&*chunk);
+        }
+        if hasher.finalize()[..] != transfer.digest {
+            return Status::DigestMismatch;
+        }
+
+        let mut vectors = [0u8; 8];
+        if self.slot.read(VECTOR_TABLE_OFFSET, &mut vectors).is_err() {
+            return Status::Flash;
+        }
+        let stack = u32::from_le_bytes(vectors[..4].try_into().unwrap());
+        let reset = u32::from_le_bytes(vectors[4..].try_into().unwrap());
+        let target = self.info.target();
+        let base = self.info.base(target);
+        if !check_vector_table(stack, reset, base, self.info.slot_len as usize) {
+            return Status::WrongSlot;
+        }
+
+        let state = State {
+            active: self.info.running,
+            trial: Some(target),
+        };
+        match self.store.write(state) {
+            Ok(()) => Status::Ok,
+            Err(_) => Status::Flash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+    use crate::ota::Slot;
+
+    const SLOT_LEN: usize = 4 * SECTOR_SIZE;
+    const SLOT_B: u32 = 0x1000_0000 + SLOT_LEN as u32;
+
+    fn updater() -> Updater<SimFlash, SimFlash> {
+        updater_in(Slot::A)
+    }
+
+    fn updater_in(running: Slot) -> Updater<SimFlash, SimFlash> {
+        let info = Info {
+            running,
+            slot_a: 0x1000_0000,
+            slot_b: SLOT_B,
+            slot_len: SLOT_LEN as u32,
+        };
+        Updater::new(SimFlash::new(4), StateStore::new(SimFlash::new(2)), info)
+    }
+
+    /// An image linked for slot B, with a partial last page.
+    fn image() -> Vec<u8> {
+        let mut image: Vec<u8> = (0..5 * PAGE_SIZE + 100).map(|i| i as u8).collect();
+        image[VECTOR_TABLE_OFFSET..VECTOR_TABLE_OFFSET + 4]
+            .copy_from_slice(&0x2004_2000u32.to_le_bytes());
+        image[VECTOR_TABLE_OFFSET + 4..VECTOR_TABLE_OFFSET + 8]
+            .copy_from_slice(&(SLOT_B + 0x1c1).to_le_bytes());
+        image
+    }
+
+    fn request(
+        updater: &mut Updater<SimFlash, SimFlash>,
+        command: Command,
+        args: &[u8],
+    ) -> Vec<u8> {
+        let mut body = vec![command as u8];
+        body.extend_from_slice(args);
+        let mut response = [0u8; MAX_BODY];
+        let len = updater.handle(&body, &mut response);
+        response[..len].to_vec()
+    }
+
+    fn begin(updater: &mut Updater<SimFlash, SimFlash>, image: &[u8]) -> Vec<u8> {
+        let mut args = (image.len() as u32).to_le_bytes().to_vec();
+        args.extend_from_slice(&Sha256::digest(image));
+        request(updater, Command::Begin, &args)
+    }
+
+    fn data(updater: &mut Updater<SimFlash, SimFlash>, offset: usize, chunk: &[u8]) -> Vec<u8> {
+        let mut args = (offset as u32).to_le_bytes().to_vec();
+        args.extend_from_slice(chunk);
+        request(updater, Command::Data, &args)
+    }
+
+    fn ok_received(received: usize) -> Vec<u8> {
+        let mut response = vec![Status::Ok as u8];
+        response.extend_from_slice(&(received as u32).to_le_bytes());
+        response
+    }
+
+    #[test]
+    fn receives_an_image() {
+        let mut updater = updater();
+        let response = request(&mut updater, Command::Info, &[]);
+        assert_eq!(response[0], Status::Ok as u8);
+        assert_eq!(Info::from_bytes(&response[1..]).unwrap().target(), Slot::B);
+
+        let image = image();
+        assert_eq!(begin(&mut updater, &image), [Status::Ok as u8]);
+        for (i, chunk) in image.chunks(MAX_DATA).enumerate() {
+            let offset = i * MAX_DATA;
+            let received = offset + chunk.len();
+            assert_eq!(data(&mut updater, offset, chunk), ok_received(received));
+            // The host did not get the response and sends the chunk again
+            assert_eq!(data(&mut updater, offset, chunk), ok_received(received));
+        }
+        assert_eq!(&updater.slot.data[..image.len()], &image[..]);
+
+        assert_eq!(
+            request(&mut updater, Command::Finish, &[]),
+            [Status::Ok as u8]
+        );
+        assert_eq!(
+            updater.store().read(),
+            Ok(State {
+                active: Slot::A,
+                trial: Some(Slot::B)
+            })
+        );
+
+        assert!(!updater.reboot_requested());
+        assert_eq!(
+            request(&mut updater, Command::Reboot, &[]),
+            [Status::Ok as u8]
+        );
+        assert!(updater.reboot_requested());
+    }
+
+    #[test]
+    fn rejects_bad_images() {
+        let mut updater = updater();
+        assert_eq!(
+            request(&mut updater, Command::Finish, &[]),
+            [Status::BadState as u8]
+        );
+        assert_eq!(
+            data(&mut updater, 0, &[0; 4]),
+            [Status::BadState as u8, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            request(&mut updater, Command::Info, &[1]),
+            [Status::BadRequest as u8]
+        );
+        assert_eq!(
+            request(&mut updater, Command::Info, &[])[0],
+            Status::Ok as u8
+        );
+
+        let too_large = vec![0; SLOT_LEN + 1];
+        assert_eq!(begin(&mut updater, &too_large), [Status::TooLarge as u8]);
+
+        // A chunk out of order, then one that was damaged on the way
+        let image = image();
+        begin(&mut updater, &image);
+        assert_eq!(
+            data(&mut updater, PAGE_SIZE, &image[..PAGE_SIZE])[0],
+            Status::BadOffset as u8
+        );
+        let mut damaged = image.clone();
+        damaged[PAGE_SIZE] ^= 1;
+        for (i, chunk) in damaged.chunks(MAX_DATA).enumerate() {
+            data(&mut updater, i * MAX_DATA, chunk);
+        }
+        assert_eq!(
+            request(&mut updater, Command::Finish, &[]),
+            [Status::DigestMismatch as u8]
+        );
+
+        // Linked for slot A
+        let mut for_a = image.clone();
+        for_a[VECTOR_TABLE_OFFSET + 4..VECTOR_TABLE_OFFSET + 8]
+            .copy_from_slice(&0x1000_01c1u32.to_le_bytes());
+        begin(&mut updater, &for_a);
+        for (i, chunk) in for_a.chunks(MAX_DATA).enumerate() {
+            data(&mut updater, i * MAX_DATA, chunk);
+        }
+        assert_eq!(
+            request(&mut updater, Command::Finish, &[]),
+            [Status::WrongSlot as u8]
+        );
+
+        assert_eq!(updater.store().read(), Ok(State::default()));
+    }
+
+    #[test]
+    fn leaves_slot_a_alone() {
+        let mut updater = updater_in(Slot::B);
+        updater
+            .store()
+            .write(State {
+                active: Slot::B,
+                trial: None,
+            })
+            .unwrap();
+        assert_eq!(begin(&mut updater, &image()), [Status::BootSlot as u8]);
+        assert_eq!(
+            data(&mut updater, 0, &[0; 4]),
+            [Status::BadState as u8, 0, 0, 0, 0]
+        );
+        assert!(updater.slot.data.iter().all(|&byte| byte == 0xff));
+
+        assert_eq!(
+            request(&mut updater, Command::Fallback, &[]),
+            [Status::Ok as u8]
+        );
+        assert_eq!(updater.store().read(), Ok(State::default()));
+        assert!(updater.reboot_requested());
+    }
+}
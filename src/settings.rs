@@ -0,0 +1,367 @@
+//! Settings kept in the flash, across reboots and firmware updates.
+//!
+//! A setting is a value of a few bytes under a one-byte [`Key`]. Changes
+//! go to a copy in RAM, [`Settings::commit`] writes all of them at once as
+//! one [`Journal`] record: after a power failure the settings are either
+//! the old or the new ones, never a mix. The journal moves through the
+//! region, so the sectors wear evenly.
+//!
+//! [`KEYS`] describes the numbers the examples read at startup, which the
+//! shell of `e07-usb-shell` sets by name.
+//!
+//! ```ignore
+//! // SAFETY: loaded once, see settings::load
+//! let settings = unsafe { settings::load() }.unwrap();
+//! let baud = settings.number(&settings::UART_BAUD).unwrap_or(115_200);
+//! ```
+
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::flash;
+use crate::flash::journal::MAX_RECORD;
+use crate::flash::Flash;
+use crate::flash::Journal;
+
+/// The settings in the `SETTINGS` region of `memory.x`.
+///
+/// # Safety
+///
+/// Call it once: only one `Settings` may use the region. `memory.x`
+/// reserves the region for the settings, sector aligned and apart from
+/// the firmware, as [`flash::RomFlash::new`] requires.
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub unsafe fn load() -> Result<Settings<flash::RomFlash>, flash::Error> {
+    Settings::load(flash::RomFlash::new(flash::settings_region()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key(pub u8);
+
+/// Describes a number setting for the shell.
+pub struct KeyInfo {
+    pub key: Key,
+    pub name: &'static str,
+    /// The values that make sense, others are ignored.
+    pub range: RangeInclusive<u32>,
+    /// Shown in hexadecimal.
+    pub hex: bool,
+    pub help: &'static str,
+}
+
+pub const UART_BAUD: KeyInfo = KeyInfo {
+    key: Key(1),
+    name: "uart.baud",
+    range: 300..=921_600,
+    hex: false,
+    help: "UART baud rate",
+};
+
+pub const LED_BRIGHTNESS: KeyInfo = KeyInfo {
+    key: Key(2),
+    name: "led.brightness",
+    range: 1..=255,
+    hex: false,
+    help: "brightness of the WS2812 LEDs",
+};
+
+pub const USB_VENDOR_ID: KeyInfo = KeyInfo {
+    key: Key(3),
+    name: "usb.vid",
+    range: 1..=0xffff,
+    hex: true,
+    help: "USB vendor ID",
+};
+
+pub const USB_PRODUCT_ID: KeyInfo = KeyInfo {
+    key: Key(4),
+    name: "usb.pid",
+    range: 1..=0xffff,
+    hex: true,
+    help: "USB product ID",
+};
+
+pub const MODBUS_ADDRESS: KeyInfo = KeyInfo {
+    key: Key(5),
+    name: "modbus.addr",
+    range: 1..=247,
+    hex: false,
+    help: "Modbus RTU server address",
+};
+
+/// All settings the shell knows of.
+pub const KEYS: &[KeyInfo] = &[
+    UART_BAUD,
+    LED_BRIGHTNESS,
+    USB_VENDOR_ID,
+    USB_PRODUCT_ID,
+    MODBUS_ADDRESS,
+];
+
+/// Looks a setting up by name.
+pub fn find(name: &str) -> Option<&'static KeyInfo> {
+    KEYS.iter().find(|info| info.name == name)
+}
+
+/// The settings do not fit in a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+impl fmt::Display for Full {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no room left for the settings")
+    }
+}
+
+/// The settings, as `key, length, value` entries in a journal record.
+pub struct Settings<F: Flash> {
+    journal: Journal<F>,
+    data: [u8; MAX_RECORD],
+    len: usize,
+    dirty: bool,
+}
+
+impl<F: Flash> Settings<F> {
+    /// Reads the committed settings. The region must be two sectors or
+    /// more.
+    pub fn load(flash: F) -> Result<Self, flash::Error> {
+        let mut journal = Journal::new(flash);
+        let mut data = [0u8; MAX_RECORD];
+        let len = journal.read(&mut data)?.unwrap_or(0);
+        let mut settings = Self {
+            journal,
+            data,
+            len,
+            dirty: false,
+        };
+        // Only a record of another format gets here past the CRC
+        if settings
+            .entries()
+            .map(|(_, _, value)| value.len() + 2)
+            .sum::<usize>()
+            != len
+        {
+            settings.len = 0;
+        }
+        Ok(settings)
+    }
+
+    pub fn get(&self, key: Key) -> Option<&[u8]> {
+        self.entries()
+            .find(|&(_, entry_key, _)| entry_key == key)
+            .map(|(_, _, value)| value)
+    }
+
+    /// Changes or adds a setting, until the next [`commit`](Self::commit).
+    pub fn set(&mut self, key: Key, value: &[u8]) -> Result<(), Full> {
+        let old_len = match self.get(key) {
+            Some(old) if old == value => return Ok(()),
+            Some(old) => old.len() + 2,
+            None => 0,
+        };
+        if self.len - old_len + value.len() + 2 > MAX_RECORD {
+            return Err(Full);
+        }
+        self.remove(key);
+        self.data[self.len] = key.0;
+        self.data[self.len + 1] = value.len() as u8;
+        self.data[self.len + 2..self.len + 2 + value.len()].copy_from_slice(value);
+        self.len += value.len() + 2;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Removes a setting, until the next [`commit`](Self::commit). Returns
+    /// whether it was there.
+    pub fn remove(&mut self, key: Key) -> bool {
+        let Some((offset, _, value)) = self.entries().find(|&(_, entry_key, _)| entry_key == key)
+        else {
+            return false;
+        };
+        let end = offset + value.len() + 2;
+        self.data.copy_within(end..self.len, offset);
+        self.len -= end - offset;
+        self.dirty = true;
+        true
+    }
+
+    pub fn get_u32(&self, key: Key) -> Option<u32> {
+        Some(u32::from_le_bytes(self.get(key)?.try_into().ok()?))
+    }
+
+    pub fn set_u32(&mut self, key: Key, value: u32) -> Result<(), Full> {
+        self.set(key, &value.to_le_bytes())
+    }
+
+    /// The value of a number setting, `None` if it is not set or out of
+    /// its range.
+    pub fn number(&self, info: &KeyInfo) -> Option<u32> {
+        self.get_u32(info.key)
+            .filter(|value| info.range.contains(value))
+    }
+
+    /// The settings in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &[u8])> {
+        self.entries().map(|(_, key, value)| (key, value))
+    }
+
+    /// Whether there are changes to commit.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Writes the changes to the flash, all or none of them. Does not
+    /// touch the flash without changes.
+    pub fn commit(&mut self) -> Result<(), flash::Error> {
+        if self.dirty {
+            self.journal.write(&self.data[..self.len])?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// The offset, the key and the value of the entries, up to the first
+    /// one that does not fit.
+    fn entries(&self) -> impl Iterator<Item = (usize, Key, &[u8])> {
+        let data = &self.data[..self.len];
+        let mut offset = 0;
+        core::iter::from_fn(move || {
+            let (&key, &len) = (data.get(offset)?, data.get(offset + 1)?);
+            let value = data.get(offset + 2..offset + 2 + usize::from(len))?;
+            let entry = (offset, Key(key), value);
+            offset += value.len() + 2;
+            Some(entry)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::SimFlash;
+    use crate::flash::PAGE_SIZE;
+
+    const NAME: Key = Key(0x80);
+
+    #[test]
+    fn commits_the_changes() {
+        let mut settings = Settings::load(SimFlash::new(4)).unwrap();
+        assert_eq!(settings.iter().count(), 0);
+
+        settings.set_u32(UART_BAUD.key, 9600).unwrap();
+        settings.set(NAME, b"pico").unwrap();
+        assert!(settings.is_dirty());
+        settings.commit().unwrap();
+        assert!(!settings.is_dirty());
+
+        // Uncommitted changes are lost
+        settings.set_u32(UART_BAUD.key, 19200).unwrap();
+        let settings = Settings::load(settings.journal.into_inner()).unwrap();
+        assert_eq!(settings.number(&UART_BAUD), Some(9600));
+        assert_eq!(settings.get(NAME), Some(&b"pico"[..]));
+        assert_eq!(settings.get(LED_BRIGHTNESS.key), None);
+    }
+
+    #[test]
+    fn replaces_and_removes() {
+        let mut settings = Settings::load(SimFlash::new(2)).unwrap();
+        settings.set(NAME, b"pico").unwrap();
+        settings.set_u32(USB_PRODUCT_ID.key, 0x27df).unwrap();
+        settings.set(NAME, b"bites").unwrap();
+        assert_eq!(settings.get(NAME), Some(&b"bites"[..]));
+        assert_eq!(settings.get_u32(USB_PRODUCT_ID.key), Some(0x27df));
+
+        assert!(settings.remove(NAME));
+        assert!(!settings.remove(NAME));
+        assert_eq!(
+            settings.iter().collect::<Vec<_>>(),
+            [(USB_PRODUCT_ID.key, &0x27dfu32.to_le_bytes()[..])]
+        );
+
+        // Out of range or of the wrong size
+        settings.set_u32(LED_BRIGHTNESS.key, 0).unwrap();
+        settings.set(UART_BAUD.key, &[1, 2]).unwrap();
+        assert_eq!(settings.number(&LED_BRIGHTNESS), None);
+        assert_eq!(settings.number(&UART_BAUD), None);
+    }
+
+    #[test]
+    fn keeps_the_old_value_when_full() {
+        let mut settings = Settings::load(SimFlash::new(2)).unwrap();
+        settings.set(NAME, &[0; 200]).unwrap();
+        settings.set(Key(1), &[1; 30]).unwrap();
+        assert_eq!(settings.set(Key(2), &[2; 20]), Err(Full));
+        assert_eq!(settings.set(NAME, &[0; 220]), Err(Full));
+        assert_eq!(settings.get(NAME), Some(&[0; 200][..]));
+        settings.set(NAME, &[0; 206]).unwrap();
+    }
+
+    #[test]
+    fn commits_all_or_nothing() {
+        let mut settings = Settings::load(SimFlash::new(2)).unwrap();
+        settings.set_u32(UART_BAUD.key, 9600).unwrap();
+        settings.set_u32(LED_BRIGHTNESS.key, 4).unwrap();
+        settings.commit().unwrap();
+        let old = settings.journal.into_inner().data;
+
+        let mut settings = Settings::load(SimFlash {
+            data: old.clone(),
+            erases: vec![0; 2],
+        })
+        .unwrap();
+        settings.set_u32(UART_BAUD.key, 115_200).unwrap();
+        settings.set_u32(LED_BRIGHTNESS.key, 32).unwrap();
+        settings.commit().unwrap();
+        let new = settings.journal.into_inner().data;
+
+        // Power lost at every step of programming the second record
+        let mut committed = false;
+        for cut in (0..=PAGE_SIZE).step_by(4) {
+            let mut data = old.clone();
+            data[PAGE_SIZE..PAGE_SIZE + cut].copy_from_slice(&new[PAGE_SIZE..PAGE_SIZE + cut]);
+            let settings = Settings::load(SimFlash {
+                data,
+                erases: vec![0; 2],
+            })
+            .unwrap();
+            match (
+                settings.number(&UART_BAUD),
+                settings.number(&LED_BRIGHTNESS),
+            ) {
+                (Some(9600), Some(4)) => assert!(!committed),
+                (Some(115_200), Some(32)) => committed = true,
+                values => panic!("mixed settings {values:?}"),
+            }
+        }
+        assert!(committed);
+    }
+
+    #[test]
+    fn commits_only_changes() {
+        let mut settings = Settings::load(SimFlash::new(2)).unwrap();
+        settings.set_u32(UART_BAUD.key, 9600).unwrap();
+        settings.commit().unwrap();
+        let written = settings.journal.into_inner();
+
+        let mut settings = Settings::load(written).unwrap();
+        settings.set_u32(UART_BAUD.key, 9600).unwrap();
+        assert!(!settings.is_dirty());
+        settings.commit().unwrap();
+        let flash = settings.journal.into_inner();
+        assert!(flash.data[PAGE_SIZE..].iter().all(|&b| b == 0xff));
+    }
+
+    #[test]
+    fn names_the_keys() {
+        assert_eq!(
+            find("usb.pid").map(|info| info.key),
+            Some(USB_PRODUCT_ID.key)
+        );
+        assert!(find("usb").is_none());
+        for (i, info) in KEYS.iter().enumerate() {
+            assert!(KEYS[..i]
+                .iter()
+                .all(|other| other.key != info.key && other.name != info.name));
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! A small command shell, independent of the transport.
+//!
+//! [`Editor`] turns the bytes typed in a terminal into lines, [`parse`]
+//! turns a line into a [`Command`] described in the [`COMMANDS`] table.
+//! Executing the command is up to the firmware.
+
+mod command;
+mod editor;
+
+pub use command::parse;
+pub use command::Command;
+pub use command::CommandInfo;
+pub use command::Error;
+pub use command::Led;
+pub use command::COMMANDS;
+pub use editor::Editor;
@@ -0,0 +1,260 @@
+//! The command table and the parser.
+
+use core::fmt;
+
+use crate::settings;
+use crate::settings::Key;
+use crate::uart;
+
+/// Describes a command for the help and the completion.
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+/// All commands the shell understands.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "help",
+        usage: "help",
+        help: "list the commands",
+    },
+    CommandInfo {
+        name: "info",
+        usage: "info",
+        help: "show the firmware and boot ROM information",
+    },
+    CommandInfo {
+        name: "led",
+        usage: "led on|off|blink <ms>",
+        help: "control the on-board LED",
+    },
+    CommandInfo {
+        name: "uart",
+        usage: "uart [<setting> <value>]",
+        help: "show the UART1 settings and errors, or change baud <n>, format 8N1, flow on|off",
+    },
+    CommandInfo {
+        name: "settings",
+        usage: "settings",
+        help: "list the settings kept in the flash",
+    },
+    CommandInfo {
+        name: "set",
+        usage: "set <setting> <n>",
+        help: "change a setting, used from the next boot",
+    },
+    CommandInfo {
+        name: "unset",
+        usage: "unset <setting>",
+        help: "bring a setting back to its default",
+    },
+    CommandInfo {
+        name: "reboot",
+        usage: "reboot",
+        help: "restart the firmware",
+    },
+    CommandInfo {
+        name: "bootsel",
+        usage: "bootsel",
+        help: "restart into the USB bootloader",
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Led {
+    On,
+    Off,
+    /// Toggle every `period_ms` milliseconds.
+    Blink {
+        period_ms: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Info,
+    Led(Led),
+    Uart,
+    UartBaud(u32),
+    UartFormat(uart::DataBits, uart::Parity, uart::StopBits),
+    UartFlow(uart::FlowControl),
+    Settings,
+    Set(Key, u32),
+    Unset(Key),
+    Reboot,
+    Bootsel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Empty,
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+    TooManyArguments,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::Empty => "empty command",
+            Error::UnknownCommand => "unknown command, try `help`",
+            Error::MissingArgument => "missing argument",
+            Error::InvalidArgument => "invalid argument",
+            Error::TooManyArguments => "too many arguments",
+        })
+    }
+}
+
+/// Parses a line into a command, words are separated by spaces.
+pub fn parse(line: &str) -> Result<Command, Error> {
+    let mut words = line.split_ascii_whitespace();
+    let name = words.next().ok_or(Error::Empty)?;
+
+    let command = match name {
+        "help" => Command::Help,
+        "info" => Command::Info,
+        "led" => Command::Led(match words.next().ok_or(Error::MissingArgument)? {
+            "on" => Led::On,
+            "off" => Led::Off,
+            "blink" => Led::Blink {
+                period_ms: number(words.next())?,
+            },
+            _ => return Err(Error::InvalidArgument),
+        }),
+        "uart" => match words.next() {
+            None => Command::Uart,
+            Some("baud") => Command::UartBaud(number(words.next())?),
+            Some("format") => {
+                let mut config = uart::Config::default();
+                config
+                    .set_format(words.next().ok_or(Error::MissingArgument)?)
+                    .ok_or(Error::InvalidArgument)?;
+                Command::UartFormat(config.data_bits, config.parity, config.stop_bits)
+            }
+            Some("flow") => match words.next().ok_or(Error::MissingArgument)? {
+                "on" => Command::UartFlow(uart::FlowControl::RtsCts),
+                "off" => Command::UartFlow(uart::FlowControl::None),
+                _ => return Err(Error::InvalidArgument),
+            },
+            Some(_) => return Err(Error::InvalidArgument),
+        },
+        "settings" => Command::Settings,
+        "set" => {
+            let info = setting(words.next())?;
+            let value = number(words.next())?;
+            if !info.range.contains(&value) {
+                return Err(Error::InvalidArgument);
+            }
+            Command::Set(info.key, value)
+        }
+        "unset" => Command::Unset(setting(words.next())?.key),
+        "reboot" => Command::Reboot,
+        "bootsel" => Command::Bootsel,
+        _ => return Err(Error::UnknownCommand),
+    };
+
+    if words.next().is_some() {
+        return Err(Error::TooManyArguments);
+    }
+    Ok(command)
+}
+
+/// A positive number, decimal or hexadecimal with `0x`.
+fn number(word: Option<&str>) -> Result<u32, Error> {
+    let word = word.ok_or(Error::MissingArgument)?;
+    let parsed = match word.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => word.parse(),
+    };
+    match parsed {
+        Ok(0) | Err(_) => Err(Error::InvalidArgument),
+        Ok(n) => Ok(n),
+    }
+}
+
+fn setting(word: Option<&str>) -> Result<&'static settings::KeyInfo, Error> {
+    settings::find(word.ok_or(Error::MissingArgument)?).ok_or(Error::InvalidArgument)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("help"), Ok(Command::Help));
+        assert_eq!(parse("  info  "), Ok(Command::Info));
+        assert_eq!(parse("led on"), Ok(Command::Led(Led::On)));
+        assert_eq!(parse("led off"), Ok(Command::Led(Led::Off)));
+        assert_eq!(
+            parse("led blink 250"),
+            Ok(Command::Led(Led::Blink { period_ms: 250 }))
+        );
+        assert_eq!(parse("uart"), Ok(Command::Uart));
+        assert_eq!(parse("uart baud 9600"), Ok(Command::UartBaud(9600)));
+        assert_eq!(
+            parse("uart format 7e2"),
+            Ok(Command::UartFormat(
+                uart::DataBits::Seven,
+                uart::Parity::Even,
+                uart::StopBits::Two
+            ))
+        );
+        assert_eq!(
+            parse("uart flow on"),
+            Ok(Command::UartFlow(uart::FlowControl::RtsCts))
+        );
+        assert_eq!(parse("settings"), Ok(Command::Settings));
+        assert_eq!(
+            parse("set uart.baud 9600"),
+            Ok(Command::Set(settings::UART_BAUD.key, 9600))
+        );
+        assert_eq!(
+            parse("set usb.pid 0x27df"),
+            Ok(Command::Set(settings::USB_PRODUCT_ID.key, 0x27df))
+        );
+        assert_eq!(
+            parse("unset led.brightness"),
+            Ok(Command::Unset(settings::LED_BRIGHTNESS.key))
+        );
+        assert_eq!(parse("reboot"), Ok(Command::Reboot));
+        assert_eq!(parse("bootsel"), Ok(Command::Bootsel));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(parse(""), Err(Error::Empty));
+        assert_eq!(parse("   "), Err(Error::Empty));
+        assert_eq!(parse("blink"), Err(Error::UnknownCommand));
+        assert_eq!(parse("led"), Err(Error::MissingArgument));
+        assert_eq!(parse("led dim"), Err(Error::InvalidArgument));
+        assert_eq!(parse("led blink"), Err(Error::MissingArgument));
+        assert_eq!(parse("led blink fast"), Err(Error::InvalidArgument));
+        assert_eq!(parse("led blink 0"), Err(Error::InvalidArgument));
+        assert_eq!(parse("uart speed 9600"), Err(Error::InvalidArgument));
+        assert_eq!(parse("uart format 8N3"), Err(Error::InvalidArgument));
+        assert_eq!(parse("uart flow"), Err(Error::MissingArgument));
+        assert_eq!(parse("set"), Err(Error::MissingArgument));
+        assert_eq!(parse("set uart.speed 9600"), Err(Error::InvalidArgument));
+        assert_eq!(parse("set usb.vid 0x10000"), Err(Error::InvalidArgument));
+        assert_eq!(parse("set usb.pid 0xzz"), Err(Error::InvalidArgument));
+        assert_eq!(parse("reboot now"), Err(Error::TooManyArguments));
+    }
+
+    #[test]
+    fn table_covers_parser() {
+        for info in COMMANDS {
+            assert_ne!(
+                parse(info.name),
+                Err(Error::UnknownCommand),
+                "{}",
+                info.name
+            );
+            assert!(info.usage.starts_with(info.name));
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Line editing for a VT100-compatible terminal.
+
+use core::fmt::Write;
+
+use heapless::Deque;
+use heapless::Vec;
+
+use super::CommandInfo;
+
+enum Escape {
+    None,
+    /// Got `ESC`
+    Esc,
+    /// Got `ESC [`, waiting for the final byte
+    Csi,
+}
+
+/// Collects a line echoing it back, supports backspace, `Ctrl-C`, `Ctrl-U`,
+/// the history of the last `H` lines on the arrow keys and the command name
+/// completion on `Tab`.
+///
+/// The line holds up to `N` bytes, only printable ASCII is accepted.
+pub struct Editor<const N: usize, const H: usize> {
+    prompt: &'static str,
+    commands: &'static [CommandInfo],
+    line: Vec<u8, N>,
+    history: Deque<Vec<u8, N>, H>,
+    /// Position in the history while browsing it, `0` is the latest line
+    browsing: Option<usize>,
+    escape: Escape,
+    complete: bool,
+    last_cr: bool,
+}
+
+impl<const N: usize, const H: usize> Editor<N, H> {
+    pub const fn new(prompt: &'static str, commands: &'static [CommandInfo]) -> Self {
+        Self {
+            prompt,
+            commands,
+            line: Vec::new(),
+            history: Deque::new(),
+            browsing: None,
+            escape: Escape::None,
+            complete: false,
+            last_cr: false,
+        }
+    }
+
+    /// Prints the prompt, call once the output of the previous line is written.
+    pub fn prompt<W: Write>(&self, out: &mut W) {
+        out.write_str(self.prompt).ok();
+    }
+
+    /// Feeds one byte, writes the echo to `out`, returns the line once
+    /// `Enter` is pressed.
+    ///
+    /// Errors writing to `out` are ignored, the terminal is expected to
+    /// drop the output it cannot take.
+    pub fn feed<W: Write>(&mut self, byte: u8, out: &mut W) -> Option<&str> {
+        if self.complete {
+            self.line.clear();
+            self.complete = false;
+        }
+        let last_cr = core::mem::replace(&mut self.last_cr, byte == b'\r');
+
+        match self.escape {
+            Escape::Esc => {
+                self.escape = if byte == b'[' {
+                    Escape::Csi
+                } else {
+                    Escape::None
+                };
+                return None;
+            }
+            Escape::Csi => {
+                // Parameter and intermediate bytes precede the final byte
+                if (0x40..=0x7e).contains(&byte) {
+                    self.escape = Escape::None;
+                    match byte {
+                        b'A' => self.history_up(out),
+                        b'B' => self.history_down(out),
+                        _ => {}
+                    }
+                }
+                return None;
+            }
+            Escape::None => {}
+        }
+
+        match byte {
+            0x1b => self.escape = Escape::Esc,
+            // The second half of `\r\n`
+            b'\n' if last_cr => {}
+            b'\r' | b'\n' => {
+                out.write_str("\r\n").ok();
+                self.remember();
+                self.browsing = None;
+                self.complete = true;
+                // Only printable ASCII gets into the line
+                return core::str::from_utf8(&self.line).ok();
+            }
+            0x08 | 0x7f if !self.line.is_empty() => {
+                self.line.pop();
+                out.write_str("\x08 \x08").ok();
+            }
+            // Ctrl-C
+            0x03 => {
+                out.write_str("^C\r\n").ok();
+                self.line.clear();
+                self.browsing = None;
+                self.prompt(out);
+            }
+            // Ctrl-U
+            0x15 => {
+                self.line.clear();
+                self.redraw(out);
+            }
+            b'\t' => self.complete_command(out),
+            0x20..=0x7e if !self.line.is_full() => {
+                self.line.push(byte).ok();
+                out.write_char(byte as char).ok();
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn remember(&mut self) {
+        if self.line.is_empty() || self.history.front() == Some(&self.line) {
+            return;
+        }
+        if self.history.is_full() {
+            self.history.pop_back();
+        }
+        self.history.push_front(self.line.clone()).ok();
+    }
+
+    fn history_up<W: Write>(&mut self, out: &mut W) {
+        let next = self.browsing.map_or(0, |i| i + 1);
+        if let Some(entry) = self.history.iter().nth(next) {
+            self.line = entry.clone();
+            self.browsing = Some(next);
+            self.redraw(out);
+        }
+    }
+
+    fn history_down<W: Write>(&mut self, out: &mut W) {
+        match self.browsing {
+            None => {}
+            Some(0) => {
+                self.line.clear();
+                self.browsing = None;
+                self.redraw(out);
+            }
+            Some(i) => {
+                if let Some(entry) = self.history.iter().nth(i - 1) {
+                    self.line = entry.clone();
+                }
+                self.browsing = Some(i - 1);
+                self.redraw(out);
+            }
+        }
+    }
+
+    /// Completes the command name, the arguments are not completed.
+    fn complete_command<W: Write>(&mut self, out: &mut W) {
+        let Ok(prefix) = core::str::from_utf8(&self.line) else {
+            return;
+        };
+        if prefix.contains(' ') {
+            return;
+        }
+
+        let mut matches = self
+            .commands
+            .iter()
+            .map(|c| c.name)
+            .filter(|name| name.starts_with(prefix));
+        let Some(first) = matches.next() else {
+            // Bell
+            out.write_char('\x07').ok();
+            return;
+        };
+
+        // Longest common prefix of all matches
+        let mut common = first;
+        let mut unique = true;
+        for name in matches {
+            unique = false;
+            let len = common
+                .bytes()
+                .zip(name.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            common = &common[..len];
+        }
+
+        if common.len() > prefix.len() || unique {
+            let start = self.line.len();
+            let tail = if unique { " " } else { "" };
+            for &b in common.as_bytes()[start..].iter().chain(tail.as_bytes()) {
+                if self.line.push(b).is_err() {
+                    break;
+                }
+                out.write_char(b as char).ok();
+            }
+        } else {
+            out.write_str("\r\n").ok();
+            for name in self.commands.iter().map(|c| c.name) {
+                if name.starts_with(prefix) {
+                    out.write_str(name).ok();
+                    out.write_str("  ").ok();
+                }
+            }
+            out.write_str("\r\n").ok();
+            self.redraw(out);
+        }
+    }
+
+    /// Rewrites the current terminal line with the prompt and the line.
+    fn redraw<W: Write>(&self, out: &mut W) {
+        // Carriage return, then erase to the end of the line
+        out.write_str("\r\x1b[K").ok();
+        self.prompt(out);
+        for &b in &self.line {
+            out.write_char(b as char).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::COMMANDS;
+
+    /// Feeds the bytes, returns the lines and the echo.
+    fn feed<const N: usize, const H: usize>(
+        editor: &mut Editor<N, H>,
+        bytes: &[u8],
+    ) -> (std::vec::Vec<String>, String) {
+        let mut out = String::new();
+        let mut lines = std::vec::Vec::new();
+        for &b in bytes {
+            if let Some(line) = editor.feed(b, &mut out) {
+                lines.push(line.to_string());
+            }
+        }
+        (lines, out)
+    }
+
+    fn editor() -> Editor<32, 4> {
+        Editor::new("> ", COMMANDS)
+    }
+
+    #[test]
+    fn echoes_and_returns_lines() {
+        let mut editor = editor();
+        let (lines, out) = feed(&mut editor, b"led on\r\ninfo\r");
+
+        assert_eq!(lines, ["led on", "info"]);
+        assert_eq!(out, "led on\r\ninfo\r\n");
+    }
+
+    #[test]
+    fn returns_empty_lines() {
+        let mut editor = editor();
+        let (lines, _) = feed(&mut editor, b"\r\n\r\n");
+
+        assert_eq!(lines, ["", ""]);
+    }
+
+    #[test]
+    fn backspace_erases() {
+        let mut editor = editor();
+        let (lines, out) = feed(
+            &mut editor,
+            b"ledd\x7f on\x08\x08\x08\x08\x08\x08\x08info\r",
+        );
+
+        assert_eq!(lines, ["info"]);
+        assert!(out.starts_with("ledd\x08 \x08 on"));
+    }
+
+    #[test]
+    fn ignores_control_and_non_ascii() {
+        let mut editor = editor();
+        let (lines, _) = feed(&mut editor, b"in\x00\x01fo\xc3\xa9\r");
+
+        assert_eq!(lines, ["info"]);
+    }
+
+    #[test]
+    fn ctrl_c_and_ctrl_u_discard_the_line() {
+        let mut editor = editor();
+        let (lines, out) = feed(&mut editor, b"reboot\x03info\x15help\r");
+
+        assert_eq!(lines, ["help"]);
+        assert!(out.contains("^C\r\n> "));
+    }
+
+    #[test]
+    fn drops_bytes_past_capacity() {
+        let mut editor = Editor::<4, 1>::new("> ", COMMANDS);
+        let (lines, _) = feed(&mut editor, b"abcdefgh\r");
+
+        assert_eq!(lines, ["abcd"]);
+    }
+
+    #[test]
+    fn browses_history() {
+        let mut editor = editor();
+        feed(&mut editor, b"info\rled on\rled on\r");
+
+        // Up, up, up stops at the oldest entry, duplicates are not remembered
+        let (lines, _) = feed(&mut editor, b"\x1b[A\x1b[A\x1b[A\r");
+        assert_eq!(lines, ["info"]);
+
+        // Up, up, down
+        let (lines, _) = feed(&mut editor, b"\x1b[A\x1b[A\x1b[B\r");
+        assert_eq!(lines, ["info"]);
+
+        // Down past the latest entry gives an empty line
+        let (lines, out) = feed(&mut editor, b"\x1b[A\x1b[B\r");
+        assert_eq!(lines, [""]);
+        assert!(out.contains("\r\x1b[K> info"));
+        assert!(out.ends_with("\r\x1b[K> \r\n"));
+    }
+
+    #[test]
+    fn history_keeps_latest_lines() {
+        let mut editor = Editor::<8, 2>::new("> ", COMMANDS);
+        feed(&mut editor, b"one\rtwo\rthree\r");
+
+        let (lines, _) = feed(&mut editor, b"\x1b[A\x1b[A\x1b[A\r");
+        assert_eq!(lines, ["two"]);
+    }
+
+    #[test]
+    fn completes_unique_command() {
+        let mut editor = editor();
+        let (lines, out) = feed(&mut editor, b"re\tbo\r");
+
+        assert_eq!(lines, ["reboot bo"]);
+        assert!(out.starts_with("reboot "));
+    }
+
+    #[test]
+    fn lists_ambiguous_commands() {
+        const COMMANDS: &[CommandInfo] = &[
+            CommandInfo {
+                name: "load",
+                usage: "",
+                help: "",
+            },
+            CommandInfo {
+                name: "lock",
+                usage: "",
+                help: "",
+            },
+            CommandInfo {
+                name: "log",
+                usage: "",
+                help: "",
+            },
+        ];
+        let mut editor = Editor::<16, 1>::new("> ", COMMANDS);
+
+        // `l` extends to the common prefix `lo`, the next tab lists
+        let (_, out) = feed(&mut editor, b"l\t");
+        assert_eq!(out, "lo");
+
+        let (lines, out) = feed(&mut editor, b"\tc\t\r");
+        assert_eq!(out, "\r\nload  lock  log  \r\n\r\x1b[K> lock \r\n");
+        assert_eq!(lines, ["lock "]);
+    }
+
+    #[test]
+    fn rings_bell_on_no_match() {
+        let mut editor = editor();
+        let (_, out) = feed(&mut editor, b"x\t");
+
+        assert_eq!(out, "x\x07");
+    }
+
+    #[test]
+    fn does_not_complete_arguments() {
+        let mut editor = editor();
+        let (lines, _) = feed(&mut editor, b"led o\t\r");
+
+        assert_eq!(lines, ["led o"]);
+    }
+}
@@ -0,0 +1,479 @@
+//! UART line settings and the interrupt-driven UART.
+//!
+//! [`Config`] holds the line settings in a form the shell and the settings
+//! store can handle. On the target the UART is split in two halves
+//! connected by a pair of lock-free single-producer single-consumer queues:
+//!
+//! * [`Driver`] lives in the `UARTx_IRQ` handler, it drains the RX FIFO into
+//!   the RX queue, refills the TX FIFO from the TX queue and counts the
+//!   receive errors; [`Driver::configure`] changes the settings while
+//!   running;
+//! * [`Port`] is used by the application to read and write bytes, writing
+//!   pends the interrupt so the driver picks the data up.
+//!
+//! ```ignore
+//! let rx_queue = cortex_m::singleton!(: Queue<u8, 256> = Queue::new()).unwrap();
+//! let tx_queue = cortex_m::singleton!(: Queue<u8, 256> = Queue::new()).unwrap();
+//! let (driver, port) = uart::split(
+//!     uart,
+//!     uart::Config::new(115_200),
+//!     clocks.peripheral_clock.freq(),
+//!     pac::Interrupt::UART1_IRQ,
+//!     rx_queue,
+//!     tx_queue,
+//! )
+//! .unwrap();
+//! ```
+//!
+//! [`dma`] has the same halves for streams of a megabaud and more, where an
+//! interrupt every few bytes costs too much: DMA channels move the data and
+//! the interrupts only come at the end of a buffer or of a burst.
+//!
+//! [`pio`] adds UARTs beyond the two of the chip, each half on a PIO state
+//! machine.
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub mod dma;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod driver;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub mod pio;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use driver::split;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use driver::Driver;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use driver::Port;
+
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowControl {
+    None,
+    /// The peer stops the transmission with CTS, the UART stops the peer
+    /// with RTS when its RX FIFO fills up.
+    RtsCts,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The baud rate divisor is out of range for the UART clock.
+    BaudRate,
+    /// Flow control without the CTS and RTS pins.
+    NoFlowControlPins,
+    /// A frame format or flow control the UART cannot do.
+    Format,
+    /// The transmitter still sends with the old settings, try again.
+    Busy,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigError::BaudRate => "baud rate out of range",
+            ConfigError::NoFlowControlPins => "no CTS and RTS pins",
+            ConfigError::Format => "frame format not supported",
+            ConfigError::Busy => "transmitter busy",
+        })
+    }
+}
+
+/// The line settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub baud: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(115_200)
+    }
+}
+
+impl Config {
+    /// 8N1 without flow control.
+    pub const fn new(baud: u32) -> Self {
+        Self {
+            baud,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+
+    /// The integer and the fractional (in 1/64) parts of the baud rate
+    /// divisor, rounded as the SDK does, for the UART clock at `clock_hz`.
+    pub fn divisors(&self, clock_hz: u32) -> Result<(u16, u8), ConfigError> {
+        // The UART samples at 16 times the baud rate, the divisor has
+        // 7 fractional bits here
+        let divisor = clock_hz
+            .checked_mul(8)
+            .and_then(|clock| clock.checked_div(self.baud))
+            .ok_or(ConfigError::BaudRate)?;
+        match (divisor >> 7, (divisor & 0x7f).div_ceil(2)) {
+            (0, _) | (65535.., _) => Err(ConfigError::BaudRate),
+            (integer, fraction) => Ok((integer as u16, fraction as u8)),
+        }
+    }
+
+    /// The integer and the fractional (in 1/256) parts of the clock divisor
+    /// of a [`pio`] state machine at `clock_hz`, which takes 8 cycles per
+    /// bit.
+    pub fn pio_divisors(&self, clock_hz: u32) -> Result<(u16, u8), ConfigError> {
+        let baud = u64::from(self.baud);
+        let divisor = (u64::from(clock_hz) * 32 + baud / 2)
+            .checked_div(baud)
+            .ok_or(ConfigError::BaudRate)?;
+        match (divisor >> 8, divisor & 0xff) {
+            (0, _) | (65536.., _) => Err(ConfigError::BaudRate),
+            (integer, fraction) => Ok((integer as u16, fraction as u8)),
+        }
+    }
+
+    /// The bits on the line per character, the start, the parity and the
+    /// stop bits included.
+    pub fn character_bits(&self) -> u32 {
+        let data_bits = match self.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        };
+        let parity_bits = match self.parity {
+            Parity::None => 0,
+            Parity::Even | Parity::Odd => 1,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        1 + data_bits + parity_bits + stop_bits
+    }
+
+    /// Changes the frame format from the usual notation, such as `8N1`.
+    pub fn set_format(&mut self, format: &str) -> Option<()> {
+        let &[data_bits, parity, stop_bits] = format.as_bytes() else {
+            return None;
+        };
+        let data_bits = match data_bits {
+            b'5' => DataBits::Five,
+            b'6' => DataBits::Six,
+            b'7' => DataBits::Seven,
+            b'8' => DataBits::Eight,
+            _ => return None,
+        };
+        let parity = match parity.to_ascii_uppercase() {
+            b'N' => Parity::None,
+            b'E' => Parity::Even,
+            b'O' => Parity::Odd,
+            _ => return None,
+        };
+        let stop_bits = match stop_bits {
+            b'1' => StopBits::One,
+            b'2' => StopBits::Two,
+            _ => return None,
+        };
+        (self.data_bits, self.parity, self.stop_bits) = (data_bits, parity, stop_bits);
+        Some(())
+    }
+
+    /// The frame format in the usual notation, such as `8N1`.
+    pub fn format(&self) -> [u8; 3] {
+        let data_bits = match self.data_bits {
+            DataBits::Five => b'5',
+            DataBits::Six => b'6',
+            DataBits::Seven => b'7',
+            DataBits::Eight => b'8',
+        };
+        let parity = match self.parity {
+            Parity::None => b'N',
+            Parity::Even => b'E',
+            Parity::Odd => b'O',
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => b'1',
+            StopBits::Two => b'2',
+        };
+        [data_bits, parity, stop_bits]
+    }
+}
+
+impl fmt::Display for Config {
+    /// As in `115200 8N1 rts/cts`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = self.format();
+        // Always ASCII
+        let format = core::str::from_utf8(&format).unwrap();
+        write!(f, "{} {format}", self.baud)?;
+        if self.flow_control == FlowControl::RtsCts {
+            f.write_str(" rts/cts")?;
+        }
+        Ok(())
+    }
+}
+
+/// The receive errors since the start.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    /// A character without a valid stop bit, likely at the wrong baud
+    /// rate.
+    pub framing: u32,
+    pub parity: u32,
+    /// Characters lost because the RX FIFO was full.
+    pub overrun: u32,
+    /// The line held low for longer than a character.
+    pub breaks: u32,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u32 {
+        self.framing
+            .wrapping_add(self.parity)
+            .wrapping_add(self.overrun)
+            .wrapping_add(self.breaks)
+    }
+}
+
+impl fmt::Display for ErrorCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "framing {}, parity {}, overrun {}, break {}",
+            self.framing, self.parity, self.overrun, self.breaks
+        )
+    }
+}
+
+/// Follows the bytes a DMA channel writes around a ring of `N` bytes.
+///
+/// The channel only tells how many bytes it has written since the start,
+/// as a wrapping count; `N` must divide 2^32 so the count maps to the same
+/// place in the ring after wrapping around.
+pub struct RingReader<const N: usize> {
+    read: u32,
+    lost: u32,
+}
+
+impl<const N: usize> Default for RingReader<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> RingReader<N> {
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two());
+        Self { read: 0, lost: 0 }
+    }
+
+    /// Copies the bytes received so far to `buf`, returns how many were
+    /// copied.
+    ///
+    /// `written` is the count of bytes the channel wrote, `ring` reads the
+    /// byte at an offset in the ring. Bytes written over before they were
+    /// read are skipped and counted in [`lost`](Self::lost).
+    pub fn read(
+        &mut self,
+        written: impl Fn() -> u32,
+        ring: impl Fn(usize) -> u8,
+        buf: &mut [u8],
+    ) -> usize {
+        let len = N as u32;
+        let available = written().wrapping_sub(self.read);
+        if available > len {
+            self.skip(available - len);
+        }
+        let count = available.min(len).min(buf.len() as u32) as usize;
+        for (i, slot) in buf[..count].iter_mut().enumerate() {
+            *slot = ring((self.read as usize).wrapping_add(i) % N);
+        }
+
+        // The channel kept writing meanwhile, and may have lapped the
+        // first bytes copied
+        let overwritten =
+            (written().wrapping_sub(self.read).saturating_sub(len) as usize).min(count);
+        buf.copy_within(overwritten..count, 0);
+        self.skip(overwritten as u32);
+        let count = count - overwritten;
+        self.read = self.read.wrapping_add(count as u32);
+        count
+    }
+
+    /// Bytes written over before they were read.
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    fn skip(&mut self, count: u32) {
+        self.read = self.read.wrapping_add(count);
+        self.lost = self.lost.wrapping_add(count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_divisors() {
+        let config = Config::new(115_200);
+        // 125 MHz / (16 * 115200) = 67.817
+        assert_eq!(config.divisors(125_000_000), Ok((67, 52)));
+        assert_eq!(Config::new(9600).divisors(125_000_000), Ok((813, 51)));
+        assert_eq!(
+            Config::new(10_000_000).divisors(125_000_000),
+            Err(ConfigError::BaudRate)
+        );
+        assert_eq!(
+            Config::new(100).divisors(125_000_000),
+            Err(ConfigError::BaudRate)
+        );
+        assert_eq!(
+            Config::new(0).divisors(125_000_000),
+            Err(ConfigError::BaudRate)
+        );
+    }
+
+    #[test]
+    fn computes_pio_divisors() {
+        // 125 MHz / (8 * 115200) = 135.634
+        assert_eq!(
+            Config::new(115_200).pio_divisors(125_000_000),
+            Ok((135, 162))
+        );
+        assert_eq!(Config::new(9600).pio_divisors(125_000_000), Ok((1627, 155)));
+        assert_eq!(
+            Config::new(15_625_000).pio_divisors(125_000_000),
+            Ok((1, 0))
+        );
+        for baud in [16_000_000, 200, 0] {
+            assert_eq!(
+                Config::new(baud).pio_divisors(125_000_000),
+                Err(ConfigError::BaudRate),
+                "{baud}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_formats() {
+        let mut config = Config::default();
+        assert_eq!(config.set_format("7e2"), Some(()));
+        assert_eq!(
+            (config.data_bits, config.parity, config.stop_bits),
+            (DataBits::Seven, Parity::Even, StopBits::Two)
+        );
+        assert_eq!(&config.format(), b"7E2");
+
+        // Unchanged by bad formats
+        for format in ["9N1", "8X1", "8N3", "8N", "8N11", ""] {
+            assert_eq!(config.set_format(format), None, "{format}");
+        }
+        assert_eq!(&config.format(), b"7E2");
+
+        config.flow_control = FlowControl::RtsCts;
+        assert_eq!(config.to_string(), "115200 7E2 rts/cts");
+        assert_eq!(Config::new(9600).to_string(), "9600 8N1");
+    }
+
+    /// A ring of 8 bytes the "channel" writes `count` bytes to.
+    struct Ring {
+        data: [u8; 8],
+        written: u32,
+    }
+
+    impl Ring {
+        fn write(&mut self, count: u32) {
+            for _ in 0..count {
+                self.data[self.written as usize % 8] = self.written as u8;
+                self.written = self.written.wrapping_add(1);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_around_the_ring() {
+        let mut ring = Ring {
+            data: [0; 8],
+            written: 0,
+        };
+        let mut reader = RingReader::<8>::new();
+        let mut buf = [0u8; 16];
+        let read = |ring: &Ring, reader: &mut RingReader<8>, buf: &mut [u8]| {
+            reader.read(|| ring.written, |i| ring.data[i], buf)
+        };
+
+        assert_eq!(read(&ring, &mut reader, &mut buf), 0);
+        ring.write(5);
+        assert_eq!(read(&ring, &mut reader, &mut buf[..3]), 3);
+        assert_eq!(buf[..3], [0, 1, 2]);
+        ring.write(6);
+        assert_eq!(read(&ring, &mut reader, &mut buf), 8);
+        assert_eq!(buf[..8], [3, 4, 5, 6, 7, 8, 9, 10]);
+
+        // Lapped by the channel
+        ring.write(11);
+        assert_eq!(read(&ring, &mut reader, &mut buf), 8);
+        assert_eq!(buf[..8], [14, 15, 16, 17, 18, 19, 20, 21]);
+        assert_eq!(reader.lost(), 3);
+
+        // Across the wrap of the count
+        ring.written = u32::MAX - 2;
+        reader.read = ring.written;
+        ring.write(6);
+        assert_eq!(read(&ring, &mut reader, &mut buf), 6);
+        assert_eq!(buf[..6], [253, 254, 255, 0, 1, 2]);
+    }
+
+    #[test]
+    fn drops_the_bytes_written_over_while_copying() {
+        let data = core::cell::RefCell::new(Ring {
+            data: [0; 8],
+            written: 0,
+        });
+        data.borrow_mut().write(7);
+        let mut reader = RingReader::<8>::new();
+        let mut buf = [0u8; 8];
+        // The channel writes 3 more bytes once the copy started
+        let count = reader.read(
+            || data.borrow().written,
+            |i| {
+                if i == 0 {
+                    data.borrow_mut().write(3);
+                }
+                data.borrow().data[i]
+            },
+            &mut buf,
+        );
+        // Byte 0 and 1 were written over by 8 and 9
+        assert_eq!(buf[..count], [2, 3, 4, 5, 6]);
+        assert_eq!(reader.lost(), 2);
+    }
+}
@@ -0,0 +1,548 @@
+//! The UART with DMA.
+//!
+//! The application queues whole buffers, the TX channel sends them one
+//! after the other straight out of the queue and the `DMA_IRQ_0` handler
+//! starts the next one at the end of each. The RX channel writes around a
+//! ring of [`RX_RING_LEN`] bytes for as long as the UART runs, [`Port::read`]
+//! copies out whatever came since the last call.
+//!
+//! The UART requests a transfer for every byte in the RX FIFO, so the FIFO
+//! never fills up to its watermark nor holds a byte long enough for the
+//! receive timeout. The line is idle, which is where a packet usually ends,
+//! once the count of received bytes stood still for 32 bit periods of a
+//! timer, see [`Port::take_idle`]. The `UARTx_IRQ` handler only counts the
+//! receive errors.
+//!
+//! ```ignore
+//! let buffers = cortex_m::singleton!(: Buffers<256, 4> = Buffers::new()).unwrap();
+//! let channels = pac.DMA.split(&mut pac.RESETS);
+//! let (driver, port) = uart::dma::split(
+//!     uart,
+//!     uart::Config::new(1_000_000),
+//!     clocks.peripheral_clock.freq(),
+//!     (channels.ch0, channels.ch1),
+//!     buffers,
+//! )
+//! .unwrap();
+//! ```
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering;
+
+use defmt as log;
+use fugit::HertzU32;
+use heapless::spsc::Consumer;
+use heapless::spsc::Producer;
+use heapless::spsc::Queue;
+use heapless::Vec;
+
+use rp2040_hal as hal;
+
+use hal::dma::Channel;
+use hal::dma::ChannelIndex;
+use hal::pac;
+use hal::uart::Disabled;
+use hal::uart::Enabled;
+use hal::uart::UartDevice;
+use hal::uart::UartPeripheral;
+use hal::uart::ValidUartPinout;
+
+use super::driver::check;
+use super::driver::enable;
+use super::driver::registers;
+use super::Config;
+use super::ConfigError;
+use super::ErrorCounts;
+use super::RingReader;
+
+/// The size of the receive ring, 10 ms at 1 Mbaud.
+pub const RX_RING_LEN: usize = 1024;
+
+/// The RX channel wraps the lower 10 bits of the address.
+const RING_BITS: u8 = 10;
+
+/// The DREQ of UART0 TX, RX follows and UART1 comes next.
+const DREQ_UART0_TX: u8 = 20;
+
+/// Aligned to its size, as the DMA wraps the address.
+#[repr(C, align(1024))]
+struct Ring(UnsafeCell<[u8; RX_RING_LEN]>);
+
+// Written by the RX channel and by the driver only where the channel is
+// stopped, read by the port with volatile reads.
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn address(&self) -> u32 {
+        self.0.get() as u32
+    }
+
+    fn read(&self, offset: usize) -> u8 {
+        unsafe {
+            self.0
+                .get()
+                .cast::<u8>()
+                .add(offset % RX_RING_LEN)
+                .read_volatile()
+        }
+    }
+
+    /// Only while the RX channel is stopped.
+    unsafe fn write(&self, offset: usize, byte: u8) {
+        self.0
+            .get()
+            .cast::<u8>()
+            .add(offset % RX_RING_LEN)
+            .write_volatile(byte);
+    }
+}
+
+/// The memory shared by the halves: the receive ring and a queue of `Q`
+/// transmit buffers of `N` bytes.
+pub struct Buffers<const N: usize, const Q: usize> {
+    ring: Ring,
+    tx: Queue<Vec<u8, N>, Q>,
+    /// The bytes received before the RX channel was last started.
+    received: AtomicU32,
+    /// 32 bit periods at the baud rate of the driver.
+    idle_us: AtomicU32,
+}
+
+impl<const N: usize, const Q: usize> Default for Buffers<N, Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const Q: usize> Buffers<N, Q> {
+    pub const fn new() -> Self {
+        Self {
+            ring: Ring(UnsafeCell::new([0; RX_RING_LEN])),
+            tx: Queue::new(),
+            received: AtomicU32::new(0),
+            idle_us: AtomicU32::new(0),
+        }
+    }
+}
+
+/// Enables the UART with `config` for the UART clock at `frequency`,
+/// starts the RX channel and splits the UART into the interrupt and the
+/// application halves.
+///
+/// The caller still has to unmask `DMA_IRQ_0` and the `UARTx_IRQ` of the
+/// UART in the NVIC.
+#[allow(clippy::type_complexity)]
+pub fn split<'q, D, P, TX, RX, const N: usize, const Q: usize>(
+    uart: UartPeripheral<Disabled, D, P>,
+    config: Config,
+    frequency: HertzU32,
+    channels: (Channel<TX>, Channel<RX>),
+    buffers: &'q mut Buffers<N, Q>,
+) -> Result<(Driver<'q, D, P, TX, RX, N, Q>, Port<'q, N, Q>), ConfigError>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+    TX: ChannelIndex,
+    RX: ChannelIndex,
+{
+    check::<D, P>(&config, frequency)?;
+    let Buffers {
+        ring,
+        tx,
+        received,
+        idle_us,
+    } = buffers;
+    let (producer, consumer) = tx.split();
+    idle_us.store(idle_time_us(&config), Ordering::Relaxed);
+
+    let uart = enable(uart, &config, frequency);
+    enable_interrupts::<D>();
+
+    let dreq = DREQ_UART0_TX + 2 * D::ID as u8;
+    let data = registers::<D>().uartdr().as_ptr() as u32;
+    let tx_channel = channel(TX::id());
+    tx_channel
+        .ch_write_addr()
+        .write(|w| unsafe { w.bits(data) });
+    tx_channel.ch_al1_ctrl().write(|w| unsafe {
+        w.en().set_bit();
+        w.data_size().size_byte();
+        w.incr_read().set_bit();
+        w.incr_write().clear_bit();
+        w.chain_to().bits(TX::id());
+        w.treq_sel().bits(dreq)
+    });
+
+    let rx_channel = channel(RX::id());
+    rx_channel.ch_read_addr().write(|w| unsafe { w.bits(data) });
+    rx_channel
+        .ch_write_addr()
+        .write(|w| unsafe { w.bits(ring.address()) });
+    rx_channel.ch_al1_ctrl().write(|w| unsafe {
+        w.en().set_bit();
+        w.data_size().size_byte();
+        w.incr_read().clear_bit();
+        w.incr_write().set_bit();
+        w.ring_size().bits(RING_BITS);
+        w.ring_sel().set_bit();
+        w.chain_to().bits(RX::id());
+        w.treq_sel().bits(dreq + 1)
+    });
+
+    let mask = 1 << TX::id() | 1 << RX::id();
+    dma()
+        .inte0()
+        .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    // Runs until stopped for new settings, or for 4 GiB
+    rx_channel
+        .ch_al1_trans_count_trig()
+        .write(|w| unsafe { w.bits(u32::MAX) });
+
+    let ring: &'q Ring = ring;
+    let received: &'q AtomicU32 = received;
+    let idle_us: &'q AtomicU32 = idle_us;
+    Ok((
+        Driver {
+            uart: Some(uart),
+            config,
+            frequency,
+            _channels: channels,
+            tx: consumer,
+            sending: false,
+            ring,
+            received,
+            idle_us,
+            errors: ErrorCounts::default(),
+        },
+        Port {
+            tx: producer,
+            rx_channel: RX::id(),
+            ring,
+            received,
+            idle_us,
+            reader: RingReader::new(),
+            last_written: 0,
+            written_at: 0,
+            burst: false,
+        },
+    ))
+}
+
+/// The interrupt half of the UART.
+pub struct Driver<'q, D, P, TX, RX, const N: usize, const Q: usize>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+    TX: ChannelIndex,
+    RX: ChannelIndex,
+{
+    /// Taken only while the settings change.
+    uart: Option<UartPeripheral<Enabled, D, P>>,
+    config: Config,
+    frequency: HertzU32,
+    _channels: (Channel<TX>, Channel<RX>),
+    tx: Consumer<'q, Vec<u8, N>, Q>,
+    /// The TX channel has the front buffer.
+    sending: bool,
+    ring: &'q Ring,
+    received: &'q AtomicU32,
+    idle_us: &'q AtomicU32,
+    errors: ErrorCounts,
+}
+
+impl<'q, D, P, TX, RX, const N: usize, const Q: usize> Driver<'q, D, P, TX, RX, N, Q>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+    TX: ChannelIndex,
+    RX: ChannelIndex,
+{
+    /// Starts the next buffer and restarts the RX channel, call from the
+    /// `DMA_IRQ_0` handler.
+    pub fn on_dma_interrupt(&mut self) {
+        let mask = 1 << TX::id() | 1 << RX::id();
+        let status = dma().ints0().read().bits() & mask;
+        dma().ints0().write(|w| unsafe { w.bits(status) });
+
+        if status & 1 << RX::id() != 0 {
+            // The channel ran through its count, it goes on from where it
+            // stopped in the ring
+            let received = self.received.load(Ordering::Relaxed);
+            self.received
+                .store(received.wrapping_add(u32::MAX), Ordering::Relaxed);
+            channel(RX::id())
+                .ch_al1_trans_count_trig()
+                .write(|w| unsafe { w.bits(u32::MAX) });
+        }
+        self.transmit();
+    }
+
+    /// Counts the receive errors, call from the `UARTx_IRQ` handler.
+    pub fn on_uart_interrupt(&mut self) {
+        let uart = registers::<D>();
+        let status = uart.uartmis().read();
+        let counts = [
+            (status.femis().bit(), &mut self.errors.framing),
+            (status.pemis().bit(), &mut self.errors.parity),
+            (status.oemis().bit(), &mut self.errors.overrun),
+            (status.bemis().bit(), &mut self.errors.breaks),
+        ];
+        for (raised, count) in counts {
+            if raised {
+                log::warn!("UART receive error");
+                *count = count.wrapping_add(1);
+            }
+        }
+        uart.uarticr().write(|w| {
+            w.feic().clear_bit_by_one();
+            w.peic().clear_bit_by_one();
+            w.beic().clear_bit_by_one();
+            w.oeic().clear_bit_by_one()
+        });
+    }
+
+    /// The receive errors so far.
+    pub fn errors(&self) -> ErrorCounts {
+        self.errors
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Changes the line settings, with both interrupts masked (e.g. from a
+    /// critical section).
+    ///
+    /// Fails with [`ConfigError::Busy`] while a buffer goes out with the
+    /// old settings, to be called again outside of the critical section.
+    /// Moves the bytes in the RX FIFO to the ring, no data is lost. The
+    /// buffers still queued go out with the new settings, see
+    /// [`Port::is_flushed`] to send them before.
+    pub fn configure(&mut self, config: Config) -> Result<(), ConfigError> {
+        check::<D, P>(&config, self.frequency)?;
+        let tx_channel = channel(TX::id());
+        if tx_channel.ch_ctrl_trig().read().busy().bit_is_set()
+            || self.uart.as_mut().unwrap().uart_is_busy()
+        {
+            return Err(ConfigError::Busy);
+        }
+        self.drain();
+
+        let uart = self.uart.take().unwrap().disable();
+        self.uart = Some(enable(uart, &config, self.frequency));
+        enable_interrupts::<D>();
+        self.idle_us.store(idle_time_us(&config), Ordering::Relaxed);
+        self.config = config;
+        Ok(())
+    }
+
+    fn transmit(&mut self) {
+        let tx_channel = channel(TX::id());
+        if tx_channel.ch_ctrl_trig().read().busy().bit_is_set() {
+            return;
+        }
+        if self.sending {
+            self.tx.dequeue();
+            self.sending = false;
+        }
+        while let Some(buffer) = self.tx.peek() {
+            if buffer.is_empty() {
+                self.tx.dequeue();
+                continue;
+            }
+            // The buffer stays in the queue, untouched by the producer,
+            // until it is dequeued at the end of the transfer
+            let address = buffer.as_ptr() as u32;
+            let len = buffer.len() as u32;
+            tx_channel
+                .ch_read_addr()
+                .write(|w| unsafe { w.bits(address) });
+            tx_channel
+                .ch_al1_trans_count_trig()
+                .write(|w| unsafe { w.bits(len) });
+            self.sending = true;
+            break;
+        }
+    }
+
+    /// Moves the bytes left in the RX FIFO to the ring, behind the ones the
+    /// RX channel wrote, and restarts the channel after them.
+    fn drain(&mut self) {
+        let rx_channel = channel(RX::id());
+        let mask = 1 << RX::id();
+
+        // Cleared first, otherwise the abort may raise the completion
+        // interrupt (RP2040-E13)
+        rx_channel.ch_al1_ctrl().modify(|_, w| w.en().clear_bit());
+        dma().chan_abort().write(|w| unsafe { w.bits(mask) });
+        while dma().chan_abort().read().bits() & mask != 0 {}
+        dma().ints0().write(|w| unsafe { w.bits(mask) });
+
+        let remaining = rx_channel.ch_trans_count().read().bits();
+        let mut received = self
+            .received
+            .load(Ordering::Relaxed)
+            .wrapping_add(u32::MAX - remaining);
+        let uart = registers::<D>();
+        while uart.uartfr().read().rxfe().bit_is_clear() {
+            let byte = uart.uartdr().read().data().bits();
+            unsafe { self.ring.write(received as usize, byte) };
+            received = received.wrapping_add(1);
+        }
+
+        let address = self.ring.address() + (received as usize % RX_RING_LEN) as u32;
+        rx_channel
+            .ch_write_addr()
+            .write(|w| unsafe { w.bits(address) });
+        rx_channel.ch_al1_ctrl().modify(|_, w| w.en().set_bit());
+        self.received.store(received, Ordering::Relaxed);
+        rx_channel
+            .ch_al1_trans_count_trig()
+            .write(|w| unsafe { w.bits(u32::MAX) });
+    }
+}
+
+/// The receive errors raise the UART interrupt.
+fn enable_interrupts<D: UartDevice>() {
+    let uart = registers::<D>();
+    uart.uartimsc().write(|w| {
+        w.feim().set_bit();
+        w.peim().set_bit();
+        w.beim().set_bit();
+        w.oeim().set_bit()
+    });
+}
+
+/// How long the line stays quiet before [`Port::take_idle`] tells it is
+/// idle, 32 bit periods.
+fn idle_time_us(config: &Config) -> u32 {
+    32_000_000u32.div_ceil(config.baud)
+}
+
+fn dma() -> &'static pac::dma::RegisterBlock {
+    // The channels of the driver are owned by it, the shared registers
+    // are only written for them
+    unsafe { &*pac::DMA::ptr() }
+}
+
+fn channel(id: u8) -> &'static pac::dma::CH {
+    dma().ch(usize::from(id))
+}
+
+/// The application half of the UART.
+pub struct Port<'q, const N: usize, const Q: usize> {
+    tx: Producer<'q, Vec<u8, N>, Q>,
+    rx_channel: u8,
+    ring: &'q Ring,
+    received: &'q AtomicU32,
+    idle_us: &'q AtomicU32,
+    reader: RingReader<RX_RING_LEN>,
+    /// The count of received bytes at the last [`Port::take_idle`] that
+    /// saw it change, and when.
+    last_written: u32,
+    written_at: u64,
+    /// Bytes came since the line was last idle.
+    burst: bool,
+}
+
+impl<'q, const N: usize, const Q: usize> Port<'q, N, Q> {
+    /// Queues a buffer, or gives it back if the queue is full.
+    pub fn send(&mut self, buffer: Vec<u8, N>) -> Result<(), Vec<u8, N>> {
+        self.tx.enqueue(buffer)?;
+        cortex_m::peripheral::NVIC::pend(pac::Interrupt::DMA_IRQ_0);
+        Ok(())
+    }
+
+    /// Queues as many bytes as fit in the free buffers, returns how many
+    /// were queued.
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        let mut queued = 0;
+        for chunk in bytes.chunks(N) {
+            // Fits by construction
+            if self.send(Vec::from_slice(chunk).unwrap()).is_err() {
+                break;
+            }
+            queued += chunk.len();
+        }
+        queued
+    }
+
+    /// Queues all bytes, waiting for the interrupt to free buffers.
+    pub fn write_all(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let queued = self.write(bytes);
+            bytes = &bytes[queued..];
+            if queued == 0 {
+                cortex_m::asm::wfi();
+            }
+        }
+    }
+
+    /// Waits for the DMA to send all the queued buffers.
+    pub fn flush(&mut self) {
+        while !self.is_flushed() {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Whether the DMA sent all the queued buffers, the last bytes may
+    /// still be in the TX FIFO.
+    pub fn is_flushed(&self) -> bool {
+        self.tx.len() == 0
+    }
+
+    /// Reads the received bytes, returns how many were read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let (ring, received, rx_channel) = (self.ring, self.received, self.rx_channel);
+        let written = || written_count(received, rx_channel);
+        self.reader.read(written, |offset| ring.read(offset), buf)
+    }
+
+    /// Whether the line went idle after a burst since the last call, with
+    /// the time in microseconds, e.g. from the RP2040 timer. The line is
+    /// idle once no byte came for 32 bit periods, as far as the calls can
+    /// tell: call at least that often for a timely answer. The whole burst
+    /// is in the ring by then.
+    pub fn take_idle(&mut self, now_us: u64) -> bool {
+        let written = written_count(self.received, self.rx_channel);
+        if written != self.last_written {
+            self.last_written = written;
+            self.written_at = now_us;
+            self.burst = true;
+            return false;
+        }
+        let idle_us = u64::from(self.idle_us.load(Ordering::Relaxed));
+        if self.burst && now_us.wrapping_sub(self.written_at) >= idle_us {
+            self.burst = false;
+            return true;
+        }
+        false
+    }
+
+    /// Received bytes written over in the ring before they were read.
+    pub fn lost(&self) -> u32 {
+        self.reader.lost()
+    }
+}
+
+/// The count of bytes the RX channel wrote to the ring.
+fn written_count(received: &AtomicU32, rx_channel: u8) -> u32 {
+    let rx_channel = channel(rx_channel);
+    loop {
+        // Consistent only if the driver did not restart the channel in
+        // between
+        let before = received.load(Ordering::Relaxed);
+        let remaining = rx_channel.ch_trans_count().read().bits();
+        if received.load(Ordering::Relaxed) == before {
+            return before.wrapping_add(u32::MAX - remaining);
+        }
+    }
+}
+
+impl<'q, const N: usize, const Q: usize> core::fmt::Write for Port<'q, N, Q> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_all(s.as_bytes());
+        Ok(())
+    }
+}
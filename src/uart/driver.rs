@@ -0,0 +1,363 @@
+//! The interrupt-driven UART.
+
+use defmt as log;
+use fugit::HertzU32;
+use heapless::spsc::Consumer;
+use heapless::spsc::Producer;
+use heapless::spsc::Queue;
+
+use crate::line::LineBuffer;
+use rp2040_hal as hal;
+
+use hal::pac;
+use hal::typelevel::OptionT;
+use hal::uart::Disabled;
+use hal::uart::Enabled;
+use hal::uart::ReadErrorType;
+use hal::uart::UartDevice;
+use hal::uart::UartPeripheral;
+use hal::uart::ValidUartPinout;
+
+use super::Config;
+use super::ConfigError;
+use super::DataBits;
+use super::ErrorCounts;
+use super::FlowControl;
+use super::Parity;
+use super::StopBits;
+
+/// Enables the UART with `config` for the UART clock at `frequency`,
+/// splits it into the interrupt and the application halves and enables
+/// the UART interrupts.
+///
+/// The caller still has to unmask `irq` in the NVIC.
+pub fn split<'q, D, P, const RX: usize, const TX: usize>(
+    uart: UartPeripheral<Disabled, D, P>,
+    config: Config,
+    frequency: HertzU32,
+    irq: pac::Interrupt,
+    rx_queue: &'q mut Queue<u8, RX>,
+    tx_queue: &'q mut Queue<u8, TX>,
+) -> Result<(Driver<'q, D, P, RX, TX>, Port<'q, RX, TX>), ConfigError>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+{
+    check::<D, P>(&config, frequency)?;
+    let (rx_producer, rx_consumer) = rx_queue.split();
+    let (tx_producer, tx_consumer) = tx_queue.split();
+
+    let mut uart = enable(uart, &config, frequency);
+    uart.enable_rx_interrupt();
+
+    Ok((
+        Driver {
+            uart: Some(uart),
+            config,
+            frequency,
+            rx: rx_producer,
+            tx: tx_consumer,
+            fifos: true,
+            received: 0,
+            dropped: 0,
+            errors: ErrorCounts::default(),
+        },
+        Port {
+            irq,
+            rx: rx_consumer,
+            tx: tx_producer,
+        },
+    ))
+}
+
+/// The interrupt half of the UART.
+pub struct Driver<'q, D: UartDevice, P: ValidUartPinout<D>, const RX: usize, const TX: usize> {
+    /// Taken only while the settings change.
+    uart: Option<UartPeripheral<Enabled, D, P>>,
+    config: Config,
+    frequency: HertzU32,
+    rx: Producer<'q, u8, RX>,
+    tx: Consumer<'q, u8, TX>,
+    fifos: bool,
+    received: u32,
+    dropped: u32,
+    errors: ErrorCounts,
+}
+
+impl<'q, D, P, const RX: usize, const TX: usize> Driver<'q, D, P, RX, TX>
+where
+    D: UartDevice,
+    P: ValidUartPinout<D>,
+{
+    /// Moves data between the FIFOs and the queues, call from the `UARTx_IRQ` handler.
+    pub fn on_interrupt(&mut self) {
+        self.receive();
+        self.transmit();
+    }
+
+    /// Number of bytes moved to the RX queue, wrapping. What it went up by
+    /// in [`Driver::on_interrupt`] tells how many bytes came, to time them.
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    /// Number of received bytes lost because the RX queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// The receive errors so far.
+    pub fn errors(&self) -> ErrorCounts {
+        self.errors
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Changes the line settings, with the interrupt masked (e.g. from a
+    /// critical section).
+    ///
+    /// Fails with [`ConfigError::Busy`] while the bytes in the TX FIFO go
+    /// out with the old settings, to be called again outside of the
+    /// critical section. Moves the received bytes to the RX queue, no data
+    /// is lost. The bytes still queued go out with the new settings, see
+    /// [`Port::flush`] to send them before.
+    pub fn configure(&mut self, config: Config) -> Result<(), ConfigError> {
+        check::<D, P>(&config, self.frequency)?;
+        if self.uart().uart_is_busy() {
+            return Err(ConfigError::Busy);
+        }
+        self.receive();
+
+        let uart = self.uart.take().unwrap().disable();
+        let mut uart = enable(uart, &config, self.frequency);
+        uart.enable_rx_interrupt();
+        if !self.fifos {
+            // The HAL always turns them on
+            while_disabled::<D>(|uart| {
+                uart.uartlcr_h().modify(|_, w| w.fen().clear_bit());
+            });
+        }
+        self.uart = Some(uart);
+        self.config = config;
+        self.transmit();
+        Ok(())
+    }
+
+    /// Turns the FIFOs off, or back on, with the interrupt masked and
+    /// failing while busy as [`Driver::configure`].
+    ///
+    /// Without the FIFOs the interrupt comes for every byte. That costs
+    /// more, but the bytes reach the RX queue as they arrive rather than at
+    /// the FIFO watermark or the receive timeout, for protocols that time
+    /// the gaps between them such as Modbus RTU, see [`Driver::received`].
+    pub fn set_fifos(&mut self, enabled: bool) -> Result<(), ConfigError> {
+        let fifos = core::mem::replace(&mut self.fifos, enabled);
+        let configured = self.configure(self.config);
+        if configured.is_err() {
+            self.fifos = fifos;
+        }
+        configured
+    }
+
+    fn uart(&mut self) -> &mut UartPeripheral<Enabled, D, P> {
+        self.uart.as_mut().unwrap()
+    }
+
+    fn receive(&mut self) {
+        let mut buf = [0u8; 32];
+        loop {
+            let (received, error) = match self.uart().read_raw(&mut buf) {
+                Ok(count) => (&buf[..count], None),
+                // The bytes before the bad one are good
+                Err(nb::Error::Other(e)) => (e.discarded, Some(e.err_type)),
+                Err(nb::Error::WouldBlock) => break,
+            };
+            for &byte in received {
+                match self.rx.enqueue(byte) {
+                    Ok(()) => self.received = self.received.wrapping_add(1),
+                    Err(_) => self.dropped = self.dropped.wrapping_add(1),
+                }
+            }
+
+            let Some(error) = error else {
+                continue;
+            };
+            log::warn!("UART receive error: {}", log::Debug2Format(&error));
+            let count = match error {
+                ReadErrorType::Framing => &mut self.errors.framing,
+                ReadErrorType::Parity => &mut self.errors.parity,
+                ReadErrorType::Overrun => &mut self.errors.overrun,
+                ReadErrorType::Break => &mut self.errors.breaks,
+            };
+            *count = count.wrapping_add(1);
+        }
+    }
+
+    fn transmit(&mut self) {
+        let uart = self.uart.as_mut().unwrap();
+        while uart.uart_is_writable() {
+            let Some(byte) = self.tx.dequeue() else {
+                break;
+            };
+            uart.write_full_blocking(&[byte]);
+        }
+
+        // The TX interrupt fires when the FIFO drains below the watermark,
+        // keep it enabled only while there is something left to send.
+        if self.tx.ready() {
+            uart.enable_tx_interrupt();
+        } else {
+            uart.disable_tx_interrupt();
+        }
+    }
+}
+
+/// Checks the settings before the UART gets disabled, enabling then
+/// cannot fail.
+pub(super) fn check<D: UartDevice, P: ValidUartPinout<D>>(
+    config: &Config,
+    frequency: HertzU32,
+) -> Result<(), ConfigError> {
+    config.divisors(frequency.to_Hz())?;
+    let has_flow_pins = P::Cts::IS_SOME && P::Rts::IS_SOME;
+    if config.flow_control == FlowControl::RtsCts && !has_flow_pins {
+        return Err(ConfigError::NoFlowControlPins);
+    }
+    Ok(())
+}
+
+pub(super) fn enable<D: UartDevice, P: ValidUartPinout<D>>(
+    uart: UartPeripheral<Disabled, D, P>,
+    config: &Config,
+    frequency: HertzU32,
+) -> UartPeripheral<Enabled, D, P> {
+    let hal_config = hal::uart::UartConfig::new(
+        HertzU32::from_raw(config.baud),
+        match config.data_bits {
+            DataBits::Five => hal::uart::DataBits::Five,
+            DataBits::Six => hal::uart::DataBits::Six,
+            DataBits::Seven => hal::uart::DataBits::Seven,
+            DataBits::Eight => hal::uart::DataBits::Eight,
+        },
+        match config.parity {
+            Parity::None => None,
+            Parity::Even => Some(hal::uart::Parity::Even),
+            Parity::Odd => Some(hal::uart::Parity::Odd),
+        },
+        match config.stop_bits {
+            StopBits::One => hal::uart::StopBits::One,
+            StopBits::Two => hal::uart::StopBits::Two,
+        },
+    );
+    // The baud rate was checked
+    let uart = uart.enable(hal_config, frequency).unwrap();
+
+    // The HAL turns flow control on whenever the pins are there
+    if config.flow_control == FlowControl::None {
+        while_disabled::<D>(|uart| {
+            uart.uartcr()
+                .modify(|_, w| w.ctsen().clear_bit().rtsen().clear_bit());
+        });
+    }
+    uart
+}
+
+/// Changes the registers the UART takes only while disabled, such as
+/// LCR_H, once the HAL enabled it. Nothing is on its way then.
+fn while_disabled<D: UartDevice>(change: impl FnOnce(&pac::uart0::RegisterBlock)) {
+    let uart = registers::<D>();
+    uart.uartcr().modify(|_, w| w.uarten().clear_bit());
+    change(uart);
+    uart.uartcr().modify(|_, w| w.uarten().set_bit());
+}
+
+/// The registers of the UART the HAL owns, for what the HAL has no method
+/// for.
+pub(super) fn registers<D: UartDevice>() -> &'static pac::uart0::RegisterBlock {
+    unsafe {
+        &*match D::ID {
+            0 => pac::UART0::ptr(),
+            _ => pac::UART1::ptr(),
+        }
+    }
+}
+
+/// The application half of the UART.
+pub struct Port<'q, const RX: usize, const TX: usize> {
+    irq: pac::Interrupt,
+    rx: Consumer<'q, u8, RX>,
+    tx: Producer<'q, u8, TX>,
+}
+
+impl<'q, const RX: usize, const TX: usize> Port<'q, RX, TX> {
+    /// Queues as many bytes as fit, returns how many were queued.
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        let queued = bytes
+            .iter()
+            .take_while(|&&byte| self.tx.enqueue(byte).is_ok())
+            .count();
+
+        if queued != 0 {
+            cortex_m::peripheral::NVIC::pend(self.irq);
+        }
+        queued
+    }
+
+    /// Queues all bytes, waiting for the interrupt to make room.
+    pub fn write_all(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let queued = self.write(bytes);
+            bytes = &bytes[queued..];
+            if queued == 0 {
+                cortex_m::asm::wfi();
+            }
+        }
+    }
+
+    /// Waits for the interrupt to move all the queued bytes to the TX FIFO.
+    pub fn flush(&mut self) {
+        while self.tx.len() != 0 {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Is there received data to read?
+    pub fn rx_ready(&self) -> bool {
+        self.rx.ready()
+    }
+
+    /// Reads the received bytes, returns how many were read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buf.iter_mut() {
+            let Some(byte) = self.rx.dequeue() else {
+                break;
+            };
+            *slot = byte;
+            count += 1;
+        }
+        count
+    }
+
+    /// Feeds the received bytes to `line` until it completes a line.
+    pub fn read_line<'l, const N: usize>(
+        &mut self,
+        line: &'l mut LineBuffer<N>,
+    ) -> Option<&'l [u8]> {
+        while let Some(byte) = self.rx.dequeue() {
+            if line.push(byte).is_some() {
+                return line.line();
+            }
+        }
+        None
+    }
+}
+
+impl<'q, const RX: usize, const TX: usize> core::fmt::Write for Port<'q, RX, TX> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_all(s.as_bytes());
+        Ok(())
+    }
+}
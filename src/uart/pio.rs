@@ -0,0 +1,296 @@
+//! UARTs on the PIO, beyond UART0 and UART1.
+//!
+//! Each half runs on a state machine of its own, so a PIO block has up to
+//! four of them in any mix, such as two full UARTs or four transmitters.
+//! The programs are those of the SDK examples, 8N1 only: [`Programs`] holds
+//! them installed once per block for all its halves. [`Tx`] and [`Rx`]
+//! implement the `embedded-io` traits like `hal::uart::UartPeripheral`,
+//! and [`Tx`] implements [`core::fmt::Write`] too. The state machines
+//! run from the system clock.
+//!
+//! ```ignore
+//! let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+//! let programs = uart::pio::Programs::install(&mut pio).unwrap();
+//! let config = uart::Config::new(115_200);
+//! let clock = clocks.system_clock.freq();
+//! let mut tx = uart::pio::Tx::new(&programs, sm0, pins.gpio2, config, clock).unwrap();
+//! let mut rx = uart::pio::Rx::new(&programs, sm1, pins.gpio3, config, clock).unwrap();
+//! ```
+//!
+//! A character without its stop bit, a break included, is dropped.
+
+use core::convert::Infallible;
+
+use fugit::HertzU32;
+
+use rp2040_hal as hal;
+
+use hal::gpio::DynPinId;
+use hal::gpio::Function;
+use hal::gpio::Pin;
+use hal::gpio::PinId;
+use hal::gpio::PullType;
+use hal::gpio::PullUp;
+use hal::gpio::ValidFunction;
+use hal::pio::Buffers;
+use hal::pio::InstallError;
+use hal::pio::InstalledProgram;
+use hal::pio::PIOBuilder;
+use hal::pio::PIOExt;
+use hal::pio::PinDir;
+use hal::pio::PinState;
+use hal::pio::Running;
+use hal::pio::ShiftDirection;
+use hal::pio::StateMachine;
+use hal::pio::StateMachineIndex;
+use hal::pio::UninitStateMachine;
+use hal::pio::PIO;
+
+use super::Config;
+use super::ConfigError;
+use super::FlowControl;
+
+/// A pin given to a PIO block, pulled up to keep an unconnected line idle.
+type PioPin<P> = Pin<DynPinId, <P as PIOExt>::PinFunction, PullUp>;
+
+/// The UART programs installed in a PIO block.
+pub struct Programs<P: PIOExt> {
+    tx: InstalledProgram<P>,
+    rx: InstalledProgram<P>,
+}
+
+impl<P: PIOExt> Programs<P> {
+    /// Installs both programs, 12 of the 32 instructions.
+    pub fn install(pio: &mut PIO<P>) -> Result<Self, InstallError> {
+        // Stop bit and idle line from the side-set, then 8 data bits, LSB
+        // first
+        let tx = pio_proc::pio_asm!(
+            ".side_set 1 opt",
+            "    pull       side 1 [7]",
+            "    set x, 7   side 0 [7]",
+            "bitloop:",
+            "    out pins, 1",
+            "    jmp x-- bitloop    [6]",
+        );
+        // Samples each bit in its middle, pushes the character only if the
+        // stop bit is there, else waits for the line to go idle
+        let rx = pio_proc::pio_asm!(
+            "start:",
+            "    wait 0 pin 0",
+            "    set x, 7       [10]",
+            "bitloop:",
+            "    in pins, 1",
+            "    jmp x-- bitloop    [6]",
+            "    jmp pin good_stop",
+            "    wait 1 pin 0",
+            "    jmp start",
+            "good_stop:",
+            "    push",
+        );
+        Ok(Self {
+            tx: pio.install(&tx.program)?,
+            rx: pio.install(&rx.program)?,
+        })
+    }
+}
+
+/// Checks that the PIO programs can do `config` with the state machines
+/// at `clock`, returns the clock divisor.
+fn divisors(config: &Config, clock: HertzU32) -> Result<(u16, u8), ConfigError> {
+    if config.format() != *b"8N1" || config.flow_control != FlowControl::None {
+        return Err(ConfigError::Format);
+    }
+    config.pio_divisors(clock.to_Hz())
+}
+
+/// Gives `pin` to the PIO block, returns it and its number.
+fn take_pin<P, I, F, M>(pin: Pin<I, F, M>) -> (PioPin<P>, u8)
+where
+    P: PIOExt,
+    I: PinId + ValidFunction<P::PinFunction>,
+    F: Function,
+    M: PullType,
+{
+    let pin = pin
+        .into_function::<P::PinFunction>()
+        .into_pull_type::<PullUp>()
+        .into_dyn_pin();
+    let number = pin.id().num;
+    (pin, number)
+}
+
+/// The transmitting half, on one state machine.
+pub struct Tx<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    fifo: hal::pio::Tx<(P, SM)>,
+    _pin: PioPin<P>,
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> Tx<P, SM> {
+    /// Starts the state machine sending on `pin`, which idles high.
+    pub fn new<I, F, M>(
+        programs: &Programs<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: Pin<I, F, M>,
+        config: Config,
+        clock: HertzU32,
+    ) -> Result<Self, ConfigError>
+    where
+        I: PinId + ValidFunction<P::PinFunction>,
+        F: Function,
+        M: PullType,
+    {
+        let (integer, fraction) = divisors(&config, clock)?;
+        let (pin, number) = take_pin::<P, _, _, _>(pin);
+        // Never uninstalled, the programs are only shared
+        let program = unsafe { programs.tx.share() };
+        let (mut sm, _, fifo) = PIOBuilder::from_installed_program(program)
+            .out_pins(number, 1)
+            .side_set_pin_base(number)
+            .out_shift_direction(ShiftDirection::Right)
+            .clock_divisor_fixed_point(integer, fraction)
+            .buffers(Buffers::OnlyTx)
+            .build(sm);
+        sm.set_pins([(number, PinState::High)]);
+        sm.set_pindirs([(number, PinDir::Output)]);
+        Ok(Self {
+            _sm: sm.start(),
+            fifo,
+            _pin: pin,
+        })
+    }
+
+    /// Queues as many bytes as the FIFO takes, 8 at most, returns how many.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> usize {
+        bytes
+            .iter()
+            .take_while(|&&byte| self.fifo.write(u32::from(byte)))
+            .count()
+    }
+
+    /// Sends the bytes, waiting for room in the FIFO.
+    pub fn write_all(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            bytes = &bytes[self.write_raw(bytes)..];
+        }
+    }
+
+    /// Waits until the last byte has left, its stop bit included.
+    pub fn flush(&mut self) {
+        while !self.fifo.is_empty() {}
+        // The state machine stalls on the next pull once the stop bit is out
+        self.fifo.clear_stalled_flag();
+        while !self.fifo.has_stalled() {}
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::ErrorType for Tx<P, SM> {
+    type Error = Infallible;
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::Write for Tx<P, SM> {
+    /// Blocks only while no byte fits.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        loop {
+            let len = self.write_raw(buf);
+            if len > 0 || buf.is_empty() {
+                return Ok(len);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Tx::flush(self);
+        Ok(())
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::WriteReady for Tx<P, SM> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.fifo.is_full())
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> core::fmt::Write for Tx<P, SM> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_all(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// The receiving half, on one state machine.
+pub struct Rx<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    fifo: hal::pio::Rx<(P, SM)>,
+    _pin: PioPin<P>,
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> Rx<P, SM> {
+    /// Starts the state machine receiving on `pin`.
+    pub fn new<I, F, M>(
+        programs: &Programs<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: Pin<I, F, M>,
+        config: Config,
+        clock: HertzU32,
+    ) -> Result<Self, ConfigError>
+    where
+        I: PinId + ValidFunction<P::PinFunction>,
+        F: Function,
+        M: PullType,
+    {
+        let (integer, fraction) = divisors(&config, clock)?;
+        let (pin, number) = take_pin::<P, _, _, _>(pin);
+        // Never uninstalled, the programs are only shared
+        let program = unsafe { programs.rx.share() };
+        let (mut sm, fifo, _) = PIOBuilder::from_installed_program(program)
+            .in_pin_base(number)
+            .jmp_pin(number)
+            .in_shift_direction(ShiftDirection::Right)
+            .clock_divisor_fixed_point(integer, fraction)
+            .buffers(Buffers::OnlyRx)
+            .build(sm);
+        sm.set_pindirs([(number, PinDir::Input)]);
+        Ok(Self {
+            _sm: sm.start(),
+            fifo,
+            _pin: pin,
+        })
+    }
+
+    /// Copies the bytes in the FIFO to `buf`, 8 at most, returns how many.
+    pub fn read_raw(&mut self, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        for slot in buf.iter_mut() {
+            // The character came in from the top of the shift register
+            let Some(word) = self.fifo.read() else {
+                break;
+            };
+            *slot = (word >> 24) as u8;
+            len += 1;
+        }
+        len
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::ErrorType for Rx<P, SM> {
+    type Error = Infallible;
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::Read for Rx<P, SM> {
+    /// Blocks until at least a byte comes.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            let len = self.read_raw(buf);
+            if len > 0 || buf.is_empty() {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_io::ReadReady for Rx<P, SM> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.fifo.is_empty())
+    }
+}
@@ -0,0 +1,165 @@
+//! defmt logs over a USB serial port, for boards without a debug probe.
+//!
+//! With the `defmt-usb` cargo feature the global defmt logger queues the
+//! encoded frames in RAM instead of sending them over RTT, and [`flush`]
+//! moves them to a CDC ACM port the firmware adds to its USB device:
+//!
+//! ```ignore
+//! let mut log_port = SerialPort::new(&usb_bus);
+//! loop {
+//!     usb_dev.poll(&mut [&mut serial, &mut log_port]);
+//!     usb_log::flush(&mut log_port);
+//! }
+//! ```
+//!
+//! On the host `defmt-print -e <elf> serial --path /dev/ttyACM1` decodes
+//! them. Nothing blocks: with no terminal on the port the frames are
+//! dropped, and so are the frames that do not fit in the queue.
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod logger;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use logger::dropped;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use logger::flush;
+
+use heapless::Deque;
+
+/// Queue of whole frames: a frame that does not fit is dropped entirely,
+/// so the decoder never sees a partial one.
+pub struct FrameQueue<const N: usize> {
+    bytes: Deque<u8, N>,
+    frame_start: usize,
+    overflow: bool,
+    dropped: u32,
+}
+
+impl<const N: usize> FrameQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: Deque::new(),
+            frame_start: 0,
+            overflow: false,
+            dropped: 0,
+        }
+    }
+
+    pub fn start_frame(&mut self) {
+        self.frame_start = self.bytes.len();
+        self.overflow = false;
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        if self.overflow {
+            return;
+        }
+        for &byte in bytes {
+            if self.bytes.push_back(byte).is_err() {
+                // Take back what was written of this frame
+                while self.bytes.len() > self.frame_start {
+                    self.bytes.pop_back();
+                }
+                self.overflow = true;
+                return;
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.overflow {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        self.frame_start = self.bytes.len();
+    }
+
+    /// Copies the oldest bytes without removing them, returns how many.
+    pub fn peek(&self, buf: &mut [u8]) -> usize {
+        buf.iter_mut()
+            .zip(self.bytes.iter())
+            .map(|(slot, &byte)| *slot = byte)
+            .count()
+    }
+
+    /// Removes the oldest `count` bytes.
+    pub fn consume(&mut self, count: usize) {
+        for _ in 0..count {
+            self.bytes.pop_front();
+        }
+        self.frame_start = self.frame_start.saturating_sub(count);
+    }
+
+    /// Drops everything queued, e.g. while nobody listens.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.frame_start = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Number of frames dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl<const N: usize> Default for FrameQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame<const N: usize>(queue: &mut FrameQueue<N>, parts: &[&[u8]]) {
+        queue.start_frame();
+        for part in parts {
+            queue.write(part);
+        }
+        queue.end_frame();
+    }
+
+    fn drain<const N: usize>(queue: &mut FrameQueue<N>) -> Vec<u8> {
+        let mut buf = [0; N];
+        let count = queue.peek(&mut buf);
+        queue.consume(count);
+        buf[..count].to_vec()
+    }
+
+    #[test]
+    fn keeps_frames_in_order() {
+        let mut queue = FrameQueue::<16>::new();
+        frame(&mut queue, &[b"ab", b"c\0"]);
+        frame(&mut queue, &[b"de\0"]);
+
+        let mut buf = [0; 3];
+        assert_eq!(queue.peek(&mut buf), 3);
+        assert_eq!(&buf, b"abc");
+        queue.consume(2);
+
+        assert_eq!(drain(&mut queue), b"c\0de\0");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drops_whole_frames() {
+        let mut queue = FrameQueue::<8>::new();
+        frame(&mut queue, &[b"abc\0"]);
+        frame(&mut queue, &[b"de", b"fgh", b"\0"]);
+        frame(&mut queue, &[b"ij\0"]);
+
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(drain(&mut queue), b"abc\0ij\0");
+
+        // A partially sent queue still rolls back to the frame start
+        frame(&mut queue, &[b"klmnop\0"]);
+        queue.consume(3);
+        frame(&mut queue, &[b"qrst\0"]);
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(drain(&mut queue), b"nop\0");
+    }
+}
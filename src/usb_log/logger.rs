@@ -0,0 +1,99 @@
+//! The global defmt logger and the USB side of the queue.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use usb_device::bus::UsbBus;
+use usbd_serial::SerialPort;
+
+use super::FrameQueue;
+
+/// About a second of chatty logging at full speed.
+const QUEUE_LEN: usize = 2048;
+
+static QUEUE: Mutex<RefCell<FrameQueue<QUEUE_LEN>>> = Mutex::new(RefCell::new(FrameQueue::new()));
+
+/// Sends the queued frames to the port, or drops them if no terminal has
+/// the port open. Call after polling the USB device.
+pub fn flush<B: UsbBus>(port: &mut SerialPort<'_, B>) {
+    let mut buf = [0u8; 64];
+    loop {
+        let count = critical_section::with(|cs| {
+            let mut queue = QUEUE.borrow_ref_mut(cs);
+            if !port.dtr() {
+                queue.clear();
+            }
+            queue.peek(&mut buf)
+        });
+        if count == 0 {
+            return;
+        }
+
+        // The logger only appends, the peeked bytes stay at the front
+        let Ok(written) = port.write(&buf[..count]) else {
+            return;
+        };
+        critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).consume(written));
+        if written < count {
+            return;
+        }
+    }
+}
+
+/// Number of frames dropped because the queue was full.
+pub fn dropped() -> u32 {
+    critical_section::with(|cs| QUEUE.borrow_ref(cs).dropped())
+}
+
+#[cfg(feature = "defmt-usb")]
+mod global {
+    use core::sync::atomic::AtomicBool;
+    use core::sync::atomic::Ordering;
+
+    use super::QUEUE;
+
+    #[defmt::global_logger]
+    struct Logger;
+
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    static mut RESTORE_STATE: critical_section::RestoreState =
+        critical_section::RestoreState::invalid();
+    static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+    // The frame is written with the interrupts off, from `acquire` to `release`
+    unsafe impl defmt::Logger for Logger {
+        fn acquire() {
+            let restore = unsafe { critical_section::acquire() };
+            if TAKEN.load(Ordering::Relaxed) {
+                panic!("defmt logger taken reentrantly")
+            }
+            TAKEN.store(true, Ordering::Relaxed);
+
+            critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).start_frame());
+            unsafe {
+                RESTORE_STATE = restore;
+                (*core::ptr::addr_of_mut!(ENCODER)).start_frame(write);
+            }
+        }
+
+        unsafe fn flush() {
+            // Flushing would wait for the host, the queue is sent by `usb_log::flush`
+        }
+
+        unsafe fn release() {
+            (*core::ptr::addr_of_mut!(ENCODER)).end_frame(write);
+            critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).end_frame());
+
+            TAKEN.store(false, Ordering::Relaxed);
+            critical_section::release(RESTORE_STATE);
+        }
+
+        unsafe fn write(bytes: &[u8]) {
+            (*core::ptr::addr_of_mut!(ENCODER)).write(bytes, write);
+        }
+    }
+
+    fn write(bytes: &[u8]) {
+        critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).write(bytes));
+    }
+}
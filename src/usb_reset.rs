@@ -0,0 +1,142 @@
+//! Rebooting into BOOTSEL from the host, no button pressing.
+//!
+//! Two conventions are understood:
+//!
+//! * the 1200-baud touch: the host opens the serial port at 1200 baud and
+//!   closes it (`stty -F /dev/ttyACM0 1200`), see [`BaudTouch`];
+//! * the vendor reset interface of the Pico SDK, which `picotool reboot -f -u`
+//!   and `picotool load -f` use, see [`ResetInterface`].
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+mod interface;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use interface::reset;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub use interface::ResetInterface;
+
+/// Interface class, subclass and protocol picotool looks for.
+pub const INTERFACE_CLASS: u8 = 0xff;
+pub const INTERFACE_SUB_CLASS: u8 = 0x00;
+pub const INTERFACE_PROTOCOL: u8 = 0x01;
+
+const REQUEST_BOOTSEL: u8 = 0x01;
+const REQUEST_FLASH: u8 = 0x02;
+
+/// The baud rate that means "reboot into BOOTSEL".
+pub const TOUCH_BAUD_RATE: u32 = 1200;
+
+/// Was the port set to 1200 baud and then closed?
+pub fn is_baud_touch(baud_rate: u32, dtr: bool) -> bool {
+    baud_rate == TOUCH_BAUD_RATE && !dtr
+}
+
+/// Tells when to reboot for the 1200-baud touch.
+///
+/// The request that closed the port has to be acknowledged first, so as
+/// for [`ResetInterface`] the reboot comes at the next poll of the device
+/// with events.
+#[derive(Debug, Default)]
+pub struct BaudTouch {
+    touched: bool,
+}
+
+impl BaudTouch {
+    pub const fn new() -> Self {
+        Self { touched: false }
+    }
+
+    /// Call after each poll of the device with what the poll returned and
+    /// the state of the port, returns whether to reboot now.
+    pub fn poll(&mut self, polled: bool, baud_rate: u32, dtr: bool) -> bool {
+        if !polled {
+            return false;
+        }
+        let touched = core::mem::replace(&mut self.touched, is_baud_touch(baud_rate, dtr));
+        touched && self.touched
+    }
+}
+
+/// What the host asked the reset interface for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetRequest {
+    /// Reboot into the USB bootloader, the masks go to `reset_to_usb_boot`.
+    Bootsel {
+        gpio_activity_pin_mask: u32,
+        disable_interface_mask: u32,
+    },
+    /// Reboot and run the firmware from the flash again.
+    Flash,
+}
+
+impl ResetRequest {
+    /// Decodes the vendor request, `value` is laid out as in the Pico SDK:
+    /// bit 8 enables the activity LED on the pin in bits 9 and up, bits
+    /// 0-6 disable the bootloader interfaces (mass storage, picoboot).
+    pub fn decode(request: u8, value: u16) -> Option<Self> {
+        match request {
+            REQUEST_BOOTSEL => {
+                let gpio_activity_pin_mask = if value & 0x100 != 0 {
+                    1u32.checked_shl(u32::from(value >> 9)).unwrap_or(0)
+                } else {
+                    0
+                };
+                Some(Self::Bootsel {
+                    gpio_activity_pin_mask,
+                    disable_interface_mask: u32::from(value & 0x7f),
+                })
+            }
+            REQUEST_FLASH => Some(Self::Flash),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_requests() {
+        assert_eq!(
+            ResetRequest::decode(0x01, 0),
+            Some(ResetRequest::Bootsel {
+                gpio_activity_pin_mask: 0,
+                disable_interface_mask: 0,
+            })
+        );
+        assert_eq!(
+            ResetRequest::decode(0x01, (25 << 9) | 0x100 | 0x02),
+            Some(ResetRequest::Bootsel {
+                gpio_activity_pin_mask: 1 << 25,
+                disable_interface_mask: 0x02,
+            })
+        );
+        assert_eq!(ResetRequest::decode(0x02, 0), Some(ResetRequest::Flash));
+        assert_eq!(ResetRequest::decode(0x03, 0), None);
+    }
+
+    #[test]
+    fn detects_the_touch() {
+        assert!(is_baud_touch(1200, false));
+        assert!(!is_baud_touch(1200, true));
+        assert!(!is_baud_touch(115200, false));
+    }
+
+    #[test]
+    fn reboots_at_the_poll_after_the_touch() {
+        let mut touch = BaudTouch::new();
+        assert!(!touch.poll(true, 115200, true));
+        assert!(!touch.poll(true, 1200, true));
+        // Closed, the status stage is still to come
+        assert!(!touch.poll(true, 1200, false));
+        assert!(!touch.poll(false, 1200, false));
+        assert!(touch.poll(true, 1200, false));
+
+        // Opened again in between
+        let mut touch = BaudTouch::new();
+        assert!(!touch.poll(true, 1200, false));
+        assert!(!touch.poll(true, 1200, true));
+        assert!(!touch.poll(true, 1200, true));
+    }
+}
@@ -0,0 +1,100 @@
+//! The vendor reset interface as a USB class.
+
+use rp2040_hal as hal;
+use usb_device::class_prelude::*;
+use usb_device::control::Recipient;
+use usb_device::control::RequestType;
+
+use super::ResetRequest;
+use super::INTERFACE_CLASS;
+use super::INTERFACE_PROTOCOL;
+use super::INTERFACE_SUB_CLASS;
+
+/// An interface without endpoints that only takes the reset requests.
+///
+/// The reset itself is up to the firmware: the request has to be
+/// acknowledged first, so [`ResetInterface::take_request`] hands it out
+/// after the next poll of the device.
+pub struct ResetInterface {
+    interface: InterfaceNumber,
+    /// Accepted during the current poll, the status stage still to come.
+    accepted: Option<ResetRequest>,
+    /// Accepted during the previous poll.
+    acknowledged: Option<ResetRequest>,
+    request: Option<ResetRequest>,
+}
+
+impl ResetInterface {
+    pub fn new<B: UsbBus>(alloc: &UsbBusAllocator<B>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            accepted: None,
+            acknowledged: None,
+            request: None,
+        }
+    }
+
+    pub fn take_request(&mut self) -> Option<ResetRequest> {
+        self.request.take()
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for ResetInterface {
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        writer.interface(
+            self.interface,
+            INTERFACE_CLASS,
+            INTERFACE_SUB_CLASS,
+            INTERFACE_PROTOCOL,
+        )
+    }
+
+    /// Called at the end of each poll with events, the one that accepted
+    /// the request included.
+    fn poll(&mut self) {
+        if let Some(request) = self.acknowledged.take() {
+            self.request = Some(request);
+        }
+        self.acknowledged = self.accepted.take();
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let req = xfer.request();
+        if req.request_type != RequestType::Vendor
+            || req.recipient != Recipient::Interface
+            || req.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        match ResetRequest::decode(req.request, req.value) {
+            Some(request) => {
+                self.accepted = Some(request);
+                xfer.accept().ok();
+            }
+            None => {
+                xfer.reject().ok();
+            }
+        }
+    }
+}
+
+/// Performs the reset.
+pub fn reset(request: ResetRequest) -> ! {
+    match request {
+        ResetRequest::Bootsel {
+            gpio_activity_pin_mask,
+            disable_interface_mask,
+        } => {
+            hal::rom_data::reset_to_usb_boot(gpio_activity_pin_mask, disable_interface_mask);
+            // Not reached, the boot ROM takes over
+            loop {
+                cortex_m::asm::wfi();
+            }
+        }
+        ResetRequest::Flash => cortex_m::peripheral::SCB::sys_reset(),
+    }
+}
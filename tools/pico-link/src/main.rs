@@ -0,0 +1,206 @@
+//! Talks to a board running the `e14-link` example, over its USB serial
+//! port or a UART adapter on UART1.
+//!
+//! ```text
+//! pico-link <port> [listen]               # shows what the board sends
+//! pico-link <port> ping [<count>]         # measures the round trip
+//! pico-link <port> led on|off
+//! pico-link <port> text <words>...        # shows up in the board log
+//! ```
+//!
+//! The frames are those of `pico_bites::link`, the same code runs on both
+//! ends.
+
+use std::env;
+use std::error::Error;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use pico_bites::link;
+use pico_bites::link::Event;
+use pico_bites::link::Link;
+use pico_bites::link::Message;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// How long a read waits for bytes, the link needs polling in between.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// How long to wait for the pong to a ping.
+const PONG_TIMEOUT: Duration = Duration::from_secs(2);
+/// The baud rate of the UART, meaningless for a USB serial port.
+const BAUD_RATE: u32 = 115_200;
+
+const USAGE: &str =
+    "usage: pico-link <port> [listen | ping [<count>] | led on|off | text <words>...]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((port, command)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let command: Vec<&str> = command.iter().map(String::as_str).collect();
+    match run(port, &command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(port: &str, command: &[&str]) -> Result<()> {
+    let message = match command {
+        [] | ["listen"] => return Session::open(port)?.listen(),
+        ["ping"] => return ping(port, 1),
+        ["ping", count] => return ping(port, count.parse().map_err(|_| USAGE)?),
+        ["led", "on"] => Message::Led(true),
+        ["led", "off"] => Message::Led(false),
+        ["text", words @ ..] if !words.is_empty() => {
+            let text = words.join(" ");
+            return Session::open(port)?.deliver(&Message::Text(&text));
+        }
+        _ => return Err(USAGE.into()),
+    };
+    Session::open(port)?.deliver(&message)
+}
+
+fn ping(port: &str, count: u32) -> Result<()> {
+    let mut session = Session::open(port)?;
+    for number in 0..count {
+        let sent_at = Instant::now();
+        session.send(&Message::Ping(number))?;
+        // The pong may come before the ACK if the first ACK got lost
+        let (mut acked, mut ponged) = (false, false);
+        let answered = session.wait(Some(sent_at + PONG_TIMEOUT), |event| {
+            acked |= *event == Event::Acked;
+            ponged |= *event == Event::Message(Message::Pong(number));
+            acked && ponged
+        })?;
+        if !answered {
+            return Err(format!("no pong for ping {number}").into());
+        }
+        println!("Pong {number} in {:?}", sent_at.elapsed());
+    }
+    Ok(())
+}
+
+/// The serial port and the link over it.
+struct Session {
+    port: Box<dyn serialport::SerialPort>,
+    link: Link,
+    start: Instant,
+}
+
+impl Session {
+    fn open(path: &str) -> Result<Self> {
+        let port = serialport::new(path, BAUD_RATE)
+            .timeout(POLL_INTERVAL)
+            .open()
+            .map_err(|e| format!("{path}: {e}"))?;
+        // Another session each run, so the board takes the first messages
+        // for new ones
+        let session = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u32)
+            ^ std::process::id();
+        Ok(Self {
+            port,
+            link: Link::new(link::Config::default(), session),
+            start: Instant::now(),
+        })
+    }
+
+    fn now_us(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+
+    /// Sends the message, [`Session::wait`] takes care of the rest.
+    fn send(&mut self, message: &Message) -> Result<()> {
+        let mut frames = Vec::new();
+        self.link
+            .send(message, self.now_us(), |frame| {
+                frames.extend_from_slice(frame)
+            })
+            .map_err(|e| format!("{message:?}: {e:?}"))?;
+        self.port.write_all(&frames)?;
+        Ok(())
+    }
+
+    /// Sends the message and waits for its ACK.
+    fn deliver(&mut self, message: &Message) -> Result<()> {
+        self.send(message)?;
+        self.wait(None, |event| *event == Event::Acked)?;
+        Ok(())
+    }
+
+    /// Shows the messages until interrupted.
+    fn listen(&mut self) -> Result<()> {
+        self.wait(None, |_| false)?;
+        Ok(())
+    }
+
+    /// Shows the messages from the board until `done` takes an event or
+    /// the deadline passes, returns whether `done` took one. Fails when
+    /// the message sent last is given up.
+    fn wait(
+        &mut self,
+        deadline: Option<Instant>,
+        mut done: impl FnMut(&Event) -> bool,
+    ) -> Result<bool> {
+        let mut buf = [0u8; 256];
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(false);
+            }
+            let len = match self.port.read(&mut buf) {
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => 0,
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut frames = Vec::new();
+            let mut finished = false;
+            for &byte in &buf[..len] {
+                if let Some(event) = self
+                    .link
+                    .push(byte, |frame| frames.extend_from_slice(frame))
+                {
+                    show(&event);
+                    finished |= done(&event);
+                }
+            }
+            let now_us = self.now_us();
+            let failed = self
+                .link
+                .poll(now_us, |frame| frames.extend_from_slice(frame))
+                .is_some_and(|event| event == Event::Failed);
+            // The ACKs go out even when done
+            self.port.write_all(&frames)?;
+
+            if finished {
+                return Ok(true);
+            }
+            if failed {
+                return Err("no ACK from the board".into());
+            }
+        }
+    }
+}
+
+fn show(event: &Event) {
+    match event {
+        Event::Message(Message::Temperature(tenths)) => {
+            println!("Temperature {:.1} °C", *tenths as f32 / 10.0)
+        }
+        Event::Message(Message::Text(text)) => println!("Text {text:?}"),
+        Event::Message(message) => println!("{message:?}"),
+        Event::Dropped(e) => eprintln!("Dropped a frame: {e:?}"),
+        Event::Acked | Event::Failed => {}
+    }
+}
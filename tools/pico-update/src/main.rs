@@ -0,0 +1,190 @@
+//! Sends a firmware image to a board running the `e12-usb-update` example.
+//!
+//! ```text
+//! pico-update <port>                      # shows the slots
+//! pico-update <port> <image.bin>...       # updates the board
+//! ```
+//!
+//! The images are raw binaries from the start of the slot, boot2 included:
+//!
+//! ```text
+//! cargo objcopy --release --example e12-usb-update -- -O binary slot-a.bin
+//! PICO_BITES_SLOT=b cargo objcopy --release --example e12-usb-update -- -O binary slot-b.bin
+//! ```
+//!
+//! Of the images given, the one linked for slot B is sent, slot A takes no
+//! updates (see `pico_bites::ota::protocol`). A board running slot B falls
+//! back to slot A first: run the uploader again once it is back.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use pico_bites::ota;
+use pico_bites::ota::protocol;
+use pico_bites::ota::protocol::Command;
+use pico_bites::ota::protocol::Info;
+use pico_bites::ota::protocol::Status;
+use pico_bites::ota::Slot;
+use sha2::Digest;
+use sha2::Sha256;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Long enough for the board to erase a sector, or to hash the whole slot.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Attempts per request, when the response is lost or damaged.
+const ATTEMPTS: usize = 3;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((port, images)) = args.split_first() else {
+        eprintln!("usage: pico-update <port> [<image.bin>...]");
+        return ExitCode::from(2);
+    };
+    match run(port, images) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(port: &str, images: &[String]) -> Result<()> {
+    let mut link = Link::open(port)?;
+    let info =
+        Info::from_bytes(&link.request(Command::Info, &[])?).ok_or("malformed response to Info")?;
+    let target = info.target();
+    println!(
+        "Running from slot {}, slot A at {:#010x}, slot B at {:#010x}, {} KiB each",
+        info.running,
+        info.slot_a,
+        info.slot_b,
+        info.slot_len / 1024
+    );
+    if images.is_empty() {
+        return Ok(());
+    }
+    if info.running == Slot::B {
+        link.request(Command::Fallback, &[])?;
+        println!("Slot A takes no updates, rebooting into it: run again once the board is back");
+        return Ok(());
+    }
+
+    let mut image = None;
+    for path in images {
+        let data = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+        if is_linked_for(&data, &info) {
+            image = Some((path, data));
+            break;
+        }
+    }
+    let (path, image) = image.ok_or(format!("none of the images is linked for slot {target}"))?;
+    println!("Sending {path}, {} bytes, to slot {target}", image.len());
+
+    let mut args = (image.len() as u32).to_le_bytes().to_vec();
+    args.extend_from_slice(&Sha256::digest(&image));
+    link.request(Command::Begin, &args)?;
+
+    for (i, chunk) in image.chunks(protocol::MAX_DATA).enumerate() {
+        let offset = i * protocol::MAX_DATA;
+        let mut args = (offset as u32).to_le_bytes().to_vec();
+        args.extend_from_slice(chunk);
+        link.request(Command::Data, &args)?;
+        print!("\r{} of {} bytes", offset + chunk.len(), image.len());
+        std::io::stdout().flush()?;
+    }
+    println!();
+
+    link.request(Command::Finish, &[])?;
+    println!("Verified, rebooting into slot {target}");
+    link.request(Command::Reboot, &[])?;
+    println!(
+        "The new image has {} seconds to confirm itself",
+        ota::TRIAL_TIMEOUT_US / 1_000_000
+    );
+    Ok(())
+}
+
+/// Whether the vector table of the image points into the slot the update
+/// goes to.
+fn is_linked_for(image: &[u8], info: &Info) -> bool {
+    let offset = ota::VECTOR_TABLE_OFFSET;
+    let Some(vectors) = image.get(offset..offset + 8) else {
+        return false;
+    };
+    let stack = u32::from_le_bytes(vectors[..4].try_into().unwrap());
+    let reset = u32::from_le_bytes(vectors[4..].try_into().unwrap());
+    let base = info.base(info.target());
+    image.len() <= info.slot_len as usize
+        && ota::check_vector_table(stack, reset, base, info.slot_len as usize)
+}
+
+/// The serial port and the frames going through it.
+struct Link {
+    port: Box<dyn serialport::SerialPort>,
+    decoder: protocol::Decoder,
+}
+
+impl Link {
+    fn open(path: &str) -> Result<Self> {
+        // The baud rate means nothing to a USB serial port
+        let port = serialport::new(path, 115_200)
+            .timeout(TIMEOUT)
+            .open()
+            .map_err(|e| format!("{path}: {e}"))?;
+        Ok(Self {
+            port,
+            decoder: protocol::Decoder::new(),
+        })
+    }
+
+    /// Sends the request and returns the results of a successful one,
+    /// retrying when the frames get lost or damaged on the way.
+    fn request(&mut self, command: Command, args: &[u8]) -> Result<Vec<u8>> {
+        let mut body = vec![command as u8];
+        body.extend_from_slice(args);
+        let mut frame = [0u8; protocol::MAX_FRAME];
+        let len = protocol::encode(&body, &mut frame);
+
+        let mut last_error = String::new();
+        for _ in 0..ATTEMPTS {
+            self.port.write_all(&frame[..len])?;
+            let response = match self.response() {
+                Ok(response) => response,
+                Err(e) => {
+                    last_error = e.to_string();
+                    continue;
+                }
+            };
+            let Some((&status, results)) = response.split_first() else {
+                return Err(format!("{command:?}: empty response").into());
+            };
+            match Status::from_u8(status) {
+                Some(Status::Ok) => return Ok(results.to_vec()),
+                Some(Status::BadFrame) => last_error = "damaged request".into(),
+                Some(status) => return Err(format!("{command:?}: {status:?}").into()),
+                None => return Err(format!("{command:?}: unknown status {status}").into()),
+            }
+        }
+        Err(format!("{command:?}: {last_error}").into())
+    }
+
+    /// Reads the next response body.
+    fn response(&mut self) -> Result<Vec<u8>> {
+        let mut byte = [0u8];
+        loop {
+            self.port.read_exact(&mut byte)?;
+            match self.decoder.push(byte[0]) {
+                None => {}
+                Some(Ok(body)) => return Ok(body.to_vec()),
+                Some(Err(e)) => return Err(format!("damaged response: {e:?}").into()),
+            }
+        }
+    }
+}